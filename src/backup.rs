@@ -0,0 +1,295 @@
+use crate::storage;
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// The layout of the files in the data directory. Bump it when one of them
+/// changes in a way an older br cannot read, and bring older backups up to
+/// date in `restore`.
+pub const STATE_VERSION: u32 = 1;
+
+// Left out of a backup: the article cache is cleared at every start anyway,
+// and the others are leftovers of writes in progress.
+const SKIPPED: &[&str] = &["content", ".write-test"];
+
+/// `manifest.json`, the first file of a backup.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub app: String, // "blogreader", to tell a backup from any other tarball
+    pub version: String, // of the br that made it
+    pub state_version: u32,
+    pub created: DateTime<Utc>,
+    pub config: bool, // whether config/config.toml is in it
+}
+
+/// `br backup create`: writes `config_path` and everything under `data_dir`
+/// but the article cache to a gzipped tarball at `output`, next to a
+/// manifest. Returns how many files went in.
+///
+/// The state files are replaced by renaming, so a running br does not leave
+/// half-written ones in the backup.
+pub fn create(config_path: &Path, data_dir: &Path, output: &Path) -> Result<usize, Box<dyn Error>> {
+    let has_config = config_path.is_file();
+    if !has_config && !data_dir.is_dir() {
+        return Err(format!("nothing to back up: neither {} nor {} exists", config_path.display(), data_dir.display()).into());
+    }
+    let manifest = Manifest {
+        app: "blogreader".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        state_version: STATE_VERSION,
+        created: Utc::now(),
+        config: has_config,
+    };
+    // Written next to `output` and renamed, so a failure leaves no partial backup.
+    storage::replace_with(output, |tmp| write_archive(&manifest, config_path, data_dir, tmp))
+}
+
+fn write_archive(manifest: &Manifest, config_path: &Path, data_dir: &Path, path: &Path) -> Result<usize, Box<dyn Error>> {
+    let file = File::create(path).map_err(|e| format!("writing {}: {}", path.display(), e))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created.timestamp().max(0) as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, "manifest.json", json.as_slice())?;
+
+    let mut files = 0;
+    if manifest.config {
+        tar.append_path_with_name(config_path, "config/config.toml")
+            .map_err(|e| format!("reading {}: {}", config_path.display(), e))?;
+        files += 1;
+    }
+    if data_dir.is_dir() {
+        files += append_dir(&mut tar, data_dir, Path::new("data"))?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(files)
+}
+
+fn append_dir<W: std::io::Write>(tar: &mut tar::Builder<W>, dir: &Path, name: &Path) -> Result<usize, Box<dyn Error>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+        .collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut files = 0;
+    for entry in entries {
+        let file_name = entry.file_name();
+        let path = entry.path();
+        if SKIPPED.iter().any(|skipped| file_name == *skipped) || path.extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        if path.is_dir() {
+            files += append_dir(tar, &path, &name.join(&file_name))?;
+        } else {
+            tar.append_path_with_name(&path, name.join(&file_name))
+                .map_err(|e| format!("reading {}: {}", path.display(), e))?;
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+/// `br backup restore`: puts the config and data directory of the backup at
+/// `archive` in place of `config_path` and `data_dir`.
+///
+/// Existing state is only replaced with `force`; otherwise restoring on a
+/// machine that already has some is refused, rather than mixing two
+/// machines' read and bookmarked items. The backup is unpacked and checked
+/// next to `data_dir` first, then swapped in with renames, so a bad or
+/// truncated backup leaves everything as it was.
+pub fn restore(config_path: &Path, data_dir: &Path, archive: &Path, force: bool) -> Result<Manifest, Box<dyn Error>> {
+    let parent = data_dir.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).map_err(|e| format!("creating {}: {}", parent.display(), e))?;
+    let staging = parent.join(format!(".br-restore-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    let result = unpack(archive, &staging).and_then(|manifest| {
+        let staged_config = staging.join("config").join("config.toml");
+        if !force {
+            check_empty(config_path, data_dir, manifest.config.then_some(staged_config.as_path()))?;
+        }
+        let staged_data = staging.join("data");
+        fs::create_dir_all(&staged_data)?;
+        swap_in(&staged_data, data_dir)?;
+        if manifest.config {
+            copy_in(&staged_config, config_path)?;
+        }
+        Ok(manifest)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// Unpacks `archive` into `dir` and reads its manifest. tar leaves out entries
+// that would land outside `dir`.
+fn unpack(archive: &Path, dir: &Path) -> Result<Manifest, Box<dyn Error>> {
+    let file = File::open(archive).map_err(|e| format!("reading {}: {}", archive.display(), e))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(dir)
+        .map_err(|e| format!("unpacking {}: {}", archive.display(), e))?;
+    let manifest: Manifest = fs::read_to_string(dir.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .filter(|manifest: &Manifest| manifest.app == "blogreader")
+        .ok_or_else(|| format!("{} is not a br backup (no manifest.json)", archive.display()))?;
+    if manifest.state_version > STATE_VERSION {
+        return Err(format!(
+            "{} was made by br {} (state version {}); this br {} reads up to version {}, upgrade it first",
+            archive.display(),
+            manifest.version,
+            manifest.state_version,
+            env!("CARGO_PKG_VERSION"),
+            STATE_VERSION
+        )
+        .into());
+    }
+    Ok(manifest)
+}
+
+// Refuses when there is state a restore would throw away: a data directory
+// with more than the log and the article cache, or a config that differs
+// from the backup's.
+fn check_empty(config_path: &Path, data_dir: &Path, staged_config: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let has_state = fs::read_dir(data_dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let name = entry.file_name();
+            name != "br.log" && !SKIPPED.iter().any(|skipped| name == *skipped)
+        })
+    });
+    if has_state {
+        return Err(format!("{} already holds state; pass --force to replace it with the backup", data_dir.display()).into());
+    }
+    if let Some(staged_config) = staged_config
+        && let Ok(current) = fs::read(config_path)
+        && fs::read(staged_config).ok().as_ref() != Some(&current)
+    {
+        return Err(format!("{} already exists and differs from the backup's; pass --force to replace it", config_path.display()).into());
+    }
+    Ok(())
+}
+
+// Replaces `target` with `staged`, a directory on the same filesystem. The
+// old one is only removed once the new one is in place.
+fn swap_in(staged: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    let old = PathBuf::from(format!("{}.old-{}", target.display(), std::process::id()));
+    let had_target = target.exists();
+    if had_target {
+        fs::rename(target, &old).map_err(|e| format!("moving {} aside: {}", target.display(), e))?;
+    }
+    if let Err(e) = fs::rename(staged, target) {
+        if had_target {
+            let _ = fs::rename(&old, target);
+        }
+        return Err(format!("replacing {}: {}", target.display(), e).into());
+    }
+    if had_target {
+        let _ = fs::remove_dir_all(&old);
+    }
+    Ok(())
+}
+
+// The config may be on another filesystem than the staging directory, so it
+// is copied next to `target` and renamed over it.
+fn copy_in(staged: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("creating {}: {}", dir.display(), e))?;
+    }
+    storage::replace_with(target, |tmp| fs::copy(staged, tmp).map(|_| ()))
+        .map_err(|e| format!("writing {}: {}", target.display(), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    // A config and a data directory with some state, the article cache and
+    // the leftover of an interrupted write.
+    fn machine(dir: &Path) -> (PathBuf, PathBuf) {
+        let (config, data) = (dir.join("config.toml"), dir.join("data"));
+        fs::create_dir_all(data.join("content")).unwrap();
+        fs::write(&config, "[[feeds]]\nname = \"A\"\nurl = \"https://a.example/feed\"\n").unwrap();
+        fs::write(data.join("read.json"), "[\"https://a.example/1\"]").unwrap();
+        fs::write(data.join("content").join("page.html"), "<p>cached</p>").unwrap();
+        fs::write(data.join(".read.json.1-0.tmp"), "[").unwrap();
+        (config, data)
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn a_backup_restores_on_a_new_machine() {
+        let old = temp_dir("backup-old");
+        let (config, data) = machine(&old);
+        let archive = old.join("br.tar.gz");
+        assert_eq!(create(&config, &data, &archive).unwrap(), 2);
+        assert_eq!(names(&old), ["br.tar.gz", "config.toml", "data"]);
+
+        let new = temp_dir("backup-new");
+        let manifest = restore(&new.join("config.toml"), &new.join("data"), &archive, false).unwrap();
+        assert_eq!(manifest.state_version, STATE_VERSION);
+        assert!(manifest.config);
+        assert_eq!(fs::read(new.join("config.toml")).unwrap(), fs::read(&config).unwrap());
+        assert_eq!(names(&new.join("data")), ["read.json"]);
+        assert_eq!(names(&new), ["config.toml", "data"]);
+    }
+
+    #[test]
+    fn restoring_over_state_needs_force() {
+        let dir = temp_dir("backup-force");
+        let (config, data) = machine(&dir);
+        let archive = dir.join("br.tar.gz");
+        create(&config, &data, &archive).unwrap();
+        fs::write(data.join("read.json"), "[]").unwrap();
+        fs::write(data.join("bookmarks.json"), "[]").unwrap();
+
+        let error = restore(&config, &data, &archive, false).unwrap_err();
+        assert!(error.to_string().contains("--force"), "{}", error);
+        assert_eq!(fs::read_to_string(data.join("read.json")).unwrap(), "[]");
+
+        restore(&config, &data, &archive, true).unwrap();
+        assert_eq!(fs::read_to_string(data.join("read.json")).unwrap(), "[\"https://a.example/1\"]");
+        assert!(!data.join("bookmarks.json").exists());
+        assert_eq!(names(&dir), ["br.tar.gz", "config.toml", "data"]);
+    }
+
+    #[test]
+    fn backups_from_a_newer_br_or_elsewhere_are_refused() {
+        let dir = temp_dir("backup-refused");
+        let manifest = Manifest {
+            app: "blogreader".to_string(),
+            version: "99.0.0".to_string(),
+            state_version: STATE_VERSION + 1,
+            created: Utc::now(),
+            config: false,
+        };
+        let newer = dir.join("newer.tar.gz");
+        write_archive(&manifest, &dir.join("none.toml"), &dir.join("none"), &newer).unwrap();
+        let error = restore(&dir.join("config.toml"), &dir.join("data"), &newer, false).unwrap_err();
+        assert!(error.to_string().contains("upgrade it first"), "{}", error);
+
+        let other = dir.join("other.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(File::create(&other).unwrap(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_cksum();
+        tar.append_data(&mut header, "notes.txt", &b"hi"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+        let error = restore(&dir.join("config.toml"), &dir.join("data"), &other, false).unwrap_err();
+        assert!(error.to_string().contains("not a br backup"), "{}", error);
+
+        assert!(!dir.join("data").exists());
+        assert_eq!(names(&dir), ["newer.tar.gz", "other.tar.gz"]);
+    }
+}
@@ -0,0 +1,39 @@
+/// How many categories are kept per item. Some feeds tag every entry with
+/// dozens; the rest are only counted.
+pub const MAX_STORED: usize = 10;
+
+/// How many categories are shown as chips after the title.
+pub const MAX_CHIPS: usize = 3;
+
+/// The categories a feed gives an entry, lowercased, without duplicates, in
+/// feed order. Inner whitespace becomes "-" so each one is a single word
+/// for `category:` searches.
+#[derive(Debug)]
+pub struct Categories {
+    pub kept: Vec<String>,
+    pub total: usize, // distinct categories before capping
+}
+
+impl Categories {
+    pub fn new<'a>(raw: impl IntoIterator<Item = &'a str>) -> Categories {
+        let mut kept = Vec::new();
+        let mut seen = Vec::new();
+        for category in raw {
+            let category = category.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase();
+            if category.is_empty() || seen.contains(&category) {
+                continue;
+            }
+            if kept.len() < MAX_STORED {
+                kept.push(category.clone());
+            }
+            seen.push(category);
+        }
+        Categories { kept, total: seen.len() }
+    }
+}
+
+/// The chips drawn after a title, e.g. " [rust] [releases]", as separate
+/// pieces so a click can be matched to one of them.
+pub fn chips(categories: &[String]) -> Vec<String> {
+    categories.iter().take(MAX_CHIPS).map(|category| format!(" [{}]", category)).collect()
+}
@@ -0,0 +1,152 @@
+use crate::control::Request;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// rss/atom blogreader for terminals
+#[derive(Parser)]
+#[command(name = "br", version)]
+pub struct Cli {
+    /// Read this config file instead of ~/.config/br/config.toml.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Subscribe to a feed, finding it from a homepage URL if needed.
+    ///
+    /// Prints one tab-separated line: "added", "exists" or "not-found",
+    /// then the feed name and URL. Exits with 0, 2 or 3 respectively, and 1
+    /// for any other error.
+    AddFeed {
+        url: String,
+        /// Name to list the feed under; defaults to the feed's title.
+        #[arg(long)]
+        name: Option<String>,
+        /// Group to file the feed under.
+        #[arg(long)]
+        group: Option<String>,
+    },
+    /// Send a command to a running br over its control socket.
+    ///
+    /// Prints the JSON response. Needs `control_socket = true` in
+    /// config.toml.
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+    /// Print the subscriptions as OPML 2.0, e.g. `br export --opml > feeds.opml`.
+    ///
+    /// Manual sites are listed as links in a "Manual" folder.
+    Export {
+        /// Write OPML; currently the only format, so it may be left out.
+        #[arg(long)]
+        opml: bool,
+        /// Write to this file instead, and print its path.
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Subscribe to the feeds of an OPML file, e.g. one exported from
+    /// another reader: `br import --opml feeds.opml`.
+    ///
+    /// Feeds whose URL the config already lists are skipped.
+    Import {
+        #[arg(long, value_name = "PATH")]
+        opml: PathBuf,
+    },
+    /// Print the end of the log of what br showed in its info panel and as
+    /// [ERROR] rows, with the time of each.
+    Log {
+        /// How many messages to print.
+        #[arg(long, default_value_t = 50, value_name = "N")]
+        tail: usize,
+        /// Print only the errors.
+        #[arg(long)]
+        errors_only: bool,
+    },
+    /// Copy everything br keeps, config and state, to or from a single file,
+    /// e.g. to move to another machine.
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Inspect the configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print config.toml.
+    Show {
+        /// Print every top-level option after defaults and BR_* environment
+        /// overrides, noting which of them set each value.
+        #[arg(long)]
+        effective: bool,
+        #[arg(long, value_enum, default_value_t = Format::Toml)]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// Write config.toml and the data directory (read, seen and bookmarked
+    /// items, notes, feed health, history, stats and the log) to a .tar.gz.
+    ///
+    /// The cached full articles are left out.
+    Create {
+        #[arg(value_name = "PATH")]
+        output: PathBuf,
+    },
+    /// Put the config and state of a backup in place.
+    ///
+    /// Refused if there is state already, e.g. from using br on this machine
+    /// before, unless --force is given. The config is written to --config if
+    /// given.
+    Restore {
+        #[arg(value_name = "PATH")]
+        archive: PathBuf,
+        /// Replace the existing config and state.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum CtlCommand {
+    /// Check for updates, like pressing 'u'.
+    Refresh,
+    /// Subscribe to a feed; it is fetched with the next refresh.
+    AddFeed {
+        url: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        group: Option<String>,
+    },
+    /// List the unread items.
+    ListUnread,
+    /// Print the unread count, then a line for every event until interrupted.
+    Watch,
+}
+
+impl CtlCommand {
+    pub fn request(self) -> Request {
+        match self {
+            CtlCommand::Refresh => Request::Refresh,
+            CtlCommand::AddFeed { url, name, group } => Request::AddFeed { url, name, group },
+            CtlCommand::ListUnread => Request::ListUnread,
+            CtlCommand::Watch => Request::Watch,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use std::time::Instant;
+
+/// Source of the current time. Everything that depends on "now" reads it
+/// through this so time-based behavior can be driven deterministically.
+pub trait Clock: Send + Sync {
+    fn now_utc(&self) -> DateTime<Utc>;
+    fn now_instant(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests.
+#[cfg(test)]
+pub struct TestClock {
+    start: (DateTime<Utc>, Instant),
+    offset: std::sync::Mutex<chrono::Duration>, // from start
+}
+
+#[cfg(test)]
+impl TestClock {
+    pub fn new(now: DateTime<Utc>) -> TestClock {
+        TestClock { start: (now, Instant::now()), offset: std::sync::Mutex::new(chrono::Duration::zero()) }
+    }
+
+    /// Moves the clock to `now`, which may be before the current time.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.offset.lock().unwrap() = now - self.start.0;
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for TestClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.start.0 + *self.offset.lock().unwrap()
+    }
+
+    fn now_instant(&self) -> Instant {
+        let offset = *self.offset.lock().unwrap();
+        match offset.to_std() {
+            Ok(ahead) => self.start.1 + ahead,
+            Err(_) => self.start.1 - (-offset).to_std().unwrap(),
+        }
+    }
+}
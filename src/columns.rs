@@ -0,0 +1,53 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The source column never takes more than this share of the list width.
+const MAX_SHARE: usize = 4;
+
+/// Widths of the date and source columns of feed items, fitted every frame
+/// to what is listed so short names don't leave a gap and long ones are only
+/// cut past `max_source_width`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Columns {
+    pub date: usize,
+    pub source: usize,
+}
+
+impl Columns {
+    /// `dates` and `sources` are the columns of every listed feed item.
+    pub fn fit<'a>(
+        dates: impl Iterator<Item = &'a str>,
+        sources: impl Iterator<Item = &'a str>,
+        max_source_width: usize,
+        list_width: usize,
+    ) -> Columns {
+        let longest = sources.map(UnicodeWidthStr::width).max().unwrap_or(0);
+        Columns {
+            date: dates.map(UnicodeWidthStr::width).max().unwrap_or(0),
+            source: longest.min(max_source_width).min(list_width / MAX_SHARE),
+        }
+    }
+}
+
+/// `text` padded to `width` display columns, or cut to it ending with "…".
+pub fn pad(text: &str, width: usize) -> String {
+    let used = text.width();
+    if used <= width {
+        return format!("{}{}", text, " ".repeat(width - used));
+    }
+    let mut cut = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        cut.push(c);
+        used += w;
+    }
+    if width > 0 {
+        cut.push('…');
+        used += 1;
+    }
+    cut.push_str(&" ".repeat(width - used));
+    cut
+}
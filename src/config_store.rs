@@ -0,0 +1,229 @@
+use crate::storage;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+// Changes to config.toml edit the document itself rather than Config, so
+// keys this version does not know about, comments and layout are written
+// back as they were. A missing file is created. If the change leaves the
+// document as it was, the file is not rewritten.
+fn edit(path: &Path, change: impl FnOnce(&mut DocumentMut) -> Result<(), String>) -> Result<(), String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("reading {}: {}", path.display(), e)),
+    };
+    let mut document: DocumentMut = content.parse().map_err(|e| format!("parsing {}: {}", path.display(), e))?;
+    change(&mut document)?;
+    let edited = document.to_string();
+    if edited == content {
+        return Ok(());
+    }
+
+    path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| storage::write_atomic(path, edited.as_bytes()))
+        .map_err(|e| format!("writing {}: {}", path.display(), e))
+}
+
+// The URLs of a `[[feeds]]` entry's url: the URL, or each of its mirrors.
+fn urls(url: Option<&Value>) -> Vec<&str> {
+    match url {
+        Some(Value::Array(urls)) => urls.iter().filter_map(Value::as_str).collect(),
+        Some(url) => url.as_str().into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+// The URLs of each entry of `feeds`, written as [[feeds]] or inline.
+fn entry_urls(feeds: &Item) -> Vec<Vec<&str>> {
+    match feeds {
+        Item::ArrayOfTables(tables) => tables.iter().map(|feed| urls(feed.get("url").and_then(Item::as_value))).collect(),
+        Item::Value(Value::Array(values)) => {
+            values.iter().map(|feed| urls(feed.as_inline_table().and_then(|feed| feed.get("url")))).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Appends `entry` to the list `name`, creating it as [[name]] if missing.
+fn push(document: &mut DocumentMut, path: &Path, name: &str, entry: Table) -> Result<(), String> {
+    match document.entry(name).or_insert(Item::ArrayOfTables(ArrayOfTables::new())) {
+        Item::ArrayOfTables(tables) => tables.push(entry),
+        Item::Value(Value::Array(values)) => values.push(entry.into_inline_table()),
+        _ => return Err(format!("{} in {} is not a list", name, path.display())),
+    }
+    Ok(())
+}
+
+fn feed(name: &str, url: &str, group: Option<&str>) -> Table {
+    let mut feed = Table::new();
+    feed.insert("name", toml_edit::value(name));
+    feed.insert("url", toml_edit::value(url));
+    if let Some(group) = group {
+        feed.insert("group", toml_edit::value(group));
+    }
+    feed
+}
+
+/// Removes the `[[feeds]]` entry whose first URL is `url`. Names are not
+/// used, as two feeds may share one.
+pub fn remove_feed(path: &Path, url: &str) -> Result<(), String> {
+    edit(path, |document| {
+        let Some(feeds) = document.get_mut("feeds") else {
+            return Err(format!("no feeds in {}", path.display()));
+        };
+        let keep: Vec<bool> = entry_urls(feeds).iter().map(|urls| urls.first() != Some(&url)).collect();
+        if keep.iter().all(|&keep| keep) {
+            return Err(format!("no feed with url '{}' in {}", url, path.display()));
+        }
+        let mut keep = keep.into_iter();
+        match feeds {
+            Item::ArrayOfTables(tables) => tables.retain(|_| keep.next().unwrap_or(true)),
+            Item::Value(Value::Array(values)) => values.retain(|_| keep.next().unwrap_or(true)),
+            _ => {}
+        }
+        Ok(())
+    })
+}
+
+/// Writes a new config.toml listing `feeds` (name, url), creating its
+/// directory. A file that is already there is left alone and is an error.
+pub fn create(path: &Path, feeds: &[(String, String)]) -> Result<(), String> {
+    let mut document = DocumentMut::new();
+    for (name, url) in feeds {
+        push(&mut document, path, "feeds", feed(name, url, None))?;
+    }
+    let content = format!("# Written by br's first-run setup. See the README for every option.\n\n{}", document);
+    path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::OpenOptions::new().write(true).create_new(true).open(path))
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("writing {}: {}", path.display(), e))
+}
+
+/// Appends a `[[feeds]]` entry.
+pub fn add_feed(path: &Path, name: &str, url: &str, group: Option<&str>) -> Result<(), String> {
+    edit(path, |document| push(document, path, "feeds", feed(name, url, group)))
+}
+
+/// Appends a `[[feeds]]` entry for each (name, url) whose URL no feed has
+/// yet, mirrors included. Returns how many were added.
+pub fn add_feeds(path: &Path, new: &[(&str, &str)]) -> Result<usize, String> {
+    let mut added = 0;
+    edit(path, |document| {
+        let mut known: HashSet<String> = document
+            .get("feeds")
+            .map(|feeds| entry_urls(feeds).into_iter().flatten().map(String::from).collect())
+            .unwrap_or_default();
+        for (name, url) in new {
+            if !known.insert(url.to_string()) {
+                continue;
+            }
+            push(document, path, "feeds", feed(name, url, None))?;
+            added += 1;
+        }
+        Ok(())
+    })?;
+    Ok(added)
+}
+
+/// Appends a `[[rules]]` entry.
+pub fn add_rule(path: &Path, rule: &crate::rules::Rule) -> Result<(), String> {
+    edit(path, |document| {
+        let rule: DocumentMut = toml::to_string(rule).map_err(|e| e.to_string())?.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+        push(document, path, "rules", rule.as_table().clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn config(content: &str) -> std::path::PathBuf {
+        let path = temp_dir("config-store").join("config.toml");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn remove_feed_goes_by_url_and_keeps_the_rest() {
+        let path = config(
+            "# my feeds\nrefres_interval = 60\n\n\
+             [[feeds]]\nname = \"Blog\"\nurl = \"https://a.example/feed\"\n\n\
+             [[feeds]]\nname = \"Blog\" # same name, other feed\nurl = [\"https://b.example/feed\", \"https://b.example/mirror\"]\ncolor = \"red\"\n",
+        );
+        remove_feed(&path, "https://a.example/feed").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# my feeds\nrefres_interval = 60\n\n\
+             [[feeds]]\nname = \"Blog\" # same name, other feed\nurl = [\"https://b.example/feed\", \"https://b.example/mirror\"]\ncolor = \"red\"\n",
+        );
+        // A mirror is not the feed's key.
+        assert!(remove_feed(&path, "https://b.example/mirror").unwrap_err().starts_with("no feed with url"));
+        remove_feed(&path, "https://b.example/feed").unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("b.example"));
+    }
+
+    #[test]
+    fn remove_feed_handles_an_inline_list() {
+        let path = config("feeds = [{ name = \"A\", url = \"https://a.example/feed\" }, { name = \"B\", url = \"https://b.example/feed\" }]\n");
+        remove_feed(&path, "https://b.example/feed").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("https://a.example/feed") && !content.contains("https://b.example/feed"));
+    }
+
+    #[test]
+    fn remove_feed_without_the_feed_leaves_the_file_alone() {
+        let path = config("# nothing here\n");
+        assert!(remove_feed(&path, "https://a.example/feed").unwrap_err().starts_with("no feeds in"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# nothing here\n");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    // Options and tables from some later version, with comments between.
+    const FUTURE: &str = "# top\nrefres_interval = 60\nfuture_option = { mode = \"fast\", level = 3 }\n\n\
+        [[feeds]]\nname = \"A\" # first\nurl = \"https://a.example/feed\"\nfuture_feed_key = [1, 2]\n\n\
+        [[feeds]]\nname = \"B\"\nurl = \"https://b.example/feed\"\n\n\
+        [future_section]\nkey = \"value\"\n";
+
+    fn assert_kept(content: &str) {
+        for kept in [
+            "# top\n",
+            "refres_interval = 60\n",
+            "future_option = { mode = \"fast\", level = 3 }\n",
+            "future_feed_key = [1, 2]\n",
+            "[future_section]\nkey = \"value\"\n",
+        ] {
+            assert!(content.contains(kept), "lost {:?} from:\n{}", kept, content);
+        }
+    }
+
+    #[test]
+    fn unknown_options_survive_every_edit() {
+        let path = config(FUTURE);
+        add_feed(&path, "C", "https://c.example/feed", Some("news")).unwrap();
+        assert_eq!(add_feeds(&path, &[("C again", "https://c.example/feed"), ("D", "https://d.example/feed")]), Ok(1));
+        add_rule(&path, &crate::rules::Rule { title: Some("sponsored".to_string()), ..Default::default() }).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_kept(&content);
+        let order: Vec<usize> = ["a.example", "b.example", "c.example", "d.example"].iter().map(|host| content.find(host).unwrap()).collect();
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(content.contains("[[rules]]\ntitle = \"sponsored\""));
+    }
+
+    #[test]
+    fn unknown_options_survive_the_feeds_moving_up() {
+        let path = config(FUTURE);
+        remove_feed(&path, "https://a.example/feed").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        // A's own keys go with it; B is now first.
+        assert!(!content.contains("future_feed_key") && !content.contains("a.example"));
+        assert!(content.starts_with("# top\nrefres_interval = 60\nfuture_option = { mode = \"fast\", level = 3 }\n\n[[feeds]]\nname = \"B\""));
+        assert!(content.ends_with("[future_section]\nkey = \"value\"\n"));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::storage::Storage;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    path::PathBuf,
+};
+
+/// How many recently viewed bodies are kept in memory.
+const CACHED: usize = 16;
+
+/// Item bodies (summaries), kept on disk under the data directory and read
+/// back when they are viewed, so memory holds only what the list shows.
+///
+/// When the store is ephemeral the bodies stay in memory instead.
+pub struct ContentStore {
+    dir: PathBuf,
+    storage: Storage,
+    recent: VecDeque<(String, String)>, // most recently viewed last
+    unsaved: HashMap<String, String>,
+}
+
+impl ContentStore {
+    pub fn new(dir: PathBuf, storage: Storage) -> ContentStore {
+        ContentStore { dir, storage, recent: VecDeque::new(), unsaved: HashMap::new() }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.txt", Sha256::digest(id.as_bytes())))
+    }
+
+    /// Items are not kept between sessions, so neither are their bodies.
+    pub fn clear(&self) -> io::Result<()> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+        self.storage.probe(&self.dir)
+    }
+
+    pub async fn put(&mut self, id: &str, content: &str) -> io::Result<()> {
+        let result = self.storage.write(&self.path(id), content.as_bytes()).await;
+        // Nothing is written once the store is ephemeral.
+        if self.storage.is_ephemeral() {
+            self.unsaved.insert(id.to_string(), content.to_string());
+        }
+        result
+    }
+
+    pub fn get(&mut self, id: &str) -> Option<String> {
+        if let Some(i) = self.recent.iter().position(|(cached, _)| cached == id) {
+            let entry = self.recent.remove(i)?;
+            self.recent.push_back(entry);
+        } else {
+            let content = match self.unsaved.get(id) {
+                Some(content) => content.clone(),
+                None => std::fs::read_to_string(self.path(id)).ok()?,
+            };
+            self.recent.push_back((id.to_string(), content));
+            if self.recent.len() > CACHED {
+                self.recent.pop_front();
+            }
+        }
+        self.recent.back().map(|(_, content)| content.clone())
+    }
+}
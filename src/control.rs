@@ -0,0 +1,180 @@
+use crate::{storage::{self, Storage}, Subscription, Update};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{broadcast, mpsc, oneshot},
+};
+
+/// `$XDG_RUNTIME_DIR/br.sock`, or None where there is no runtime directory.
+pub fn socket_path() -> Option<PathBuf> {
+    Some(dirs::runtime_dir()?.join("br.sock"))
+}
+
+/// A command read from the control socket, one JSON object per line, e.g.
+/// `{"cmd":"refresh"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    Refresh,
+    AddFeed {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    ListUnread,
+    /// Answers with the unread count, then keeps the connection open and
+    /// sends a line for every event.
+    Watch,
+}
+
+/// Listens on the control socket for as long as it is kept; dropping it
+/// removes the socket file.
+pub struct Server {
+    path: PathBuf,
+    events: broadcast::Sender<Value>,
+}
+
+impl Server {
+    /// Binds `path`, readable and writable by the user only. Commands that
+    /// need the app are sent down `tx` as Update::Control and answered
+    /// through the oneshot that comes with them. Feeds are not added while
+    /// `storage` is ephemeral.
+    pub async fn start(path: PathBuf, tx: mpsc::Sender<Update>, config_path: PathBuf, storage: Storage) -> io::Result<Server> {
+        if path.exists() {
+            if UnixStream::connect(&path).await.is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use by another br", path.display())));
+            }
+            // Left behind by a br that did not shut down cleanly.
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        let (events, _) = broadcast::channel(100);
+        let server_events = events.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, tx.clone(), server_events.clone(), config_path.clone(), storage.clone()));
+            }
+        });
+        Ok(Server { path, events })
+    }
+
+    /// Sends `event` to every watching client.
+    pub fn publish(&self, event: Value) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn error(message: impl ToString) -> Value {
+    json!({ "ok": false, "error": message.to_string() })
+}
+
+async fn write_line(stream: &mut (impl AsyncWriteExt + Unpin), value: &Value) -> io::Result<()> {
+    stream.write_all(format!("{}\n", value).as_bytes()).await
+}
+
+async fn serve(stream: UnixStream, tx: mpsc::Sender<Update>, events: broadcast::Sender<Value>, config_path: PathBuf, storage: Storage) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                if write_line(&mut write, &error(e)).await.is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+        // Subscribe first so nothing is missed between the answer and the stream.
+        let mut watching = matches!(request, Request::Watch).then(|| events.subscribe());
+
+        let response = match request {
+            Request::AddFeed { .. } if storage.is_ephemeral() => error(format!("feed {}", storage::NOT_SAVED)),
+            Request::AddFeed { url, name, group } => match crate::subscribe(&config_path, &url, name, group).await {
+                Subscription::Added(feed) => {
+                    let response = json!({ "ok": true, "status": "added", "name": feed.name, "url": feed.url.all()[0] });
+                    let _ = tx.send(Update::Subscribed(*feed)).await;
+                    response
+                }
+                Subscription::Exists(name, url) => json!({ "ok": true, "status": "exists", "name": name, "url": url }),
+                Subscription::NotFound(e) => json!({ "ok": false, "status": "not-found", "error": e }),
+                Subscription::Failed(e) => error(e),
+            },
+            request => {
+                let (reply, response) = oneshot::channel();
+                if tx.send(Update::Control(request, reply)).await.is_err() {
+                    return;
+                }
+                response.await.unwrap_or_else(|_| error("br is shutting down"))
+            }
+        };
+        if write_line(&mut write, &response).await.is_err() {
+            return;
+        }
+
+        if let Some(events) = &mut watching {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if write_line(&mut write, &event).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        }
+    }
+}
+
+/// `br ctl`: sends `request` and prints every line that comes back until
+/// the connection closes, or after the first one unless watching. Returns
+/// the exit code.
+pub async fn send(path: &Path, request: &Request) -> i32 {
+    let stream = match UnixStream::connect(path).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("connecting to {}: {} (is br running with control_socket = true?)", path.display(), e);
+            return 1;
+        }
+    };
+    let (read, mut write) = stream.into_split();
+    let request = serde_json::to_value(request).unwrap_or_default();
+    if let Err(e) = write_line(&mut write, &request).await {
+        eprintln!("writing to {}: {}", path.display(), e);
+        return 1;
+    }
+    let watch = request["cmd"] == "watch";
+    let mut lines = BufReader::new(read).lines();
+    let mut code = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        println!("{}", line);
+        // The answer to the request decides the exit code, not later events.
+        code.get_or_insert_with(|| match serde_json::from_str::<Value>(&line) {
+            Ok(response) if response["ok"] == true => 0,
+            _ => 1,
+        });
+        if !watch {
+            break;
+        }
+    }
+    code.unwrap_or(1)
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Utc};
+
+/// The calendar day `date` falls on in the local timezone.
+pub fn local_day(date: DateTime<Utc>) -> NaiveDate {
+    date.with_timezone(&Local).date_naive()
+}
+
+/// "Today", "Yesterday", or the weekday and date ("Monday 10 Mar") for
+/// anything older. The year is added once it differs from the current one.
+pub fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".to_string()
+    } else if Some(day) == today.pred_opt() {
+        "Yesterday".to_string()
+    } else if day.year() == today.year() {
+        day.format("%A %-d %b").to_string()
+    } else {
+        day.format("%A %-d %b %Y").to_string()
+    }
+}
+
+/// A date range as typed after `d`: `12h`, `7d` or `2w` back from `now`, or
+/// local days such as `2024-01-01..2024-06-30` (either end may be left
+/// open) or `2024-01-01` alone. Returns the start and the end, which is
+/// excluded.
+pub fn parse_date_filter(text: &str, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let text = text.trim();
+    if let Some((from, to)) = text.split_once("..") {
+        let from = if from.trim().is_empty() { DateTime::<Utc>::MIN_UTC } else { day_start(parse_day(from)?)? };
+        let to = if to.trim().is_empty() { DateTime::<Utc>::MAX_UTC } else { day_start(parse_day(to)?.succ_opt()?)? };
+        return (from < to).then_some((from, to));
+    }
+    if let Some(day) = parse_day(text) {
+        return Some((day_start(day)?, day_start(day.succ_opt()?)?));
+    }
+    let unit = text.chars().last()?;
+    let count: i64 = text[..text.len() - unit.len_utf8()].parse().ok().filter(|&count| count > 0)?;
+    let span = match unit {
+        'h' => TimeDelta::try_hours(count)?,
+        'd' => TimeDelta::try_days(count)?,
+        'w' => TimeDelta::try_weeks(count)?,
+        _ => return None,
+    };
+    // Open-ended, so items dated a little ahead of the local clock stay.
+    Some((now.checked_sub_signed(span)?, DateTime::<Utc>::MAX_UTC))
+}
+
+/// A range from parse_date_filter as local days, e.g. "2024-01-01..2024-06-30"
+/// or "2024-06-01.." when it has no end.
+pub fn describe_range((from, to): (DateTime<Utc>, DateTime<Utc>)) -> String {
+    let from = if from == DateTime::<Utc>::MIN_UTC { String::new() } else { local_day(from).to_string() };
+    let to = if to == DateTime::<Utc>::MAX_UTC { String::new() } else { local_day(to - TimeDelta::seconds(1)).to_string() };
+    format!("{}..{}", from, to)
+}
+
+fn parse_day(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()
+}
+
+// Local midnight at the start of `day`.
+fn day_start(day: NaiveDate) -> Option<DateTime<Utc>> {
+    day.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest().map(|start| start.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn labels_count_back_from_today() {
+        let today = day(2024, 3, 12);
+        assert_eq!(day_label(today, today), "Today");
+        assert_eq!(day_label(day(2024, 3, 11), today), "Yesterday");
+        assert_eq!(day_label(day(2024, 3, 10), today), "Sunday 10 Mar");
+        assert_eq!(day_label(day(2023, 12, 31), today), "Sunday 31 Dec 2023");
+    }
+
+    #[test]
+    fn yesterday_crosses_month_and_year_boundaries() {
+        assert_eq!(day_label(day(2024, 2, 29), day(2024, 3, 1)), "Yesterday");
+        assert_eq!(day_label(day(2023, 12, 31), day(2024, 1, 1)), "Yesterday");
+    }
+
+    #[test]
+    fn local_day_splits_at_local_midnight() {
+        let midnight = day_start(day(2024, 3, 11)).unwrap();
+        assert_eq!(local_day(midnight - TimeDelta::seconds(1)), day(2024, 3, 10));
+        assert_eq!(local_day(midnight), day(2024, 3, 11));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::guid;
+use feed_rs::{model::Entry, parser as feed_parser};
+use quick_xml::{events::Event, Reader};
+
+/// Longest a text field is shown in the report before it is cut short.
+const MAX_FIELD: usize = 300;
+
+/// Lines for the scrollable report popup: what `F` found out about the feed
+/// of one item, or how a reading session went.
+#[derive(Debug)]
+pub struct Report {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+/// Fetches `url` with `request` and describes every step: the answer, how
+/// the body is encoded, what feed-rs made of it, and the raw fields of the
+/// entry whose key (permalink, else link, as the list uses) is `key`.
+/// Returns whether the feed parsed, so the next mirror is only tried when
+/// this one failed.
+pub async fn check(request: reqwest::RequestBuilder, url: &str, key: &str, lines: &mut Vec<String>) -> bool {
+    lines.push(format!("GET {}", url));
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            lines.push(format!("  request failed: {}", e));
+            return false;
+        }
+    };
+    lines.push(format!("  Status: {}", response.status()));
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    lines.push(format!("  Content-Type: {}", content_type.as_deref().unwrap_or("(none)")));
+    if !response.status().is_success() {
+        return false;
+    }
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            lines.push(format!("  reading the body failed: {}", e));
+            return false;
+        }
+    };
+    lines.push(format!("  Body: {} bytes", bytes.len()));
+    let charset = content_type.as_deref().and_then(|t| t.split(';').find_map(|p| p.trim().strip_prefix("charset="))).map(|c| c.trim_matches('"').to_string());
+    lines.push(format!(
+        "  Encoding: {} (Content-Type charset: {}, byte order mark: {})",
+        declared_encoding(&bytes).unwrap_or_else(|| "not declared".to_string()),
+        charset.as_deref().unwrap_or("none"),
+        bom(&bytes).unwrap_or("none")
+    ));
+
+    let feed = match feed_parser::parse(&bytes[..]) {
+        Ok(feed) => feed,
+        Err(e) => {
+            lines.push(format!("  Parse error: {}", e));
+            lines.push(format!("  Body starts: {}", clip(&String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_FIELD)]))));
+            return false;
+        }
+    };
+    lines.push(format!("  Parsed as: {:?}", feed.feed_type));
+    lines.push(format!("  Feed title: {}", feed.title.as_ref().map_or("(none)", |t| t.content.as_str())));
+    lines.push(format!("  Entries: {}", feed.entries.len()));
+    lines.push(String::new());
+
+    let not_permalinks = guid::not_permalinks(&bytes);
+    let found = feed.entries.iter().enumerate().find(|(_, entry)| {
+        let link = entry.links.first().map_or("", |l| l.href.as_str());
+        guid::permalink(&feed, entry, &not_permalinks).as_deref().unwrap_or(link) == key
+    });
+    match found {
+        Some((i, entry)) => {
+            lines.push(format!("Entry {} of {}, as parsed:", i + 1, feed.entries.len()));
+            entry_fields(entry, guid::permalink(&feed, entry, &not_permalinks), lines);
+        }
+        None => lines.push(format!("No entry in the feed has the key {} any more.", key)),
+    }
+    true
+}
+
+// The encoding an XML declaration names, e.g. ISO-8859-1.
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_reader(bytes);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Decl(decl)) => return decl.encoding()?.ok().map(|e| String::from_utf8_lossy(&e).into_owned()),
+            Ok(Event::Start(_) | Event::Empty(_) | Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+fn bom(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => Some("UTF-8"),
+        [0xFF, 0xFE, ..] => Some("UTF-16LE"),
+        [0xFE, 0xFF, ..] => Some("UTF-16BE"),
+        _ => None,
+    }
+}
+
+// Each field feed-rs filled in, as close to the document as it keeps them.
+fn entry_fields(entry: &Entry, permalink: Option<String>, lines: &mut Vec<String>) {
+    let mut field = |name: &str, value: String| lines.push(format!("  {}: {}", name, value));
+    field("id", entry.id.clone());
+    field("permalink guid", permalink.unwrap_or_else(|| "no".to_string()));
+    match &entry.title {
+        Some(title) => field("title", format!("{} ({})", clip(&title.content), title.content_type)),
+        None => field("title", "(none)".to_string()),
+    }
+    field("published", entry.published.map_or("(none)".to_string(), |d| d.to_rfc3339()));
+    field("updated", entry.updated.map_or("(none)".to_string(), |d| d.to_rfc3339()));
+    for link in &entry.links {
+        field("link", format!("{} (rel {})", link.href, link.rel.as_deref().unwrap_or("none")));
+    }
+    for author in &entry.authors {
+        field("author", author.name.clone());
+    }
+    let categories: Vec<&str> = entry.categories.iter().map(|c| c.term.as_str()).collect();
+    field("categories", categories.join(", "));
+    match &entry.summary {
+        Some(summary) => field("summary", format!("{} ({})", clip(&summary.content), summary.content_type)),
+        None => field("summary", "(none)".to_string()),
+    }
+    match &entry.content {
+        Some(content) => field(
+            "content",
+            format!("{} ({})", content.body.as_deref().map_or("(no body)".to_string(), clip), content.content_type),
+        ),
+        None => field("content", "(none)".to_string()),
+    }
+}
+
+// At most MAX_FIELD characters of `text`, on one line.
+fn clip(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(MAX_FIELD) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
@@ -0,0 +1,51 @@
+use feed_rs::parser as feed_parser;
+use scraper::{Html, Selector};
+use url::Url;
+
+// Feeds a page advertises with <link rel="alternate">, resolved against the
+// page URL.
+fn advertised_feeds(page: &str, base: &Url) -> Vec<String> {
+    let document = Html::parse_document(page);
+    let selector = Selector::parse(r#"link[rel~="alternate"]"#).unwrap();
+    document
+        .select(&selector)
+        .filter(|link| {
+            let kind = link.value().attr("type").unwrap_or_default();
+            kind.contains("rss") || kind.contains("atom")
+        })
+        .filter_map(|link| base.join(link.value().attr("href")?).ok())
+        .map(String::from)
+        .collect()
+}
+
+async fn fetch(http: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
+    let response = http.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    Ok(response.bytes().await.map_err(|e| e.to_string())?.to_vec())
+}
+
+/// Finds the feed for `url`: the URL itself if it is a feed, otherwise the
+/// first working feed the page links to. Returns the feed URL and its title.
+pub async fn discover(http: &reqwest::Client, url: &str) -> Result<(String, Option<String>), String> {
+    let base = Url::parse(url).map_err(|e| format!("{}: {}", url, e))?;
+    let body = fetch(http, url).await?;
+    if let Ok(feed) = feed_parser::parse(&body[..]) {
+        return Ok((url.to_string(), feed.title.map(|t| t.content)));
+    }
+
+    let page = String::from_utf8_lossy(&body);
+    let candidates = advertised_feeds(&page, &base);
+    if candidates.is_empty() {
+        return Err(format!("{} is not a feed and links to none", url));
+    }
+    for candidate in &candidates {
+        if let Ok(body) = fetch(http, candidate).await
+            && let Ok(feed) = feed_parser::parse(&body[..])
+        {
+            return Ok((candidate.clone(), feed.title.map(|t| t.content)));
+        }
+    }
+    Err(format!("none of the {} feeds {} links to could be read", candidates.len(), url))
+}
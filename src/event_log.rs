@@ -0,0 +1,151 @@
+use chrono::{DateTime, Local};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Once the log is larger than this at start-up, only its newer half is kept.
+const MAX_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Debug, // logged only, never shown
+    Info,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+/// A message as the info panel or the list shows it, e.g. "[INFO] Note
+/// saved". The log records exactly this text.
+pub fn shown(level: Level, message: &str) -> String {
+    format!("[{}] {}", level.name(), message)
+}
+
+/// `br.log` in the data directory.
+pub fn default_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("br").join("br.log"))
+}
+
+/// Every message shown in the info panel or as an [ERROR] row, and the debug
+/// notes nothing shows, with the time, one tab-separated line each. Messages
+/// from before the data directory is known are held until `open`.
+pub struct EventLog {
+    file: Option<File>,
+    pending: Option<Vec<String>>,
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        EventLog { file: None, pending: Some(Vec::new()) }
+    }
+
+    /// Starts appending to `path`, with what was held so far.
+    pub fn open(&mut self, path: &Path) -> io::Result<()> {
+        trim(path)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for line in self.pending.take().unwrap_or_default() {
+            file.write_all(line.as_bytes())?;
+        }
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Stops holding messages, for when there is nowhere to write them.
+    pub fn discard(&mut self) {
+        self.pending = None;
+    }
+
+    /// Records `shown`, the text as displayed. A log that cannot be written
+    /// is given up on rather than reported, which would only log again.
+    pub fn record(&mut self, level: Level, shown: &str) {
+        let line = format!("{}\t{}\t{}\n", Local::now().to_rfc3339(), level.name(), shown.trim_end().replace('\n', " "));
+        if let Some(pending) = &mut self.pending {
+            pending.push(line);
+        } else if let Some(file) = &mut self.file
+            && file.write_all(line.as_bytes()).is_err()
+        {
+            self.file = None;
+        }
+    }
+}
+
+// Keeps the newer half of a log past MAX_BYTES, from a line boundary.
+fn trim(path: &Path) -> io::Result<()> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > MAX_BYTES => {}
+        _ => return Ok(()),
+    }
+    let content = std::fs::read(path)?;
+    let start = content.len() - (MAX_BYTES / 2) as usize;
+    let start = content[start..].iter().position(|&b| b == b'\n').map_or(content.len(), |i| start + i + 1);
+    std::fs::write(path, &content[start..])
+}
+
+/// `br log`: prints the last `count` messages of the log at `path`, or of
+/// the errors only, with their local time. Returns the exit code.
+pub fn print(path: &Path, count: usize, errors_only: bool) -> i32 {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return 0,
+        Err(e) => {
+            eprintln!("reading {}: {}", path.display(), e);
+            return 1;
+        }
+    };
+    let lines: Vec<(&str, &str)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (time, level, shown) = (fields.next()?, fields.next()?, fields.next()?);
+            (!errors_only || level == Level::Error.name()).then_some((time, shown))
+        })
+        .collect();
+    for (time, shown) in &lines[lines.len().saturating_sub(count)..] {
+        let time = DateTime::parse_from_rfc3339(time)
+            .map_or_else(|_| time.to_string(), |t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string());
+        println!("{}  {}", time, shown);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn messages(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path).unwrap().lines().map(|line| line.split_once('\t').unwrap().1.to_string()).collect()
+    }
+
+    #[test]
+    fn messages_before_open_are_held_for_the_log() {
+        let path = temp_dir("event-log-held").join("br.log");
+        let mut log = EventLog::new();
+        log.record(Level::Error, &shown(Level::Error, "reading config.toml: denied"));
+        log.open(&path).unwrap();
+        log.record(Level::Debug, &shown(Level::Debug, "two\nlines"));
+        assert_eq!(messages(&path), ["ERROR\t[ERROR] reading config.toml: denied", "DEBUG\t[DEBUG] two lines"]);
+    }
+
+    #[test]
+    fn a_long_log_keeps_its_newer_half() {
+        let path = temp_dir("event-log-trim").join("br.log");
+        let line = format!("2024-03-10T12:00:00+00:00\tINFO\t[INFO] {}\n", "x".repeat(99));
+        std::fs::write(&path, line.repeat((MAX_BYTES as usize / line.len()) + 10)).unwrap();
+        let mut log = EventLog::new();
+        log.open(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.len() as u64 <= MAX_BYTES / 2);
+        assert!(content.lines().all(|kept| format!("{}\n", kept) == line));
+    }
+}
@@ -0,0 +1,94 @@
+use feed_rs::model::{Entry, Feed, FeedType};
+use quick_xml::{events::Event, Reader};
+use std::collections::HashSet;
+
+/// The `<guid>`s of an RSS document marked `isPermaLink="false"`. feed-rs
+/// keeps a guid as the entry's id but drops the attribute, so it is read
+/// from the document here.
+pub fn not_permalinks(xml: &[u8]) -> HashSet<String> {
+    let mut reader = Reader::from_reader(xml);
+    let mut found = HashSet::new();
+    // The text of the <guid> being read, if it is marked.
+    let mut guid: Option<String> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) if element.local_name().as_ref() == b"guid" => {
+                let marked = element.try_get_attribute("isPermaLink").ok().flatten();
+                guid = marked.filter(|a| a.value.eq_ignore_ascii_case(b"false")).map(|_| String::new());
+            }
+            Ok(Event::Text(text)) => {
+                if let Some(guid) = &mut guid
+                    && let Ok(text) = text.unescape()
+                {
+                    guid.push_str(&text);
+                }
+            }
+            Ok(Event::CData(text)) => {
+                if let Some(guid) = &mut guid {
+                    guid.push_str(&String::from_utf8_lossy(&text.into_inner()));
+                }
+            }
+            Ok(Event::End(element)) if element.local_name().as_ref() == b"guid" => {
+                found.extend(guid.take().map(|guid| guid.trim().to_string()));
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    found
+}
+
+/// The permalink `entry` gives as its guid, in an RSS feed: a guid that is
+/// an http(s) URL, unless marked otherwise, since isPermaLink defaults to
+/// true. Generated ids and Atom ids, which are names rather than
+/// addresses, are never permalinks.
+pub fn permalink(feed: &Feed, entry: &Entry, not_permalinks: &HashSet<String>) -> Option<String> {
+    if !matches!(feed.feed_type, FeedType::RSS0 | FeedType::RSS2) || not_permalinks.contains(&entry.id) {
+        return None;
+    }
+    let url = url::Url::parse(&entry.id).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| entry.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // As FeedBurner serves them: tracking links, and guids that are either
+    // the post's address or an opaque id marked as not one.
+    const FEEDBURNER: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:feedburner="http://rssnamespace.org/feedburner/ext/1.0">
+<channel><title>Blog</title>
+<item><title>Permalink</title><link>https://feedproxy.google.com/~r/blog/~3/abc/</link>
+<guid>https://blog.example/2024/permalink</guid></item>
+<item><title>Opaque</title><link>https://feedproxy.google.com/~r/blog/~3/def/</link>
+<guid isPermaLink="false">tag:blogger.com,1999:blog-1.post-2</guid></item>
+<item><title>Marked URL</title><link>https://feedproxy.google.com/~r/blog/~3/ghi/</link>
+<guid isPermaLink="FALSE"><![CDATA[ https://blog.example/?p=3 ]]></guid></item>
+</channel></rss>"#;
+
+    fn permalinks(xml: &str) -> Vec<Option<String>> {
+        let feed = feed_rs::parser::parse(xml.as_bytes()).unwrap();
+        let marked = not_permalinks(xml.as_bytes());
+        feed.entries.iter().map(|entry| permalink(&feed, entry, &marked)).collect()
+    }
+
+    #[test]
+    fn marked_guids_are_read_with_their_text_trimmed() {
+        let marked = not_permalinks(FEEDBURNER.as_bytes());
+        let expected: HashSet<String> = ["tag:blogger.com,1999:blog-1.post-2", "https://blog.example/?p=3"].map(String::from).into();
+        assert_eq!(marked, expected);
+    }
+
+    #[test]
+    fn only_unmarked_url_guids_are_permalinks() {
+        assert_eq!(permalinks(FEEDBURNER), [Some("https://blog.example/2024/permalink".to_string()), None, None]);
+    }
+
+    #[test]
+    fn atom_ids_are_never_permalinks() {
+        let atom = r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+            <entry><title>Post</title><id>https://blog.example/post</id><link href="https://blog.example/post?utm=1"/></entry></feed>"#;
+        assert_eq!(permalinks(atom), [None]);
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// How long "ignore" silences the unsubscribe notice for a feed.
+pub const IGNORE_DAYS: i64 = 30;
+
+/// A source with something wrong: a feed that keeps answering 404 or 410,
+/// or a problem worth noting such as a server refusing HEAD. Feeds that fetch
+/// fine have no entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeedHealth {
+    #[serde(default)]
+    pub consecutive_gone: u32,
+    #[serde(default)]
+    pub last_status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignored_until: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Keyed by URL (a feed's first one), persisted as health.json.
+pub type Health = BTreeMap<String, FeedHealth>;
+
+/// A missing or unreadable file means every feed is healthy. Entries from
+/// before health was keyed by URL are dropped; a feed that is still gone
+/// is noticed again within `gone_after` refreshes.
+pub fn load(path: &Path) -> Health {
+    let mut health: Health = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    health.retain(|key, _| key.contains("://"));
+    health
+}
+
+/// Records the outcome of fetching `feed`: `None` when it was fetched, or the
+/// 404/410 status it answered with. Returns true once the feed has been gone
+/// for `threshold` refreshes in a row and the notice is not being ignored.
+pub fn record(health: &mut Health, feed: &str, gone: Option<u16>, threshold: u32, now: DateTime<Utc>) -> bool {
+    let Some(status) = gone else {
+        // A warning about what the feed served is cleared by warn() instead.
+        match health.get(feed).and_then(|entry| entry.warning.clone()) {
+            Some(warning) => {
+                health.insert(feed.to_string(), FeedHealth { warning: Some(warning), ..Default::default() });
+            }
+            None => {
+                health.remove(feed);
+            }
+        }
+        return false;
+    };
+    let entry = health.entry(feed.to_string()).or_default();
+    entry.consecutive_gone += 1;
+    entry.last_status = status;
+    if entry.ignored_until.is_some_and(|until| until <= now) {
+        entry.ignored_until = None;
+    }
+    entry.consecutive_gone >= threshold && entry.ignored_until.is_none()
+}
+
+pub fn ignore(health: &mut Health, feed: &str, now: DateTime<Utc>) {
+    health.entry(feed.to_string()).or_default().ignored_until = Some(now + Duration::days(IGNORE_DAYS));
+}
+
+/// Records a warning for `source`, or clears it with `None`. Returns true if
+/// the warning was not already known, so it is only shown once.
+pub fn warn(health: &mut Health, source: &str, warning: Option<&str>) -> bool {
+    let Some(warning) = warning else {
+        if let Some(entry) = health.get_mut(source) {
+            entry.warning = None;
+            if entry.consecutive_gone == 0 && entry.ignored_until.is_none() {
+                health.remove(source);
+            }
+        }
+        return false;
+    };
+    let entry = health.entry(source.to_string()).or_default();
+    if entry.warning.as_deref() == Some(warning) {
+        return false;
+    }
+    entry.warning = Some(warning.to_string());
+    true
+}
+
+/// Feeds with fewer entries than this are not judged by what all their
+/// entries have in common; one or two posts can share a link or lack dates.
+const SUSPICIOUS_MIN_ENTRIES: usize = 3;
+
+/// What looks wrong with a feed that parsed, if anything: no entries, or
+/// entries that all lack a title, all lack a date, or all share one link.
+pub fn suspicious(entries: &[feed_rs::model::Entry]) -> Option<String> {
+    if entries.is_empty() {
+        return Some("parsed but returned 0 entries — feed may be broken".to_string());
+    }
+    if entries.len() < SUSPICIOUS_MIN_ENTRIES {
+        return None;
+    }
+    let n = entries.len();
+    if entries.iter().all(|e| e.title.as_ref().is_none_or(|t| t.content.trim().is_empty())) {
+        return Some(format!("parsed but none of its {} entries has a title — feed may be broken", n));
+    }
+    if entries.iter().all(|e| e.published.or(e.updated).is_none()) {
+        return Some(format!("parsed but none of its {} entries has a date — feed may be broken", n));
+    }
+    let first = entries[0].links.first().map(|l| l.href.as_str());
+    if entries.iter().all(|e| e.links.first().map(|l| l.href.as_str()) == first) {
+        return Some(format!("parsed but all of its {} entries link to the same page — feed may be broken", n));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = "https://example.com/feed";
+
+    #[test]
+    fn a_feed_is_offered_once_gone_for_the_threshold() {
+        let (mut health, now) = (Health::new(), Utc::now());
+        assert!(!record(&mut health, FEED, Some(404), 3, now));
+        assert!(!record(&mut health, FEED, Some(410), 3, now));
+        assert!(record(&mut health, FEED, Some(410), 3, now));
+        assert_eq!((health[FEED].consecutive_gone, health[FEED].last_status), (3, 410));
+    }
+
+    #[test]
+    fn a_successful_fetch_resets_the_count() {
+        let (mut health, now) = (Health::new(), Utc::now());
+        record(&mut health, FEED, Some(404), 2, now);
+        record(&mut health, FEED, None, 2, now);
+        assert!(!health.contains_key(FEED));
+        assert!(!record(&mut health, FEED, Some(404), 2, now));
+
+        // A warning outlives the reset; the count does not.
+        warn(&mut health, FEED, Some("parsed but returned 0 entries"));
+        record(&mut health, FEED, None, 2, now);
+        assert_eq!(health[FEED].consecutive_gone, 0);
+        assert!(health[FEED].warning.is_some());
+    }
+
+    #[test]
+    fn ignoring_holds_until_it_expires() {
+        let (mut health, now) = (Health::new(), Utc::now());
+        assert!(record(&mut health, FEED, Some(410), 1, now));
+        ignore(&mut health, FEED, now);
+        assert!(!record(&mut health, FEED, Some(410), 1, now + Duration::days(IGNORE_DAYS - 1)));
+        assert!(record(&mut health, FEED, Some(410), 1, now + Duration::days(IGNORE_DAYS)));
+        assert_eq!(health[FEED].ignored_until, None);
+    }
+
+    #[test]
+    fn warnings_are_reported_once() {
+        let mut health = Health::new();
+        assert!(warn(&mut health, FEED, Some("refuses HEAD")));
+        assert!(!warn(&mut health, FEED, Some("refuses HEAD")));
+        warn(&mut health, FEED, None);
+        assert!(health.is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// How many refreshes are kept in history.json.
+pub const MAX_ENTRIES: usize = 20;
+
+/// What a single refresh brought in. Reported when the refresh completes and
+/// kept for the refresh history popup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshSummary {
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub bytes: u64,
+    pub errors: usize,
+    pub new_items: BTreeMap<String, usize>, // source name -> new items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<i64>, // median server minus local time in seconds, if any server sent a Date
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<String, usize>, // reason -> entries fetched but not listed
+}
+
+impl RefreshSummary {
+    pub fn total_new(&self) -> usize {
+        self.new_items.values().sum()
+    }
+
+    pub fn headline(&self) -> String {
+        format!(
+            "{} new from {} sources, {} errors, {} in {:.1}s",
+            self.total_new(),
+            self.new_items.len(),
+            self.errors,
+            format_bytes(self.bytes),
+            self.duration_ms as f64 / 1000.0,
+        )
+    }
+
+    pub fn timestamp(&self) -> String {
+        self.finished_at.with_timezone(&Local).format("%a %e %b %H:%M").to_string()
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Oldest first. A missing or unreadable file is an empty history.
+pub fn load(path: &Path) -> Vec<RefreshSummary> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn record(history: &mut Vec<RefreshSummary>, summary: RefreshSummary) {
+    history.push(summary);
+    if history.len() > MAX_ENTRIES {
+        history.drain(..history.len() - MAX_ENTRIES);
+    }
+}
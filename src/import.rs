@@ -0,0 +1,145 @@
+use crate::config_store;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A feed listed in an OPML file.
+pub struct Outline {
+    pub name: String,
+    pub url: String,
+}
+
+// The trimmed value of attribute `name`, if the element has it.
+fn attribute(reader: &Reader<&[u8]>, element: &BytesStart, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.decode_and_unescape_value(reader)?.trim().to_string())),
+        None => Ok(None),
+    }
+}
+
+/// The `<outline>` elements that have an `xmlUrl`, in document order. Each
+/// is named after its `text`, else its `title`, else the URL itself, after
+/// the folders it is in: "Tech / Rust / This Week in Rust".
+pub fn parse(opml: &str) -> Result<Vec<Outline>, Box<dyn Error>> {
+    let mut reader = Reader::from_str(opml);
+    let mut outlines = Vec::new();
+    // One entry per open <outline>: the folder name, or None for a feed.
+    let mut folders: Vec<Option<String>> = Vec::new();
+    loop {
+        let (element, empty) = match reader.read_event()? {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(element) if element.local_name().as_ref() == b"outline" => {
+                folders.pop();
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        if element.local_name().as_ref() != b"outline" {
+            continue;
+        }
+        let label = [attribute(&reader, &element, "text")?, attribute(&reader, &element, "title")?]
+            .into_iter()
+            .flatten()
+            .find(|label| !label.is_empty());
+        let url = attribute(&reader, &element, "xmlUrl")?.filter(|url| !url.is_empty());
+        if !empty {
+            folders.push(if url.is_none() { label.clone() } else { None });
+        }
+        let Some(url) = url else {
+            continue;
+        };
+        let mut name: Vec<&str> = folders.iter().flatten().map(String::as_str).collect();
+        name.push(label.as_deref().unwrap_or(&url));
+        outlines.push(Outline { name: name.join(" / "), url });
+    }
+    Ok(outlines)
+}
+
+/// `br import --opml`: adds the feeds of the OPML file at `opml_path` to
+/// `[[feeds]]` in the config, leaving out URLs it already lists. Returns how
+/// many were added.
+pub fn import_opml(config_path: &Path, opml_path: &Path) -> Result<usize, Box<dyn Error>> {
+    let content = fs::read_to_string(opml_path).map_err(|e| format!("reading {}: {}", opml_path.display(), e))?;
+    let outlines = parse(&content).map_err(|e| format!("parsing {}: {}", opml_path.display(), e))?;
+    let feeds: Vec<(&str, &str)> = outlines.iter().map(|outline| (outline.name.as_str(), outline.url.as_str())).collect();
+    Ok(config_store::add_feeds(config_path, &feeds)?)
+}
+
+/// A feed listed in newsboat's `urls` file.
+pub struct UrlsEntry {
+    pub url: String,
+    pub title: Option<String>, // from a "~Title" tag
+    pub tags: Vec<String>,
+}
+
+/// newsboat's `urls` file: a feed per line, its URL followed by tags, which
+/// are separated by whitespace and may be double-quoted to hold spaces. A
+/// tag starting with `~` renames the feed, and one starting with `!` only
+/// hides it in newsboat. Blank lines, `#` comments and the query:, exec: and
+/// filter: feeds newsboat makes itself are left out.
+pub fn parse_newsboat(content: &str) -> Vec<UrlsEntry> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut words = newsboat_words(line).into_iter();
+            let url = words.next().filter(|url| url.starts_with("http://") || url.starts_with("https://"))?;
+            let mut entry = UrlsEntry { url, title: None, tags: Vec::new() };
+            for word in words {
+                if let Some(title) = word.strip_prefix('~') {
+                    entry.title = Some(title.to_string()).filter(|title| !title.is_empty());
+                } else if !word.starts_with('!') && !word.is_empty() {
+                    entry.tags.push(word);
+                }
+            }
+            Some(entry)
+        })
+        .collect()
+}
+
+// The whitespace-separated words of `line`, with double quotes grouping
+// and a backslash escaping the next character inside them.
+fn newsboat_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => word.extend(chars.next()),
+                    c => word.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+    words
+}
+
+/// `path` with a leading `~/` replaced by the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
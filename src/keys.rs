@@ -0,0 +1,536 @@
+use crossterm::event::KeyCode;
+use std::collections::{BTreeMap, HashMap};
+
+/// A layer of key handling. Whichever contexts are active are consulted
+/// top-most first (popup, then search, then normal); a key a context does
+/// not declare falls through to the next one down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    Normal,
+    Search,
+    Confirm,
+    History,
+    Notice,
+    Peek,
+    Link,
+    Preview,
+    Rule,
+    Article,
+    Note,
+    FeedCheck,
+    Help,
+    Setup,
+    Session,
+    DateFilter,
+}
+
+impl Context {
+    /// Every context, in the order the help popup lists them.
+    pub const ALL: [Context; 16] = [
+        Context::Normal,
+        Context::Search,
+        Context::DateFilter,
+        Context::Peek,
+        Context::Article,
+        Context::FeedCheck,
+        Context::History,
+        Context::Preview,
+        Context::Rule,
+        Context::Note,
+        Context::Session,
+        Context::Link,
+        Context::Notice,
+        Context::Confirm,
+        Context::Help,
+        Context::Setup,
+    ];
+
+    /// Whether the context only adds keys to the ones under it, as the
+    /// gone-feed notice does, rather than taking them over like a popup.
+    /// A key it binds that one under it binds too is a conflict.
+    pub fn adds_keys(self) -> bool {
+        matches!(self, Context::Notice)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Context::Normal => "normal",
+            Context::Search => "search",
+            Context::Confirm => "confirm",
+            Context::History => "history",
+            Context::Notice => "notice",
+            Context::Peek => "peek",
+            Context::Link => "link",
+            Context::Preview => "preview",
+            Context::Rule => "rule builder",
+            Context::Article => "article",
+            Context::Note => "note",
+            Context::FeedCheck => "feed check",
+            Context::Help => "help",
+            Context::Setup => "first-run setup",
+            Context::Session => "reading session",
+            Context::DateFilter => "date filter",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    StartSearch,
+    ClearFilter,
+    First,
+    Last,
+    JumpNewAbove,
+    ToggleDisabled,
+    Next,
+    Previous,
+    ToggleGroupByDay,
+    CycleSort,
+    FoldDay,
+    UnfoldDays,
+    MarkRead,
+    UndoMarkRead,
+    ToggleRead,
+    ShowBookmarks,
+    CycleGroup,
+    ToggleDetail,
+    ScrollDetailDown,
+    ScrollDetailUp,
+    OpenListed,
+    OpenListedOther,
+    ReloadConfig,
+    FetchArticle,
+    CheckFeed,
+    Annotate,
+    SaveNote,
+    ShowHistory,
+    ShowHelp,
+    Refresh,
+    Open,
+    SubmitSearch,
+    CancelSearch,
+    ToggleCase,
+    ToggleRegex,
+    SetupUrl,
+    SkipSetup,
+    ToggleSession,
+    StartSession,
+    StartDateFilter,
+    ApplyDateFilter,
+    DeleteChar,
+    InsertChar(char),
+    Accept,
+    Cancel,
+    Close,
+    Expand,
+    RemoveFeed,
+    IgnoreFeed,
+    Peek,
+    Star,
+    Hide,
+    MarkItemRead,
+    CopyLink,
+    Preview,
+    ApplyPreview,
+    DiscardPreview,
+    BuildRule,
+    SwitchField,
+    ToggleField,
+    CycleRuleKind,
+    SaveRule,
+}
+
+pub struct Binding {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+fn bind(key: KeyCode, action: Action, description: &'static str) -> Binding {
+    Binding { key, ctrl: false, action, description }
+}
+
+fn bind_ctrl(key: KeyCode, action: Action, description: &'static str) -> Binding {
+    Binding { key, ctrl: true, action, description }
+}
+
+/// The actions `[keys]` in config.toml can move to other keys, by the name
+/// used there.
+const REMAPPABLE: [(&str, Action); 8] = [
+    ("quit", Action::Quit),
+    ("next", Action::Next),
+    ("previous", Action::Previous),
+    ("update", Action::Refresh),
+    ("open", Action::Open),
+    ("search", Action::StartSearch),
+    ("first", Action::First),
+    ("last", Action::Last),
+];
+
+/// Parses a key as written in config.toml: a single character or a name
+/// such as "down", "enter" or "space", optionally after "ctrl-".
+pub fn parse_key(text: &str) -> Option<(KeyCode, bool)> {
+    let (text, ctrl) = match text.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("ctrl-") => (&text[5..], true),
+        _ => (text, false),
+    };
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some((KeyCode::Char(c), ctrl));
+    }
+    let key = match text.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => return None,
+    };
+    Some((key, ctrl))
+}
+
+/// The keys each context consumes.
+pub struct Keymap {
+    bindings: HashMap<Context, Vec<Binding>>,
+}
+
+impl Keymap {
+    pub fn new() -> Keymap {
+        use Action::*;
+        use KeyCode::{Backspace, Char, Enter, Esc, Tab};
+
+        let mut bindings = HashMap::new();
+        bindings.insert(Context::Normal, vec![
+            bind(Char('q'), Quit, "quit"),
+            bind(Char('/'), StartSearch, "search/filter"),
+            bind(Char('d'), StartDateFilter, "list only the items published in a date range"),
+            bind(Esc, ClearFilter, "clear the filter"),
+            bind(Char('g'), First, "go to first item"),
+            bind(Char('G'), Last, "go to last item"),
+            bind(Char('n'), JumpNewAbove, "jump to the new items above"),
+            bind(Char('x'), ToggleDisabled, "disable/enable the source of the selected error"),
+            bind(Char('j'), Next, "next item"),
+            bind(Char('k'), Previous, "previous item"),
+            bind(Char('D'), ToggleGroupByDay, "group items by day"),
+            bind(Char(','), CycleSort, "cycle the sort order"),
+            bind(Char('s'), CycleSort, "cycle the sort order"),
+            bind(Char('z'), FoldDay, "fold/unfold the selected day"),
+            bind(Char('Z'), UnfoldDays, "unfold all days"),
+            bind(Char('R'), MarkRead, "mark the listed items read"),
+            bind(Char('U'), UndoMarkRead, "undo the last mark read"),
+            bind(Char('r'), ToggleRead, "mark the selected item read/unread"),
+            bind(Char('b'), Star, "bookmark the selected item"),
+            bind(Char('B'), ShowBookmarks, "list only bookmarked items"),
+            bind(Char('N'), Annotate, "write a note on the selected item"),
+            bind(Char('c'), CycleGroup, "show the next group of feeds"),
+            bind(Char('H'), ShowHistory, "show refresh history"),
+            bind(Char('?'), ShowHelp, "show the key bindings"),
+            bind(Char('u'), Refresh, "check for updates"),
+            bind_ctrl(Char('r'), ReloadConfig, "reload config.toml"),
+            bind_ctrl(Char('u'), Preview, "preview what a refresh would bring in"),
+            bind(Char('o'), Open, "open selected link"),
+            bind(Enter, Open, "open selected link"),
+            bind(Char('O'), OpenListed, "open the listed unread items"),
+            bind_ctrl(Char('o'), OpenListedOther, "open the listed unread items the other way (tabs/page)"),
+            bind(Char(' '), Peek, "peek at the selected item"),
+            bind(Char('f'), FetchArticle, "read the full article"),
+            bind(Char('F'), CheckFeed, "fetch the selected item's feed again and show what it says"),
+            bind(Char('p'), ToggleDetail, "show/hide the detail pane"),
+            bind_ctrl(Char('d'), ScrollDetailDown, "scroll the detail pane down"),
+            bind_ctrl(Char('b'), ScrollDetailUp, "scroll the detail pane up"),
+            bind_ctrl(Char('t'), ToggleSession, "start a reading session, or end the one running"),
+            // Terminals send Ctrl-i as Tab.
+            bind(Tab, BuildRule, "build a rule from the selected item"),
+        ]);
+        bindings.insert(Context::Search, vec![
+            bind(Enter, SubmitSearch, "apply the filter"),
+            bind(Esc, CancelSearch, "go back to the previous filter"),
+            bind(Backspace, DeleteChar, "delete a character"),
+            bind_ctrl(Char('t'), ToggleCase, "match case, or go back to the smart_case setting"),
+            bind_ctrl(Char('r'), ToggleRegex, "read the query as a regex, or as text again"),
+            bind_ctrl(Char('e'), ToggleRegex, "read the query as a regex, or as text again"),
+        ]);
+        bindings.insert(Context::Confirm, vec![
+            bind(Char('y'), Accept, "confirm"),
+        ]);
+        bindings.insert(Context::History, vec![
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+            bind(Char('H'), Close, "close"),
+            bind(Char('j'), Next, "next refresh"),
+            bind(Char('k'), Previous, "previous refresh"),
+            bind(Enter, Expand, "expand/collapse"),
+            bind(Char(' '), Expand, "expand/collapse"),
+        ]);
+        bindings.insert(Context::Notice, vec![
+            // Keys the normal context does not bind, so none is shadowed.
+            bind(Char('X'), RemoveFeed, "remove the feed from config, once confirmed"),
+            bind(Char('I'), IgnoreFeed, "ignore for 30 days"),
+        ]);
+        bindings.insert(Context::Peek, vec![
+            bind(Char('o'), Open, "open in the browser"),
+            bind(Char('s'), Star, "star"),
+            bind(Char('d'), Hide, "hide"),
+            bind(Char('r'), MarkItemRead, "mark read"),
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+        ]);
+        bindings.insert(Context::Link, vec![
+            bind(Char('y'), CopyLink, "copy the link"),
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+        ]);
+        bindings.insert(Context::Preview, vec![
+            bind(Char('a'), ApplyPreview, "add the items to the list"),
+            bind(Enter, ApplyPreview, "add the items to the list"),
+            bind(Char('d'), DiscardPreview, "discard the items"),
+            bind(Esc, DiscardPreview, "discard the items"),
+        ]);
+        bindings.insert(Context::Rule, vec![
+            bind(Tab, SwitchField, "switch between source and title"),
+            bind_ctrl(Char('t'), ToggleField, "match on the field or not"),
+            bind_ctrl(Char('a'), CycleRuleKind, "cycle hide/mark read/highlight"),
+            bind(Enter, SaveRule, "save the rule to config.toml"),
+            bind(Esc, Close, "cancel"),
+            bind(Backspace, DeleteChar, "delete a character"),
+        ]);
+        bindings.insert(Context::Article, vec![
+            bind(Char('j'), Next, "scroll down"),
+            bind(Char('k'), Previous, "scroll up"),
+            bind(Char(' '), Expand, "scroll down a page"),
+            bind(Char('o'), Open, "open in the browser"),
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+        ]);
+        bindings.insert(Context::Note, vec![
+            bind(Enter, SaveNote, "save the note (empty deletes it)"),
+            bind(Esc, Close, "cancel"),
+            bind(Backspace, DeleteChar, "delete a character"),
+        ]);
+        bindings.insert(Context::FeedCheck, vec![
+            bind(Char('j'), Next, "scroll down"),
+            bind(Char('k'), Previous, "scroll up"),
+            bind(Char(' '), Expand, "scroll down a page"),
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+        ]);
+        bindings.insert(Context::Help, vec![
+            bind(Char('j'), Next, "scroll down"),
+            bind(Char('k'), Previous, "scroll up"),
+            bind(Char(' '), Expand, "scroll down a page"),
+            bind(Esc, Close, "close"),
+            bind(Char('q'), Close, "close"),
+            bind(Char('?'), Close, "close"),
+        ]);
+        bindings.insert(Context::DateFilter, vec![
+            bind(Enter, ApplyDateFilter, "apply the range (empty clears it)"),
+            bind(Esc, Close, "cancel"),
+            bind(Backspace, DeleteChar, "delete a character"),
+        ]);
+        bindings.insert(Context::Session, vec![
+            bind(Enter, StartSession, "start the session, for that many minutes if given"),
+            bind(Esc, Close, "cancel"),
+            bind(Backspace, DeleteChar, "delete a character"),
+        ]);
+        bindings.insert(Context::Setup, vec![
+            bind(Enter, SetupUrl, "look for the feed at the URL; on an empty line, write config.toml"),
+            bind(Esc, SkipSetup, "skip the setup"),
+            bind(Backspace, DeleteChar, "delete a character"),
+        ]);
+        Keymap { bindings }
+    }
+
+    pub fn bindings(&self, context: Context) -> &[Binding] {
+        self.bindings.get(&context).map_or(&[], Vec::as_slice)
+    }
+
+    /// The bindings of `context` as the help popup lists them: the keys of
+    /// each action joined, e.g. ("o or Enter", "open selected link"), in the
+    /// order they were bound. Remapped keys show where they moved to.
+    pub fn help(&self, context: Context) -> Vec<(String, &'static str)> {
+        let mut entries: Vec<(Action, String, &'static str)> = Vec::new();
+        for b in self.bindings(context) {
+            let key = key_name(b.key, b.ctrl);
+            match entries.iter_mut().find(|(action, _, _)| *action == b.action) {
+                Some((_, keys, _)) => {
+                    keys.push_str(" or ");
+                    keys.push_str(&key);
+                }
+                None => entries.push((b.action, key, b.description)),
+            }
+        }
+        entries.into_iter().map(|(_, keys, description)| (keys, description)).collect()
+    }
+
+    /// Finds the action for `key`, with Ctrl held if `ctrl`, trying `stack`
+    /// from the top down. Search, notes and the rule builder take every other
+    /// character as text, and a confirmation treats any other key as "no",
+    /// so nothing falls through any of them by accident.
+    pub fn route(&self, stack: &[Context], key: KeyCode, ctrl: bool) -> Option<(Context, Action)> {
+        for &context in stack {
+            if let Some(binding) = self.bindings(context).iter().find(|b| b.key == key && b.ctrl == ctrl) {
+                return Some((context, binding.action));
+            }
+            match (context, key) {
+                (Context::Search | Context::Rule | Context::Note | Context::Setup | Context::Session | Context::DateFilter, KeyCode::Char(c)) if !ctrl => return Some((context, Action::InsertChar(c))),
+                (Context::Confirm, _) => return Some((context, Action::Cancel)),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Moves actions of the normal context to the keys `[keys]` in
+    /// config.toml gives them, e.g. `next = "ctrl-n"`; the keys they had
+    /// before are freed. Returns why each entry that was not used was not.
+    pub fn remap(&mut self, keys: &BTreeMap<String, String>) -> Vec<String> {
+        let mut errors = Vec::new();
+        let normal = self.bindings.entry(Context::Normal).or_default();
+        for (name, key) in keys {
+            let Some(&(_, action)) = REMAPPABLE.iter().find(|(remappable, _)| remappable == name) else {
+                let names: Vec<&str> = REMAPPABLE.iter().map(|(name, _)| *name).collect();
+                errors.push(format!("[keys] {} = \"{}\": not an action that can be remapped ({})", name, key, names.join(", ")));
+                continue;
+            };
+            let Some((code, ctrl)) = parse_key(key) else {
+                errors.push(format!("[keys] {} = \"{}\": not a key", name, key));
+                continue;
+            };
+            let description = normal.iter().find(|b| b.action == action).map_or("", |b| b.description);
+            normal.retain(|b| b.action != action);
+            normal.push(Binding { key: code, ctrl, action, description });
+        }
+        errors
+    }
+
+    /// Keys bound to more than one action within the same context, and, in
+    /// each of `stacks` (as route takes them), keys of a context that only
+    /// adds keys which hide a binding of a context under it.
+    pub fn conflicts(&self, stacks: &[&[Context]]) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for context in Context::ALL {
+            let bindings = self.bindings(context);
+            for (i, a) in bindings.iter().enumerate() {
+                if let Some(b) = bindings[..i].iter().find(|b| b.key == a.key && b.ctrl == a.ctrl && b.action != a.action) {
+                    conflicts.push(format!(
+                        "'{}' is bound to both '{}' and '{}' in {} mode",
+                        key_name(a.key, a.ctrl), b.description, a.description, context.name()
+                    ));
+                }
+            }
+        }
+        for (top, below) in stacks.iter().flat_map(|stack| stack.iter().enumerate().map(|(i, &top)| (top, &stack[i + 1..]))) {
+            if !top.adds_keys() {
+                continue;
+            }
+            for a in self.bindings(top) {
+                for &under in below {
+                    let Some(b) = self.bindings(under).iter().find(|b| b.key == a.key && b.ctrl == a.ctrl) else { continue };
+                    let conflict = format!(
+                        "'{}' for '{}' in {} mode hides '{}' of {} mode",
+                        key_name(a.key, a.ctrl), a.description, top.name(), b.description, under.name()
+                    );
+                    if !conflicts.contains(&conflict) {
+                        conflicts.push(conflict);
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+pub fn key_name(key: KeyCode, ctrl: bool) -> String {
+    if ctrl {
+        return format!("Ctrl-{}", key_name(key, false));
+    }
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use KeyCode::{Char, Enter, Esc};
+
+    fn keys(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(name, key)| (name.to_string(), key.to_string())).collect()
+    }
+
+    #[test]
+    fn keys_a_popup_does_not_bind_fall_through_to_normal() {
+        let keymap = Keymap::new();
+        let peek = [Context::Peek, Context::Normal];
+        assert_eq!(keymap.route(&peek, Char('o'), false), Some((Context::Peek, Action::Open)));
+        assert_eq!(keymap.route(&peek, Char('j'), false), Some((Context::Normal, Action::Next)));
+        assert_eq!(keymap.route(&[Context::Link], Char('j'), false), None);
+    }
+
+    #[test]
+    fn text_inputs_keep_characters_but_pass_on_ctrl_keys() {
+        let keymap = Keymap::new();
+        let search = [Context::Search, Context::Normal];
+        assert_eq!(keymap.route(&search, Char('q'), false), Some((Context::Search, Action::InsertChar('q'))));
+        assert_eq!(keymap.route(&search, Enter, false), Some((Context::Search, Action::SubmitSearch)));
+        assert_eq!(keymap.route(&search, Char('u'), true), Some((Context::Normal, Action::Preview)));
+    }
+
+    #[test]
+    fn a_confirmation_takes_any_other_key_as_no() {
+        let keymap = Keymap::new();
+        assert_eq!(keymap.route(&[Context::Confirm], Char('y'), false), Some((Context::Confirm, Action::Accept)));
+        assert_eq!(keymap.route(&[Context::Confirm], Char('q'), false), Some((Context::Confirm, Action::Cancel)));
+        assert_eq!(keymap.route(&[Context::Confirm], Esc, false), Some((Context::Confirm, Action::Cancel)));
+    }
+
+    #[test]
+    fn the_default_keymap_has_no_conflicts() {
+        let stacks: [&[Context]; 3] = [&[Context::Normal], &[Context::Notice, Context::Normal], &[Context::Peek, Context::Normal]];
+        assert_eq!(Keymap::new().conflicts(&stacks), Vec::<String>::new());
+    }
+
+    #[test]
+    fn remapping_frees_the_old_key_and_reports_a_clash() {
+        let mut keymap = Keymap::new();
+        assert!(keymap.remap(&keys(&[("next", "ctrl-n")])).is_empty());
+        assert_eq!(keymap.route(&[Context::Normal], Char('n'), true), Some((Context::Normal, Action::Next)));
+        assert_eq!(keymap.route(&[Context::Normal], Char('j'), false), None);
+        assert!(keymap.conflicts(&[]).is_empty());
+
+        keymap.remap(&keys(&[("next", "k")]));
+        assert_eq!(keymap.conflicts(&[]), ["'k' is bound to both 'previous item' and 'next item' in normal mode"]);
+    }
+
+    #[test]
+    fn a_context_that_adds_keys_must_not_hide_the_ones_under_it() {
+        let mut keymap = Keymap::new();
+        let stacks: [&[Context]; 2] = [&[Context::Notice, Context::Normal], &[Context::Peek, Context::Normal]];
+        // Peek takes over q, s, d and r by design; only the notice is checked.
+        keymap.remap(&keys(&[("quit", "X")]));
+        assert_eq!(
+            keymap.conflicts(&stacks),
+            ["'X' for 'remove the feed from config, once confirmed' in notice mode hides 'quit' of normal mode"]
+        );
+    }
+
+    #[test]
+    fn remap_reports_unknown_actions_and_keys() {
+        let errors = Keymap::new().remap(&keys(&[("next", "ctrl-nope"), ("jump", "x")]));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("[keys] jump = \"x\": not an action that can be remapped"));
+        assert_eq!(errors[1], "[keys] next = \"ctrl-nope\": not a key");
+    }
+}
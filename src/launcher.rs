@@ -0,0 +1,58 @@
+use crate::opml::escape;
+use serde::Deserialize;
+use std::{io, path::PathBuf};
+
+/// How `O` opens the listed items, from `bulk_open` in config.toml. Ctrl-o
+/// does the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkOpen {
+    #[default]
+    Tabs,
+    Page,
+}
+
+impl BulkOpen {
+    pub fn other(self) -> BulkOpen {
+        match self {
+            BulkOpen::Tabs => BulkOpen::Page,
+            BulkOpen::Page => BulkOpen::Tabs,
+        }
+    }
+}
+
+/// An item on the launcher page.
+pub struct Link<'a> {
+    pub title: &'a str,
+    pub source: Option<&'a str>,
+    pub date: Option<&'a str>,
+    pub url: &'a str,
+}
+
+/// A page listing `links`, to open the ones wanted from one browser tab
+/// instead of a tab for each.
+pub fn page(links: &[Link]) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>br</title>\n");
+    html.push_str("<style>body { font-family: sans-serif; max-width: 50em; margin: 2em auto; } li { margin: 0.5em 0; } small { color: gray; }</style>\n");
+    html.push_str(&format!("</head>\n<body>\n<h1>{} items</h1>\n<ol>\n", links.len()));
+    for link in links {
+        let details: Vec<String> = link.source.into_iter().chain(link.date).map(|detail| escape(detail.trim())).collect();
+        html.push_str(&format!("<li><a href=\"{}\">{}</a>", escape(link.url), escape(link.title)));
+        if !details.is_empty() {
+            html.push_str(&format!(" <small>{}</small>", details.join(" · ")));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ol>\n</body>\n</html>\n");
+    html
+}
+
+/// Writes `html` to launcher.html in the cache directory, replacing the
+/// last one, and returns its path.
+pub fn write(html: &str) -> io::Result<PathBuf> {
+    let dir = dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("br");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("launcher.html");
+    std::fs::write(&path, html)?;
+    Ok(path)
+}
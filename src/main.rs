@@ -1,129 +1,779 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyModifiers, MouseButton, MouseEventKind},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
+    },
     Frame, Terminal,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use scraper::{Html, Selector};
 use sha2::{Digest, Sha256};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     error::Error,
-    io,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tokio::sync::mpsc;
+use tokio::{
+    sync::{mpsc, oneshot, Semaphore},
+    task::JoinHandle,
+};
 use feed_rs::parser as feed_parser;
 
+mod backup;
+mod categories;
+mod cli;
+mod clock;
+mod columns;
+mod config_store;
+mod content;
+mod control;
+mod discover;
+mod days;
+mod diagnose;
+mod event_log;
+mod guid;
+mod health;
+mod history;
+mod import;
+mod keys;
+mod launcher;
+mod network;
+mod notify;
+mod opener;
+mod opml;
+mod reading;
+mod rules;
+mod search;
+mod session;
+mod settings;
+mod skew;
+mod sort;
+mod summary;
+#[cfg(test)]
+mod testing;
+mod storage;
+mod theme;
+mod translate;
+
+use categories::Categories;
+use columns::Columns;
+use clap::Parser;
+use cli::{BackupCommand, Cli, Command, ConfigCommand, Format};
+use control::Request;
+use clock::{Clock, SystemClock};
+use content::ContentStore;
+use event_log::{EventLog, Level};
+use health::Health;
+use history::RefreshSummary;
+use keys::{Action, Context, Keymap};
+use launcher::BulkOpen;
+use reading::{Activity, ReadingSession};
+use opener::Opener;
+use rules::{Alert, Matcher, Rule, RuleAction};
+use search::{CaseMode, Query, SearchKey, SearchMode};
+use settings::Settings;
+use unicode_width::UnicodeWidthStr;
+use sort::SortOrder;
+use summary::ShowSnippets;
+use storage::Storage;
+use theme::{parse_color, ColorMode, Palette, Theme};
+
 #[derive(Debug, Deserialize, Clone)]
 struct Feed {
     name: String,
-    url: String,
+    url: FeedUrls,
+    translate_titles: Option<String>, // target language, with [translate] configured
+    max_entries: Option<usize>, // overrides the top-level max_entries
+    max_retries: Option<u32>, // overrides [network] max_retries
+    color: Option<String>, // of its items in the list, instead of the theme's feed color
+    #[serde(alias = "category")]
+    group: Option<String>, // for @group filters
+    username: Option<String>, // basic auth, with password or password_env
+    password: Option<String>,
+    password_env: Option<String>, // the variable holding the password
+    headers: Option<BTreeMap<String, String>>, // sent with every request
+    #[serde(default)]
+    disabled: bool, // kept in the config but skipped by refreshes
+    #[serde(default)]
+    prefer_guid_link: bool, // open the permalink guid instead of <link>
+}
+
+// A feed's URL, or several mirrors of the same feed tried in order.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+enum FeedUrls {
+    One(String),
+    Mirrors(Vec<String>),
+}
+
+impl FeedUrls {
+    fn all(&self) -> &[String] {
+        match self {
+            FeedUrls::One(url) => std::slice::from_ref(url),
+            FeedUrls::Mirrors(urls) => urls,
+        }
+    }
+}
+
+impl Feed {
+    // A feed with nothing but a name, a URL and maybe a group set.
+    fn new(name: String, url: String, group: Option<String>) -> Feed {
+        Feed {
+            name,
+            url: FeedUrls::One(url),
+            translate_titles: None,
+            max_entries: None,
+            max_retries: None,
+            color: None,
+            group,
+            username: None,
+            password: None,
+            password_env: None,
+            headers: None,
+            disabled: false,
+            prefer_guid_link: false,
+        }
+    }
+
+    // What per-feed state is kept under: the first URL, which unlike the
+    // name cannot be shared with another feed by mistake.
+    fn key(&self) -> &str {
+        self.url.all().first().map_or(&self.name, String::as_str)
+    }
+
+    // A GET for `url` with the feed's headers and credentials. Fails when
+    // password_env names a variable that is not set.
+    fn request(&self, http: &reqwest::Client, url: &str) -> Result<reqwest::RequestBuilder, String> {
+        let mut request = http.get(url);
+        for (name, value) in self.headers.iter().flatten() {
+            request = request.header(name, value);
+        }
+        let password = match &self.password_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| format!("password_env {} is not set", var))?),
+            None => self.password.clone(),
+        };
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, password);
+        }
+        Ok(request)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct Manual {
     name: String,
     url: String,
+    cooldown_minutes: Option<u64>, // default 60, 0 disables throttling
+    method: Option<Method>,
+    headers: Option<Vec<String>>, // compared instead of the body with method = "head"
+    selector: Option<String>, // CSS; only the text of matching elements is compared
+    max_retries: Option<u32>, // overrides [network] max_retries
+    #[serde(alias = "category")]
+    group: Option<String>,
+    #[serde(default)]
+    disabled: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+// How a manual site is checked: by hashing the page, or only some of its
+// response headers.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum Method {
+    #[default]
+    Get,
+    Head,
+}
+
+const WATCHED_HEADERS: [&str; 3] = ["ETag", "Last-Modified", "Content-Length"];
+
+// The feeds and sections of config.toml. Top-level options are resolved
+// with their defaults and overrides in settings::Settings.
+#[derive(Debug, Deserialize, Clone, Default)]
 struct Config {
     feeds: Option<Vec<Feed>>,
     manual: Option<Vec<Manual>>,
+    translate: Option<translate::Backend>,
+    rules: Option<Vec<Rule>>,
+    keys: Option<BTreeMap<String, String>>, // action name -> key, see keys::Keymap::remap
+    network: Option<network::Network>,
+}
+
+// The sections of Config, which config.toml may have besides the options.
+const SECTIONS: [&str; 6] = ["feeds", "manual", "translate", "rules", "keys", "network"];
+
+impl Config {
+    // Disables the feed or manual site called `name`, or enables it again.
+    // Returns whether it is now disabled, or None if there is no such source.
+    fn toggle_disabled(&mut self, name: &str) -> Option<bool> {
+        let disabled = self.feeds.iter_mut().flatten().find(|feed| feed.name == name).map(|feed| &mut feed.disabled)
+            .or_else(|| self.manual.iter_mut().flatten().find(|site| site.name == name).map(|site| &mut site.disabled))?;
+        *disabled = !*disabled;
+        Some(*disabled)
+    }
+
+    // Adds the feeds of newsboat's urls file that config.toml does not list
+    // already, named after their ~title and grouped by their first tag.
+    // Returns how many were added and how many were already listed.
+    fn merge_newsboat(&mut self, entries: Vec<import::UrlsEntry>) -> (usize, usize) {
+        let feeds = self.feeds.get_or_insert_with(Vec::new);
+        let mut pages: HashSet<String> = feeds.iter().flat_map(|feed| feed.url.all().iter().map(|url| same_page(url))).collect();
+        let (mut added, mut listed) = (0, 0);
+        for entry in entries {
+            if !pages.insert(same_page(&entry.url)) {
+                listed += 1;
+                continue;
+            }
+            let name = entry.title.unwrap_or_else(|| entry.url.clone());
+            feeds.push(Feed::new(name, entry.url, entry.tags.into_iter().next()));
+            added += 1;
+        }
+        (added, listed)
+    }
+
+    // Manual sites at the very URL of a feed, which would announce the same
+    // page twice, are dropped when `skip`. Returns a warning for each such
+    // site, and a note for each site that only shares a host with a feed.
+    fn check_overlaps(&mut self, skip: bool) -> (Vec<String>, Vec<String>) {
+        let feeds: Vec<(String, String)> = self.feeds.iter().flatten()
+            .flat_map(|feed| feed.url.all().iter().map(|url| (feed.name.clone(), url.clone())).collect::<Vec<_>>())
+            .collect();
+        let host = |url: &str| url::Url::parse(url).ok().and_then(|url| url.host_str().map(String::from));
+        let (mut warnings, mut notes) = (Vec::new(), Vec::new());
+        self.manual.get_or_insert_with(Vec::new).retain(|site| {
+            if let Some((feed, _)) = feeds.iter().find(|(_, url)| same_page(url) == same_page(&site.url)) {
+                let outcome = if skip { "skipping the site" } else { "checking both" };
+                warnings.push(format!("Manual site '{}' has the same URL as feed '{}'; {} (see skip_duplicate_manual)", site.name, feed, outcome));
+                return !skip;
+            }
+            let site_host = host(&site.url);
+            for (feed, _) in feeds.iter().filter(|(_, url)| site_host.is_some() && host(url) == site_host) {
+                notes.push(format!("manual site '{}' and feed '{}' are both on {}", site.name, feed, site_host.as_deref().unwrap_or_default()));
+            }
+            true
+        });
+        notes.dedup();
+        (warnings, notes)
+    }
+
+    // Feeds and manual sites that share a name are listed as "Blog
+    // (example.com)" instead, or with the whole URL where the host is shared
+    // too. Returns a warning for each name that was taken more than once.
+    fn disambiguate_names(&mut self) -> Vec<String> {
+        let mut feeds = self.feeds.iter_mut().flatten().map(|feed| {
+            let url = feed.key().to_string();
+            (&mut feed.name, url)
+        }).collect::<Vec<_>>();
+        let manual = self.manual.iter_mut().flatten().map(|site| (&mut site.name, site.url.clone()));
+        feeds.extend(manual);
+        let mut by_name: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, (name, _)) in feeds.iter().enumerate() {
+            by_name.entry(name.to_string()).or_default().push(i);
+        }
+        let mut warnings = Vec::new();
+        for (name, shared) in by_name.into_iter().filter(|(_, shared)| shared.len() > 1) {
+            let host = |url: &str| url::Url::parse(url).ok().and_then(|url| url.host_str().map(String::from));
+            let hosts: Vec<Option<String>> = shared.iter().map(|&i| host(&feeds[i].1)).collect();
+            for (&i, host) in shared.iter().zip(&hosts) {
+                let (name, url) = &mut feeds[i];
+                let suffix = match host {
+                    Some(host) if hosts.iter().filter(|other| other.as_ref() == Some(host)).count() == 1 => host,
+                    _ => url,
+                };
+                **name = format!("{} ({})", name, suffix);
+            }
+            warnings.push(format!("{} feeds are named '{}'; listing each with its host or URL", shared.len(), name));
+        }
+        warnings
+    }
+}
+
+// `url` as far as telling pages apart goes: without the scheme, the
+// fragment, a leading "www." and a trailing slash, so http://example.com/
+// and https://www.example.com are the same page.
+fn same_page(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    parsed.set_fragment(None);
+    let host = parsed.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host).to_string();
+    let rest = &parsed[url::Position::BeforePort..];
+    format!("{}{}", host, rest.trim_end_matches('/'))
+}
+
+// What is kept of a feed entry.
+#[derive(Debug)]
+struct Entry {
+    title: String,
+    link: String, // what 'o' opens
+    permalink: Option<String>, // the guid, when it is one
+    alternate: Option<String>, // the other of <link> and the permalink, when they differ
+    published: Option<DateTime<Utc>>,
+    author: Option<String>,
+    summary: Option<String>, // plain text, at most max_content_bytes
+    summary_bytes: Option<usize>, // the summary's size before it was cut
+    categories: Categories,
+    translated: Option<translate::Translated>,
+    group: Option<String>, // the feed's
+}
+
+impl Entry {
+    // What it is told apart from other entries by, across fetches and runs.
+    fn key(&self) -> &str {
+        self.permalink.as_deref().unwrap_or(&self.link)
+    }
 }
 
 #[derive(Debug)]
 enum Update {
-    NewFeedItem(String, String, String, Option<DateTime<Utc>>), // blog name, title, link, date
-    ManualUpdate(String, String),
+    NewFeedItem(String, Entry), // blog name, entry
+    ManualChange(String, String, String, Option<String>), // site name, message, link, group
+    Finished(u64), // a fetch task is done, with the bytes it downloaded
+    FeedStatus(String, String, Option<u16>), // feed name, key, the 404/410 status if it looks gone
+    HealthWarning(String, String, Option<String>), // source name, URL, warning or None to clear it
+    ServerDate(DateTime<Utc>, DateTime<Utc>), // a response's Date header, local time it arrived
+    TranslateError(String, String), // feed name, error
+    Control(Request, oneshot::Sender<serde_json::Value>), // from the control socket, with where to answer
+    Subscribed(Feed), // added to config.toml through the control socket
+    SetupFound(String, Result<(String, Option<String>), String>), // URL typed in the first-run setup, the feed found there
+    FullArticle(String, String), // link, the page's text
+    FeedReport(String, diagnose::Report), // item key, what fetching its feed showed
+    SourceError(String, String), // feed or site name, error
     Error(String),
     Info(String),
 }
 
-type Cache = Arc<Mutex<HashMap<String, String>>>;
+// Per-site state for manual tracking, and the validators a feed was last
+// served with, keyed by URL in cache.json.
+//
+// The validators are not saved: a 304 only means nothing changed since
+// what this session listed, and a new session has listed nothing yet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheEntry {
+    hash: String,
+    #[serde(skip)]
+    etag: Option<String>, // sent back as If-None-Match
+    #[serde(skip)]
+    last_modified: Option<String>, // sent back as If-Modified-Since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notified_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    suppressed: u32, // changes seen since notified_at that were not reported
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>, // last values seen with method = "head"
+}
 
-async fn fetch_feed(feed: Feed, tx: mpsc::Sender<Update>) {
-    let response = match reqwest::get(&feed.url).await {
-        Ok(res) => res,
-        Err(e) => {
-            let error_msg = format!("[ERROR] fetching {}: {}", feed.name, e);
-            let _ = tx.send(Update::Error(error_msg)).await;
-            return;
+impl CacheEntry {
+    // Counts a change of the site's content. Returns the message reporting
+    // it, or None while the last report's cooldown lasts; the next report
+    // mentions how many were held back.
+    fn record_change(&mut self, name: &str, now: DateTime<Utc>, cooldown: chrono::Duration) -> Option<String> {
+        if self.notified_at.is_some_and(|at| now - at < cooldown) {
+            self.suppressed += 1;
+            return None;
         }
-    };
+        let mut message = format!("New content detected on {}", name);
+        if self.suppressed > 0 {
+            message.push_str(&format!(" (+{} more changes during cooldown)", self.suppressed));
+        }
+        self.notified_at = Some(now);
+        self.suppressed = 0;
+        Some(message)
+    }
+}
 
-    let bytes = match response.bytes().await {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            let error_msg = format!("[ERROR] reading bytes for {}: {}", feed.name, e);
-            let _ = tx.send(Update::Error(error_msg)).await;
-            return;
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+// cache.json used to map each URL straight to its hash.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredCacheEntry {
+    Hash(String),
+    Entry(CacheEntry),
+}
+
+impl From<StoredCacheEntry> for CacheEntry {
+    fn from(stored: StoredCacheEntry) -> CacheEntry {
+        match stored {
+            StoredCacheEntry::Hash(hash) => CacheEntry { hash, ..Default::default() },
+            StoredCacheEntry::Entry(entry) => entry,
+        }
+    }
+}
+
+type Cache = Arc<Mutex<HashMap<String, CacheEntry>>>;
+
+// Both ends of the update channel. App keeps a sender to hand to the tasks
+// it starts, so the channel stays open while br runs; a fetch task that dies
+// without reporting back is noticed through its JoinHandle instead, see
+// App::reap_tasks.
+struct Channel {
+    tx: mpsc::Sender<Update>,
+    rx: mpsc::Receiver<Update>,
+}
+
+impl Channel {
+    fn new() -> Channel {
+        let (tx, rx) = mpsc::channel(100);
+        Channel { tx, rx }
+    }
+}
+
+enum FetchError {
+    Auth(String), // the credentials could not be read
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+    Body(reqwest::Error),
+    Parse(feed_parser::ParseFeedError),
+}
+
+impl FetchError {
+    // Worth asking again after a while: no answer at all, or a server that
+    // is failing or busy for now.
+    fn is_transient(&self) -> bool {
+        match self {
+            FetchError::Request(_) => true,
+            FetchError::Status(status) => status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+
+    // What went wrong, without the details, for listing several attempts.
+    fn class(&self) -> String {
+        match self {
+            FetchError::Auth(_) => "no credentials".to_string(),
+            FetchError::Request(e) if e.is_timeout() => "timed out".to_string(),
+            FetchError::Request(e) if e.is_connect() => "connection failed".to_string(),
+            FetchError::Request(_) => "request failed".to_string(),
+            FetchError::Status(status) => format!("HTTP {}", status.as_u16()),
+            FetchError::Body(_) => "body unreadable".to_string(),
+            FetchError::Parse(_) => "not a valid feed".to_string(),
         }
+    }
+
+    fn describe(&self, name: &str) -> String {
+        match self {
+            FetchError::Auth(e) => format!("fetching {}: {}", name, e),
+            FetchError::Request(e) if e.is_timeout() => format!("fetching {}: timed out", name),
+            FetchError::Request(e) => format!("fetching {}: {}", name, e),
+            FetchError::Status(reqwest::StatusCode::UNAUTHORIZED) => format!("401 Unauthorized for {}", name),
+            FetchError::Status(reqwest::StatusCode::FORBIDDEN) => {
+                format!("fetching {}: HTTP 403 Forbidden (if the server turns away br, try user_agent in [network])", name)
+            }
+            FetchError::Status(status) => format!("fetching {}: HTTP {}", name, status),
+            FetchError::Body(e) => format!("reading bytes for {}: {}", name, e),
+            FetchError::Parse(e) => format!("parsing feed for {}: {}", name, e),
+        }
+    }
+}
+
+// `date` is set to the response's Date header and the local time it arrived.
+// Returns the feed and the guids it marks as not permalinks, or None when
+// the server answers 304 to the validators in `cache`. Transient failures
+// are retried up to `retries` times before they count.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_and_parse(http: &reqwest::Client, feed: &Feed, url: &str, cache: &Cache, retries: u32, downloaded: &mut u64, date: &mut Option<(DateTime<Utc>, DateTime<Utc>)>, clock: &dyn Clock) -> Result<Option<(feed_rs::model::Feed, HashSet<String>)>, FetchError> {
+    let validators = cache.lock().unwrap().get(url).map(|entry| (entry.etag.clone(), entry.last_modified.clone()));
+    let send = || async {
+        let mut request = feed.request(http, url).map_err(FetchError::Auth)?;
+        if let Some((etag, last_modified)) = &validators {
+            if let Some(etag) = etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await.map_err(FetchError::Request)?;
+        let status = response.status();
+        if !status.is_success() && status != reqwest::StatusCode::NOT_MODIFIED {
+            return Err(FetchError::Status(status));
+        }
+        Ok(response)
     };
+    let response = network::retry_with_backoff(send, retries, network::INITIAL_BACKOFF, FetchError::is_transient).await?;
+    *date = skew::server_date(&response).map(|server| (server, clock.now_utc()));
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let (etag, last_modified) = (header(reqwest::header::ETAG), header(reqwest::header::LAST_MODIFIED));
+    let bytes = response.bytes().await.map_err(FetchError::Body)?;
+    *downloaded += bytes.len() as u64;
+    let parsed = feed_parser::parse(&bytes[..]).map_err(FetchError::Parse)?;
+    // Only once the feed is known to be readable, or a 304 would hide it.
+    let mut cache = cache.lock().unwrap();
+    let entry = cache.entry(url.to_string()).or_default();
+    entry.etag = etag;
+    entry.last_modified = last_modified;
+    Ok(Some((parsed, guid::not_permalinks(&bytes))))
+}
 
-    match feed_parser::parse(&bytes[..]) {
-        Ok(parsed_feed) => {
-            for entry in parsed_feed.entries.iter().take(5) {
-                let title = entry.title.clone().map_or_else(|| "No Title".to_string(), |t| t.content);
-                let link = entry.links.first().map_or("", |l| &l.href).to_string();
-                // Extract the date - use updated as a fallback for published
-                let date = entry.published.or(entry.updated);
-                
-                if let Err(e) = tx.send(Update::NewFeedItem(feed.name.clone(), title, link, date)).await {
-                    eprintln!("Failed to send feed update: {}", e);
-                    break;
+// Returns the number of bytes downloaded.
+// `translation` is the backend and cache to translate titles with, for feeds
+// that ask for it. `max_entries` and `max_retries` apply unless the feed sets
+// its own. `cache` holds the validators of this session's earlier fetches.
+// Summaries longer than `max_content_bytes` are cut.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_feed(http: &reqwest::Client, feed: &Feed, cache: &Cache, tx: mpsc::Sender<Update>, clock: Arc<dyn Clock>, translation: Option<(translate::Backend, translate::Cache)>, max_entries: usize, max_content_bytes: usize, max_retries: u32) -> u64 {
+    let urls = feed.url.all();
+    let mut downloaded = 0;
+    let mut date = None;
+    let mut failures = Vec::new();
+    let mut parsed = None;
+    let retries = feed.max_retries.unwrap_or(max_retries);
+    for (i, url) in urls.iter().enumerate() {
+        let result = fetch_and_parse(http, feed, url, cache, retries, &mut downloaded, &mut date, clock.as_ref()).await;
+        if let Some((server, local)) = date.take() {
+            let _ = tx.send(Update::ServerDate(server, local)).await;
+        }
+        match result {
+            Ok(fetched) => {
+                if i > 0 {
+                    let _ = tx.send(Update::Info(format!("{} served by mirror {}", feed.name, url))).await;
                 }
+                parsed = Some(fetched);
+                break;
             }
+            Err(e) => failures.push((url, e)),
         }
-        Err(e) => {
-            let error_msg = format!("[ERROR] parsing feed for {}: {}", feed.name, e);
-            let _ = tx.send(Update::Error(error_msg)).await;
+    }
+
+    let Some(parsed) = parsed else {
+        let error_msg = match failures.as_slice() {
+            [] => format!("no url configured for {}", feed.name),
+            [(_, e)] => e.describe(&feed.name),
+            _ => {
+                let attempts: Vec<_> = failures.iter().map(|(url, e)| format!("{}: {}", url, e.class())).collect();
+                format!("fetching {}: all {} mirrors failed ({})", feed.name, failures.len(), attempts.join("; "))
+            }
+        };
+        let _ = tx.send(Update::SourceError(feed.name.clone(), error_msg)).await;
+        // Only count the feed as gone when no mirror served anything else.
+        let gone: Vec<u16> = failures.iter().filter_map(|(_, e)| match e {
+            FetchError::Status(status) if matches!(status.as_u16(), 404 | 410) => Some(status.as_u16()),
+            _ => None,
+        }).collect();
+        if !failures.is_empty() && gone.len() == failures.len() {
+            let _ = tx.send(Update::FeedStatus(feed.name.clone(), feed.key().to_string(), gone.last().copied())).await;
+        }
+        return downloaded;
+    };
+    let _ = tx.send(Update::FeedStatus(feed.name.clone(), feed.key().to_string(), None)).await;
+    // What is listed from the last fetch is still current.
+    let Some((parsed_feed, not_permalinks)) = parsed else {
+        let _ = tx.send(Update::Info(format!("No changes for {}", feed.name))).await;
+        return downloaded;
+    };
+    let warning = health::suspicious(&parsed_feed.entries);
+    let _ = tx.send(Update::HealthWarning(feed.name.clone(), feed.key().to_string(), warning)).await;
+
+    // After one failure the rest of the feed is left untranslated.
+    let mut translation = translation.zip(feed.translate_titles.clone());
+    // 0 takes the whole feed.
+    let limit = match feed.max_entries.unwrap_or(max_entries) {
+        0 => usize::MAX,
+        n => n,
+    };
+    for entry in parsed_feed.entries.iter().take(limit) {
+        let title = entry.title.clone().map_or_else(|| "No Title".to_string(), |t| t.content);
+        let mut link = entry.links.first().map_or("", |l| &l.href).to_string();
+        let permalink = guid::permalink(&parsed_feed, entry, &not_permalinks);
+        let mut alternate = permalink.clone().filter(|permalink| *permalink != link);
+        if feed.prefer_guid_link && let Some(permalink) = &alternate {
+            alternate = Some(std::mem::replace(&mut link, permalink.clone()));
+        }
+        // A permalink stays the same when a tracking <link> changes.
+        let key = permalink.as_deref().unwrap_or(&link).to_string();
+        // Extract the date - use updated as a fallback for published
+        let published = entry.published.or(entry.updated);
+        let author = entry.authors.first().map(|a| a.name.clone());
+        let mut summary = entry.summary.as_ref().map(|s| s.content.as_str())
+            .or_else(|| entry.content.as_ref().and_then(|c| c.body.as_deref()))
+            .and_then(summary::plain_text);
+        // Before it is translated, stored or searched.
+        let summary_bytes = summary.as_mut().and_then(|summary| summary::cap(summary, max_content_bytes));
+        let categories = Categories::new(entry.categories.iter().map(|c| c.term.as_str()));
+
+        let translated = match &translation {
+            Some(((backend, cache), target)) => {
+                match translate::translate(http, backend, cache, &key, &title, summary.as_deref(), target).await {
+                    Ok(translated) => Some(translated),
+                    Err(e) => {
+                        let _ = tx.send(Update::TranslateError(feed.name.clone(), e)).await;
+                        translation = None;
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let entry = Entry { title, link, permalink, alternate, published, author, summary, summary_bytes, categories, translated, group: feed.group.clone() };
+        // The app has quit if nothing receives it.
+        if tx.send(Update::NewFeedItem(feed.name.clone(), entry)).await.is_err() {
+            break;
         }
     }
+    downloaded
 }
 
-async fn check_manual_site(site: Manual, tx: mpsc::Sender<Update>, cache: Cache, cache_path: String) {
-    let content = match reqwest::get(&site.url).await {
-        Ok(res) => match res.text().await {
-            Ok(text) => text,
+// A request that got no answer in time says so instead of reqwest's wording.
+fn describe_request_error(e: reqwest::Error) -> String {
+    if e.is_timeout() { "timed out".to_string() } else { e.to_string() }
+}
+
+// The watched response headers of `site`, or None when the server does not
+// allow HEAD.
+async fn fetch_headers(http: &reqwest::Client, site: &Manual, retries: u32) -> Result<Option<BTreeMap<String, String>>, String> {
+    let send = || http.head(&site.url).send();
+    let response = network::retry_with_backoff(send, retries, network::INITIAL_BACKOFF, |_| true).await.map_err(describe_request_error)?;
+    if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let names = site.headers.clone().unwrap_or_else(|| WATCHED_HEADERS.map(String::from).to_vec());
+    Ok(Some(names.into_iter().map(|name| {
+        let value = response.headers().get(name.as_str()).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+        (name, value)
+    }).collect()))
+}
+
+// The text of the elements of `html` that `selector` picks, with runs of
+// whitespace made one space, or None if it picks none.
+fn selected_text(html: &str, selector: &str) -> Result<Option<String>, String> {
+    let selector = Selector::parse(selector).map_err(|e| e.to_string())?;
+    let document = Html::parse_document(html);
+    let texts: Vec<String> = document
+        .select(&selector)
+        .map(|element| element.text().flat_map(str::split_whitespace).collect::<Vec<_>>().join(" "))
+        .collect();
+    Ok((!texts.is_empty()).then(|| texts.join("\n")))
+}
+
+// Returns the number of bytes downloaded. Requests that get no answer are
+// retried up to `max_retries` times, unless the site sets its own.
+#[allow(clippy::too_many_arguments)]
+async fn check_manual_site(http: reqwest::Client, site: Manual, tx: mpsc::Sender<Update>, cache: Cache, cache_path: String, clock: Arc<dyn Clock>, storage: Storage, max_retries: u32) -> u64 {
+    let retries = site.max_retries.unwrap_or(max_retries);
+    let mut headers = BTreeMap::new();
+    if site.method == Some(Method::Head) {
+        match fetch_headers(&http, &site, retries).await {
+            Ok(Some(values)) => headers = values,
+            Ok(None) => {
+                let warning = "server does not allow HEAD (405), checking the whole page instead".to_string();
+                let _ = tx.send(Update::HealthWarning(site.name.clone(), site.url.clone(), Some(warning))).await;
+            }
             Err(e) => {
-                let _ = tx.send(Update::Error(format!("[ERROR] reading content for {}: {}", site.name, e))).await;
-                return;
+                let _ = tx.send(Update::SourceError(site.name.clone(), format!("fetching headers for {}: {}", site.name, e))).await;
+                return 0;
+            }
+        }
+    }
+
+    let content = if !headers.is_empty() {
+        headers.iter().map(|(name, value)| format!("{}: {}\n", name, value)).collect()
+    } else {
+        let send = || http.get(&site.url).send();
+        match network::retry_with_backoff(send, retries, network::INITIAL_BACKOFF, |_| true).await {
+            Ok(res) => {
+                if let Some(server) = skew::server_date(&res) {
+                    let _ = tx.send(Update::ServerDate(server, clock.now_utc())).await;
+                }
+                match res.text().await {
+                    Ok(text) => text,
+                    Err(e) => {
+                        let _ = tx.send(Update::SourceError(site.name.clone(), format!("reading content for {}: {}", site.name, e))).await;
+                        return 0;
+                    }
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(Update::SourceError(site.name.clone(), format!("fetching {}: {}", site.name, describe_request_error(e)))).await;
+                return 0;
             }
-        },
-        Err(e) => {
-            let _ = tx.send(Update::Error(format!("[Error] fetching {}: {}", site.name, e))).await;
-            return;
         }
     };
 
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    let new_hash = format!("{:x}", hasher.finalize());
+    // Hashing a large page is CPU-bound; keep it off the runtime's workers so
+    // it does not hold up other fetches and the UI. With a selector only the
+    // text it picks is hashed, so ads or tokens elsewhere on the page do not
+    // count as changes.
+    let length = content.len() as u64;
+    let selector = site.selector.clone().filter(|_| headers.is_empty());
+    let new_hash = match tokio::task::spawn_blocking(move || {
+        let selected = selector.as_deref().map(|selector| selected_text(&content, selector));
+        let hashed = match &selected {
+            Some(Ok(Some(text))) => text,
+            _ => &content,
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(hashed.as_bytes());
+        (format!("{:x}", hasher.finalize()), selected.map(|selected| selected.map(|text| text.is_some())))
+    })
+    .await
+    {
+        Ok((hash, matched)) => {
+            if let (Some(selector), Some(matched)) = (&site.selector, matched) {
+                let warning = match matched {
+                    Ok(true) => None,
+                    Ok(false) => Some(format!("selector \"{}\" matches nothing, comparing the whole page", selector)),
+                    Err(e) => Some(format!("selector \"{}\" is not valid ({}), comparing the whole page", selector, e)),
+                };
+                // A HEAD that fell back to the page keeps its own warning.
+                if warning.is_some() || site.method != Some(Method::Head) {
+                    let _ = tx.send(Update::HealthWarning(site.name.clone(), site.url.clone(), warning)).await;
+                }
+            }
+            hash
+        }
+        Err(e) => {
+            let _ = tx.send(Update::SourceError(site.name.clone(), format!("hashing content for {}: {}", site.name, e))).await;
+            return length;
+        }
+    };
 
     let old_hash = {
         let cache_guard = cache.lock().unwrap();
-        cache_guard.get(&site.url).cloned()
+        cache_guard.get(&site.url).map(|entry| entry.hash.clone())
     };
 
     if old_hash.as_deref() != Some(&new_hash) {
-        let update_message = format!("New content detected on {}", site.name);
-        if let Err(e) = tx.send(Update::ManualUpdate(update_message, site.url.clone())).await {
-            eprintln!("Failed to send manual update: {}", e);
-        }
-
-        {
+        // The hash is always updated; only the notification is throttled.
+        let cooldown = chrono::Duration::minutes(site.cooldown_minutes.unwrap_or(60) as i64);
+        let update_message = {
             let mut cache_guard = cache.lock().unwrap();
-            cache_guard.insert(site.url.clone(), new_hash);
+            let entry = cache_guard.entry(site.url.clone()).or_default();
+            entry.hash = new_hash;
+            entry.headers = headers;
+            entry.record_change(&site.name, clock.now_utc(), cooldown)
+        };
+
+        match update_message {
+            Some(message) => {
+                let _ = tx.send(Update::ManualChange(site.name.clone(), message, site.url.clone(), site.group.clone())).await;
+            }
+            None => {
+                let _ = tx.send(Update::Info(format!("Change on {} suppressed during cooldown", site.name))).await;
+            }
         }
 
         let cache_content = {
@@ -131,362 +781,4440 @@ async fn check_manual_site(site: Manual, tx: mpsc::Sender<Update>, cache: Cache,
             serde_json::to_string_pretty(&*cache_guard).unwrap()
         };
         
-        if let Err(e) = tokio::fs::write(&cache_path, cache_content).await {
-            eprintln!("Failed to write to cache file: {}", e);
+        if let Err(e) = storage.write(Path::new(&cache_path), cache_content.as_bytes()).await {
+            let _ = tx.send(Update::Error(format!("writing {}: {} — state will not be saved this session", cache_path, e))).await;
         }
     } else {
         let _ = tx.send(Update::Info(format!("No changes for {}", site.name))).await;
     }
+    length
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let res = run_app(&mut terminal).await;
+// read.json, seen.json or bookmarks.json; a missing or unreadable file is
+// empty.
+fn load_links(path: &Path) -> HashSet<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
+// None where there is no config directory, e.g. without HOME.
+fn default_config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("br/config.toml"))
+}
 
-    if let Err(err) = res {
-        println!("{:?}", err)
+// Manual-site hashes for the default config live in cache.json. Any other
+// config gets a file named after its path, so two feed sets never compare
+// against each other's hashes.
+fn cache_file(config_path: &Path) -> String {
+    if Some(config_path) == default_config_path().as_deref() {
+        return "cache.json".to_string();
     }
-
-    Ok(())
+    let path = config_path.canonicalize().unwrap_or_else(|_| config_path.to_path_buf());
+    let digest = format!("{:x}", Sha256::digest(path.to_string_lossy().as_bytes()));
+    format!("cache-{}.json", &digest[..16])
 }
 
-enum InputMode {
-    Normal,
-    Search,
+// The outcome of subscribing to a feed, from `br add-feed` or the control
+// socket.
+enum Subscription {
+    Added(Box<Feed>),
+    Exists(String, String), // name, url
+    NotFound(String),       // why
+    Failed(String),
 }
 
-struct App {
-    all_updates: Vec<(String, Option<String>, Option<String>, bool)>, // display_text, link, date_string, is_new
-    info_messages: Vec<String>,
-    list_state: ListState,
-    input: String,
-    input_mode: InputMode,
+async fn subscribe(config_path: &Path, url: &str, name: Option<String>, group: Option<String>) -> Subscription {
+    let content = std::fs::read_to_string(config_path).unwrap_or_default();
+    let config: Config = toml::from_str(&content).unwrap_or_default();
+    // Discovery goes through the same proxy and timeout as the fetches.
+    let settings = Settings::resolve(&content.parse().unwrap_or_default(), &settings::env_var);
+    let http = match network::build_client(&config.network.clone().unwrap_or_default(), settings.timeout_secs) {
+        Ok(http) => http,
+        Err(e) => return Subscription::Failed(e),
+    };
+    let feeds = config.feeds.unwrap_or_default();
+    let subscribed = |feed_url: &str| feeds.iter().find(|feed| feed.url.all().iter().any(|u| u == feed_url));
+
+    if let Some(feed) = subscribed(url) {
+        return Subscription::Exists(feed.name.clone(), url.to_string());
+    }
+    let (feed_url, title) = match discover::discover(&http, url).await {
+        Ok(found) => found,
+        Err(e) => return Subscription::NotFound(e),
+    };
+    if let Some(feed) = subscribed(&feed_url) {
+        return Subscription::Exists(feed.name.clone(), feed_url);
+    }
+
+    let name = name
+        .or(title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()))
+        .unwrap_or_else(|| feed_url.clone());
+    match config_store::add_feed(config_path, &name, &feed_url, group.as_deref()) {
+        Ok(()) => Subscription::Added(Box::new(Feed::new(name, feed_url, group))),
+        Err(e) => Subscription::Failed(e),
+    }
 }
 
-impl App {
-    fn new(initial_updates: Vec<(String, Option<String>, Option<String>, bool)>) -> App {
-        App {
-            all_updates: initial_updates,
-            info_messages: Vec::new(),
-            list_state: ListState::default(),
-            input: String::new(),
-            input_mode: InputMode::Normal,
+// `br add-feed`. Returns the exit code.
+async fn add_feed(config_path: &Path, url: &str, name: Option<String>, group: Option<String>) -> i32 {
+    match subscribe(config_path, url, name, group).await {
+        Subscription::Added(feed) => {
+            println!("added\t{}\t{}", feed.name, feed.url.all()[0]);
+            0
+        }
+        Subscription::Exists(name, url) => {
+            println!("exists\t{}\t{}", name, url);
+            2
+        }
+        Subscription::NotFound(e) => {
+            eprintln!("{}", e);
+            println!("not-found\t\t{}", url);
+            3
+        }
+        Subscription::Failed(e) => {
+            eprintln!("{}", e);
+            1
         }
     }
+}
 
-    fn first(&mut self, item_count: usize) {
-        if item_count == 0 {
-            self.list_state.select(None);
-            return;
+// `br export`: prints the feeds and manual sites of the config as OPML, or
+// writes them to `output`.
+fn export(config_path: &Path, output: Option<&Path>) -> i32 {
+    let content = match std::fs::read_to_string(config_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("reading {}: {}", config_path.display(), e);
+            return 1;
+        }
+    };
+    let config: Config = match toml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("parsing {}: {}", config_path.display(), e);
+            return 1;
+        }
+    };
+    // Mirrors are left out; OPML has one URL per feed.
+    let feeds: Vec<(&str, &str)> = config.feeds.iter().flatten()
+        .filter_map(|feed| Some((feed.name.as_str(), feed.url.all().first()?.as_str())))
+        .collect();
+    let manual: Vec<(&str, &str)> = config.manual.iter().flatten().map(|site| (site.name.as_str(), site.url.as_str())).collect();
+    let document = opml::export(&feeds, &manual);
+    let Some(output) = output else {
+        print!("{}", document);
+        return 0;
+    };
+    match std::fs::write(output, document) {
+        Ok(()) => {
+            println!("{}", output.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("writing {}: {}", output.display(), e);
+            1
         }
-        self.list_state.select(Some(0));
     }
+}
 
-    fn last(&mut self, item_count: usize) {
-        if item_count == 0 {
-            self.list_state.select(None);
-            return;
+// `br backup create` and `br backup restore`. Returns the exit code.
+fn backup(config_path: &Path, command: BackupCommand) -> i32 {
+    let Some(data_dir) = dirs::data_dir().map(|dir| dir.join("br")) else {
+        eprintln!("no data directory (set XDG_DATA_HOME or HOME)");
+        return 1;
+    };
+    let result = match command {
+        BackupCommand::Create { output } => backup::create(config_path, &data_dir, &output)
+            .map(|files| println!("{}: {} files", output.display(), files)),
+        BackupCommand::Restore { archive, force } => backup::restore(config_path, &data_dir, &archive, force).map(|manifest| {
+            let created = manifest.created.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            println!("restored the backup br {} made on {} to {}", manifest.version, created, data_dir.display());
+            if manifest.config {
+                println!("and {}", config_path.display());
+            }
+        }),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
         }
-        self.list_state.select(Some(item_count - 1));
     }
+}
 
-    fn next(&mut self, item_count: usize) {
-        if item_count == 0 {
-            self.list_state.select(None);
-            return;
+// `br config show`. Returns the exit code.
+fn show_config(config_path: &Path, effective: bool, format: Format) -> i32 {
+    let content = match std::fs::read_to_string(config_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && effective => String::new(),
+        Err(e) => {
+            eprintln!("reading {}: {}", config_path.display(), e);
+            return 1;
         }
-        let i = match self.list_state.selected() {
-            Some(i) => if i >= item_count - 1 { 0 } else { i + 1 },
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+    };
+    if !effective {
+        print!("{}", content);
+        return 0;
     }
-    
-    fn previous(&mut self, item_count: usize) {
-        if item_count == 0 {
-            self.list_state.select(None);
-            return;
+    let table = match content.parse() {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("parsing {}: {}", config_path.display(), e);
+            return 1;
         }
-        let i = match self.list_state.selected() {
-            Some(i) => if i == 0 { item_count - 1 } else { i - 1 },
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+    };
+    let settings = Settings::resolve(&table, &settings::env_var);
+    for warning in &settings.warnings {
+        eprintln!("ignoring {}", warning);
     }
+    match format {
+        Format::Toml => print!("{}", settings.to_toml()),
+        Format::Json => println!("{}", settings.to_json()),
+    }
+    0
 }
 
+// Why the TUI cannot run here, if it cannot.
+fn interactive_terminal() -> Result<(), &'static str> {
+    if !io::stdout().is_terminal() {
+        return Err("stdout is not a terminal");
+    }
+    if !io::stdin().is_terminal() {
+        return Err("stdin is not a terminal");
+    }
+    if std::env::var("TERM").is_ok_and(|term| term == "dumb") {
+        return Err("TERM=dumb does not support cursor movement");
+    }
+    Ok(())
+}
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
-    let initial_updates: Vec<(String, Option<String>, Option<String>, bool)> = vec![
-        ("Press 'u' to check for updates.".to_string(), None, None, false),
-        ("Press 'o' or Enter to open selected link.".to_string(), None, None, false),
-        ("Press '/' to search/filter.".to_string(), None, None, false),
-        ("Use j/k to scroll.".to_string(), None, None, false),
-        ("Press g or G to go to first or last item.".to_string(), None, None, false),
-        ("Press 'q' to quit.".to_string(), None, None, false),
-    ];
-
-    let mut app = App::new(initial_updates);
-    app.list_state.select(Some(0));
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let Some(config_path) = cli.config.or_else(default_config_path) else {
+        eprintln!("no config directory (set XDG_CONFIG_HOME or HOME), pass --config PATH");
+        std::process::exit(1);
+    };
+    match cli.command {
+        Some(Command::AddFeed { url, name, group }) => {
+            let code = add_feed(&config_path, &url, name, group).await;
+            std::process::exit(code);
+        }
+        Some(Command::Ctl { command }) => {
+            let Some(path) = control::socket_path() else {
+                eprintln!("no runtime directory (XDG_RUNTIME_DIR) to find the control socket in");
+                std::process::exit(1);
+            };
+            std::process::exit(control::send(&path, &command.request()).await);
+        }
+        Some(Command::Export { opml: _, output }) => {
+            std::process::exit(export(&config_path, output.as_deref()));
+        }
+        Some(Command::Import { opml }) => match import::import_opml(&config_path, &opml) {
+            Ok(added) => {
+                println!("added {} feeds to {}", added, config_path.display());
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Log { tail, errors_only }) => {
+            let Some(path) = event_log::default_path() else {
+                eprintln!("no data directory (set XDG_DATA_HOME or HOME)");
+                std::process::exit(1);
+            };
+            std::process::exit(event_log::print(&path, tail, errors_only));
+        }
+        Some(Command::Backup { command }) => {
+            std::process::exit(backup(&config_path, command));
+        }
+        Some(Command::Config { command: ConfigCommand::Show { effective, format } }) => {
+            std::process::exit(show_config(&config_path, effective, format));
+        }
+        None => {}
+    }
 
-    let (tx, mut rx) = mpsc::channel(100);
+    // Decide before touching the terminal: raw mode on a pipe or a dumb
+    // terminal only garbles the output.
+    if let Err(reason) = interactive_terminal() {
+        eprintln!("br: {}; the reader needs an interactive terminal (use `br add-feed` from scripts)", reason);
+        std::process::exit(1);
+    }
 
-    let config_path = dirs::config_dir().unwrap().join("br/config.toml");
+    // Puts the terminal back if the UI panics, so the message can be read.
+    // A panic in a fetch task is caught by tokio and br carries on.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen, Print(RESTORE_TITLE));
+        }
+        hook(info);
+    }));
 
-    let config: Config = match tokio::fs::read_to_string(&config_path).await {
-        Ok(config_str) => toml::from_str(&config_str).unwrap_or(Config { feeds: None, manual: None }),
-        Err(_) => {
-            app.all_updates.push(("[ERROR] config.toml not found.".to_string(), None, None, false));
-            Config { feeds: None, manual: None }
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, Print(SAVE_TITLE))?;
+    let mut startup_warnings = Vec::new();
+    let alternate_screen = match execute!(stdout, EnterAlternateScreen) {
+        Ok(_) => true,
+        Err(e) => {
+            startup_warnings.push(format!("Alternate screen unavailable ({}), drawing on the main screen", e));
+            false
         }
     };
-    
-    let cache_path = dirs::data_dir().unwrap().join("br/cache.json").to_string_lossy().to_string();
-    let cache_content = tokio::fs::read_to_string(&cache_path).await.unwrap_or_else(|_| "{}".to_string());
-    let cache_map: HashMap<String, String> = serde_json::from_str(&cache_content).unwrap_or_default();
-    let cache = Arc::new(Mutex::new(cache_map));
-
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(250);
-
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+    execute!(stdout, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
 
-        let timeout = tick_rate.checked_sub(last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
+    let res = run_app(&mut terminal, Arc::new(SystemClock), config_path, startup_warnings).await;
 
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('/') => {
-                            app.input_mode = InputMode::Search;
-                        },
-                        KeyCode::Char('g') => {
-                             let filtered_count = app.all_updates.iter().filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase())).count();
-                             app.first(filtered_count);
-                        },
-                        KeyCode::Char('G') => {
-                             let filtered_count = app.all_updates.iter().filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase())).count();
-                             app.last(filtered_count);
-                        },
-                        KeyCode::Char('j') => {
-                             let filtered_count = app.all_updates.iter().filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase())).count();
-                             app.next(filtered_count);
-                        },
-                        KeyCode::Char('k') => {
-                             let filtered_count = app.all_updates.iter().filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase())).count();
-                             app.previous(filtered_count);
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    if alternate_screen {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    } else {
+        terminal.clear()?;
+    }
+    terminal.show_cursor()?;
+    execute!(terminal.backend_mut(), Print(RESTORE_TITLE))?;
+
+    if let Err(err) = res {
+        println!("{:?}", err)
+    }
+
+    Ok(())
+}
+
+// There is no portable way to read the terminal title, so the one br
+// started with is pushed onto xterm's title stack and popped on the way out.
+const SAVE_TITLE: &str = "\x1b[22;0t";
+const RESTORE_TITLE: &str = "\x1b[23;0t";
+
+enum InputMode {
+    Normal,
+    Search,
+    Confirm,
+    History,
+    Peek,
+    Link, // a link no opener could open, shown to be copied
+    Preview, // a finished preview waiting to be applied or discarded
+    Rule, // building a rule from the selected item
+    Article, // reading the full text of a page
+    Note, // writing a note on the selected item
+    FeedCheck, // what fetching the selected item's feed showed
+    Help, // the key bindings, over the list
+    Setup, // asking for the first feeds, while there is no config.toml
+    Session, // asking how long a reading session should last
+    DateFilter, // asking for the date range to list
+}
+
+impl InputMode {
+    const ALL: [InputMode; 15] = [
+        InputMode::Normal,
+        InputMode::Search,
+        InputMode::Confirm,
+        InputMode::History,
+        InputMode::Peek,
+        InputMode::Link,
+        InputMode::Preview,
+        InputMode::Rule,
+        InputMode::Article,
+        InputMode::Note,
+        InputMode::FeedCheck,
+        InputMode::Help,
+        InputMode::Setup,
+        InputMode::Session,
+        InputMode::DateFilter,
+    ];
+
+    // The key contexts of the mode, top-most first. The gone-feed notice
+    // sits over the normal mode while a feed waits for an answer.
+    fn contexts(&self, notice: bool) -> &'static [Context] {
+        match self {
+            InputMode::Normal if notice => &[Context::Notice, Context::Normal],
+            InputMode::Normal => &[Context::Normal],
+            InputMode::Search => &[Context::Search, Context::Normal],
+            InputMode::Confirm => &[Context::Confirm],
+            InputMode::History => &[Context::History, Context::Normal],
+            InputMode::Peek => &[Context::Peek, Context::Normal],
+            InputMode::Link => &[Context::Link],
+            InputMode::Preview => &[Context::Preview],
+            InputMode::Rule => &[Context::Rule],
+            InputMode::Article => &[Context::Article],
+            InputMode::Note => &[Context::Note],
+            InputMode::FeedCheck => &[Context::FeedCheck],
+            InputMode::Help => &[Context::Help],
+            InputMode::Setup => &[Context::Setup],
+            InputMode::Session => &[Context::Session],
+            InputMode::DateFilter => &[Context::DateFilter],
+        }
+    }
+
+    // Every stack of contexts keys can be routed through.
+    fn stacks() -> Vec<&'static [Context]> {
+        InputMode::ALL.iter().flat_map(|mode| [mode.contexts(false), mode.contexts(true)]).collect()
+    }
+}
+
+// A fetched page shown in InputMode::Article.
+struct Article {
+    link: String,
+    title: String,
+    text: String,
+    scroll: u16,
+}
+
+// A rule being put together in the rule builder. Both patterns are kept
+// while a field is switched off, so switching it back loses nothing.
+struct RuleDraft {
+    source: String,
+    title: String,
+    use_source: bool,
+    use_title: bool,
+    editing_title: bool,
+    action: RuleAction,
+}
+
+impl RuleDraft {
+    // Pre-filled to match the item's source exactly and its title literally.
+    fn new(source: &str, title: &str) -> RuleDraft {
+        RuleDraft {
+            source: format!("^{}$", regex::escape(source)),
+            title: regex::escape(title),
+            use_source: true,
+            use_title: true,
+            editing_title: false,
+            action: RuleAction::default(),
+        }
+    }
+
+    fn rule(&self) -> Rule {
+        Rule {
+            source: self.use_source.then(|| self.source.clone()),
+            title: self.use_title.then(|| self.title.clone()),
+            action: self.action,
+        }
+    }
+
+    fn editing(&mut self) -> &mut String {
+        if self.editing_title { &mut self.title } else { &mut self.source }
+    }
+}
+
+// Progress of the refresh that is currently running.
+struct Refresh {
+    started: Instant,
+    sources: usize, // fetch tasks started
+    pending: usize, // fetch tasks that have not finished
+    bytes: u64,
+    errors: usize,
+    new_items: BTreeMap<String, usize>,
+    skew_samples: Vec<i64>, // server minus local time, in seconds
+    translate_failed: bool, // reported once per refresh
+    skipped: BTreeMap<String, usize>, // reason -> items not added
+    preview: bool, // new items are staged, not listed
+    alerted: bool, // the alert goes off once per refresh
+    feed_pages: HashSet<String>, // same_page of the feed items listed so far
+    manual_pages: HashMap<String, usize>, // same_page of manual changes listed so far, to their rows
+    tasks: Vec<(String, JoinHandle<()>)>, // source name and fetch task, until it ends
+}
+
+impl Refresh {
+    fn new(started: Instant, preview: bool) -> Refresh {
+        Refresh {
+            started,
+            sources: 0,
+            pending: 0,
+            bytes: 0,
+            errors: 0,
+            new_items: BTreeMap::new(),
+            skew_samples: Vec::new(),
+            translate_failed: false,
+            skipped: BTreeMap::new(),
+            preview,
+            alerted: false,
+            feed_pages: HashSet::new(),
+            manual_pages: HashMap::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    fn add_tasks(&mut self, tasks: Vec<(String, JoinHandle<()>)>) {
+        self.sources += tasks.len();
+        self.pending += tasks.len();
+        self.tasks.extend(tasks);
+    }
+}
+
+// An action waiting for y/n in InputMode::Confirm.
+enum PendingAction {
+    MarkRead(Vec<usize>),
+    OpenTabs(Vec<usize>),
+    RemoveFeed(String, String), // name and key of a gone feed
+}
+
+struct Confirmation {
+    prompt: String,
+    action: PendingAction,
+}
+
+const MAX_NOTES: usize = 5;
+
+struct Item {
+    text: String,
+    link: Option<String>,
+    permalink: Option<String>, // of a feed entry, see Entry
+    alternate: Option<String>,
+    published: Option<DateTime<Utc>>,
+    date: Option<String>, // the date column of feed items
+    is_new: bool,
+    search_key: SearchKey,
+    snippet: Option<String>, // first sentence of the summary, for the wide layout
+    snippet_key: Option<SearchKey>,
+    summary_bytes: Option<usize>, // when the stored summary was cut, its size before
+    title: Option<String>, // as the feed has it, even when translated
+    translated_title: Option<String>,
+    source: Option<String>,
+    author: Option<String>,
+    group: Option<String>, // of the feed or manual site
+    categories: Vec<String>,
+    category_total: usize, // including those past categories::MAX_STORED
+    hidden: bool,
+    highlighted: bool, // by a rule
+    notes: VecDeque<String>, // why its state last changed, oldest first
+    edits_config: bool, // Enter opens config.toml in $EDITOR, for a parse error
+}
+
+impl Item {
+    fn new(text: String, link: Option<String>, is_new: bool) -> Item {
+        let search_key = SearchKey::new(&text);
+        Item {
+            text,
+            link,
+            permalink: None,
+            alternate: None,
+            published: None,
+            date: None,
+            is_new,
+            search_key,
+            snippet: None,
+            snippet_key: None,
+            summary_bytes: None,
+            title: None,
+            translated_title: None,
+            source: None,
+            author: None,
+            group: None,
+            categories: Vec::new(),
+            category_total: 0,
+            hidden: false,
+            highlighted: false,
+            notes: VecDeque::new(),
+            edits_config: false,
+        }
+    }
+
+    // Records why the item was just hidden, read or restored, keeping the
+    // last MAX_NOTES.
+    fn note(&mut self, at: DateTime<Utc>, note: String) {
+        self.notes.push_back(format!("{} {}", at.with_timezone(&chrono::Local).format("%H:%M"), note));
+        if self.notes.len() > MAX_NOTES {
+            self.notes.pop_front();
+        }
+    }
+
+    // What read.json and seen.json remember it by, see Entry::key.
+    fn key(&self) -> Option<&str> {
+        self.permalink.as_deref().or(self.link.as_deref())
+    }
+
+    // Status rows (help text, errors, progress) carry no link.
+    fn is_article(&self) -> bool {
+        self.link.is_some()
+    }
+
+    // The source name and original title rules match against; only feed
+    // entries have them.
+    fn rule_fields(&self) -> Option<(&str, &str)> {
+        Some((self.source.as_deref()?, self.title.as_deref()?))
+    }
+}
+
+// One rendered line of the list. Headers only appear when grouping by day
+// and are skipped by navigation.
+enum Row {
+    Header { day: Option<NaiveDate>, count: usize, collapsed: bool },
+    Item(usize), // index into all_updates
+}
+
+impl Row {
+    fn is_selectable(&self) -> bool {
+        matches!(self, Row::Item(_))
+    }
+}
+
+struct App {
+    all_updates: Vec<Item>,
+    info_messages: Vec<String>,
+    log: EventLog, // everything info() and error() show
+    list_state: ListState,
+    new_above: HashSet<usize>, // items that arrived above the selection, not yet scrolled to
+    pending_input: String, // being typed in the search bar
+    active_filter: String, // applied to the list
+    input_mode: InputMode,
+    smart_case: bool,
+    match_case: bool, // Ctrl-t in the search bar, over smart_case
+    search_mode: SearchMode, // Ctrl-r or Ctrl-e in the search bar
+    channel: Channel,
+    clock: Arc<dyn Clock>,
+    palette: Palette,
+    sort: SortOrder,
+    flat_sort: SortOrder, // the order 'D' goes back to from the by-day view
+    collapsed_days: HashSet<Option<NaiveDate>>,
+    confirmation: Option<Confirmation>,
+    last_marked_read: Vec<usize>, // for a single level of undo
+    refresh: Option<Refresh>,
+    history: Vec<RefreshSummary>,
+    history_state: ListState,
+    history_expanded: Option<usize>,
+    storage: Storage,
+    keymap: Keymap,
+    health: Health,
+    gone_feeds: Vec<(String, String)>, // name and key, waiting for the user to remove or ignore them
+    show_snippets: ShowSnippets,
+    search_snippets: bool,
+    show_categories: bool,
+    show_detail: bool,
+    detail_scroll: (Option<usize>, u16), // the item the pane shows, lines scrolled past
+    max_source_width: usize,
+    max_entries: usize, // per feed and refresh, 0 for all
+    max_content_bytes: usize, // of each stored summary, 0 for no limit
+    columns: Columns, // as laid out in the last frame
+    list_area: Rect, // where the list was last drawn, for mouse clicks
+    bookmarked_links: HashSet<String>,
+    bookmarks_changed: bool, // bookmarked_links differs from bookmarks.json
+    bookmarks_only: bool,
+    date_filter: Option<(DateTime<Utc>, DateTime<Utc>)>, // set with d; undated items pass
+    annotations: BTreeMap<String, String>, // link -> the note written on it with N
+    annotations_changed: bool, // annotations differs from annotations.json
+    note_draft: Option<(String, String)>, // link and text while writing a note
+    listed_links: HashSet<String>, // links of all_updates, to skip duplicates
+    read_links: HashSet<String>, // feed entries read, remembered across runs
+    read_changed: bool, // read_links differs from read.json
+    seen_links: HashSet<String>, // feed entries listed in this or an earlier run
+    seen_changed: bool,
+    triaged: usize, // items acted on since the peek popup was opened
+    content: ContentStore,
+    clock_skew: chrono::Duration, // server minus local time, from the last refresh
+    skew_warned: bool,
+    openers: Vec<Box<dyn Opener>>,
+    bulk_open: BulkOpen,
+    article: Option<Article>,
+    help_scroll: u16, // how far down the help popup is scrolled
+    article_pending: Option<(String, String)>, // link and title being fetched
+    report: Option<diagnose::Report>,
+    report_pending: Option<String>, // key of the item whose feed is being checked
+    setup_feeds: Vec<(String, String)>, // name and URL of each feed the first-run setup found
+    setup_lookups: usize, // URLs the first-run setup is still looking for feeds at
+    reading: Option<ReadingSession>, // started with Ctrl-t
+    stats: reading::Stats,
+    unopened_link: Option<String>,
+    translations: translate::Cache,
+    staged: Vec<Update>, // new items from a preview
+    applying: Vec<Update>, // staged items to add on the next frame
+    preview_cache: Option<Cache>, // the manual-site cache as the preview left it
+    fetch_slots: Arc<Semaphore>, // fetches that may run at once
+    feed_colors: HashMap<String, Color>, // by feed name, for feeds that set one
+    http: reqwest::Client, // shared by every fetch; configure() sets the timeout
+    last_refresh: Instant, // when the last refresh started, or start-up
+    refresh_interval: Option<Duration>, // between timed refreshes
+    alert: Alert,
+    alert_command: Option<String>,
+    notifications: bool,
+    fresh_posts: Vec<notify::Post>, // new this refresh, for the desktop notification
+    alert_due: bool, // rung after the next frame is drawn
+    rules: Vec<Matcher>,
+    rule_draft: Option<RuleDraft>,
+}
+
+impl App {
+    fn new(initial_updates: Vec<Item>, clock: Arc<dyn Clock>) -> App {
+        let started = clock.now_instant();
+        App {
+            all_updates: initial_updates,
+            info_messages: Vec::new(),
+            log: EventLog::new(),
+            list_state: ListState::default(),
+            new_above: HashSet::new(),
+            pending_input: String::new(),
+            active_filter: String::new(),
+            input_mode: InputMode::Normal,
+            smart_case: false,
+            match_case: false,
+            search_mode: SearchMode::Substring,
+            channel: Channel::new(),
+            clock,
+            palette: Palette::new(ColorMode::Auto, Theme::DARK),
+            sort: SortOrder::default(),
+            flat_sort: SortOrder::default(),
+            collapsed_days: HashSet::new(),
+            confirmation: None,
+            last_marked_read: Vec::new(),
+            refresh: None,
+            history: Vec::new(),
+            history_state: ListState::default(),
+            history_expanded: None,
+            storage: Storage::default(),
+            keymap: Keymap::new(),
+            health: Health::new(),
+            gone_feeds: Vec::new(),
+            show_snippets: ShowSnippets::Auto,
+            search_snippets: false,
+            show_categories: true,
+            show_detail: true,
+            detail_scroll: (None, 0),
+            max_source_width: 30,
+            max_entries: 5,
+            max_content_bytes: 256 * 1024,
+            columns: Columns::default(),
+            list_area: Rect::default(),
+            bookmarked_links: HashSet::new(),
+            bookmarks_changed: false,
+            bookmarks_only: false,
+            date_filter: None,
+            annotations: BTreeMap::new(),
+            annotations_changed: false,
+            note_draft: None,
+            listed_links: HashSet::new(),
+            read_links: HashSet::new(),
+            read_changed: false,
+            seen_links: HashSet::new(),
+            seen_changed: false,
+            triaged: 0,
+            content: ContentStore::new(PathBuf::new(), Storage::default()),
+            clock_skew: chrono::Duration::zero(),
+            skew_warned: false,
+            openers: opener::chain(None),
+            bulk_open: BulkOpen::Tabs,
+            article: None,
+            help_scroll: 0,
+            article_pending: None,
+            report: None,
+            report_pending: None,
+            setup_feeds: Vec::new(),
+            setup_lookups: 0,
+            reading: None,
+            stats: reading::Stats::default(),
+            unopened_link: None,
+            translations: translate::Cache::default(),
+            staged: Vec::new(),
+            applying: Vec::new(),
+            preview_cache: None,
+            fetch_slots: Arc::new(Semaphore::new(8)),
+            feed_colors: HashMap::new(),
+            http: reqwest::Client::new(),
+            last_refresh: started,
+            refresh_interval: None,
+            alert: Alert::None,
+            alert_command: None,
+            notifications: false,
+            fresh_posts: Vec::new(),
+            alert_due: false,
+            rules: Vec::new(),
+            rule_draft: None,
+        }
+    }
+
+    // Key contexts to consult, top-most first.
+    fn contexts(&self) -> &'static [Context] {
+        self.input_mode.contexts(!self.gone_feeds.is_empty())
+    }
+
+    fn gone_notice(&self) -> Option<String> {
+        let (name, key) = self.gone_feeds.first()?;
+        let health = self.health.get(key)?;
+        Some(format!(
+            "Feed '{}' appears gone ({}, {} failures) — press X to remove it from config, I to ignore",
+            name, health.last_status, health.consecutive_gone
+        ))
+    }
+
+    // X on the gone-feed notice: asks before removing the feed. Until the
+    // answer is y the notice stays up.
+    fn confirm_remove_feed(&mut self) {
+        let Some((name, key)) = self.gone_feeds.first().cloned() else { return };
+        let prompt = format!("Remove '{}' ({}) from config.toml? (y/n)", name, key);
+        self.confirmation = Some(Confirmation { prompt, action: PendingAction::RemoveFeed(name, key) });
+        self.input_mode = InputMode::Confirm;
+    }
+
+    // Removes a confirmed gone feed from config.toml and forgets its health.
+    // Returns whether it was removed, for the caller to drop it from Config.
+    fn remove_feed(&mut self, config_path: &Path, name: &str, key: &str) -> bool {
+        self.gone_feeds.retain(|(_, gone)| gone != key);
+        match config_store::remove_feed(config_path, key) {
+            Ok(()) => {
+                self.health.remove(key);
+                self.info(format!("Removed {} from config", name));
+                true
+            }
+            Err(e) => {
+                self.error(format!("removing {}: {}", name, e));
+                false
+            }
+        }
+    }
+
+    // I on the gone-feed notice: silences it for IGNORE_DAYS.
+    async fn ignore_gone_feed(&mut self, health_path: &Path) {
+        if self.gone_feeds.is_empty() {
+            return;
+        }
+        let (name, key) = self.gone_feeds.remove(0);
+        health::ignore(&mut self.health, &key, self.clock.now_utc());
+        self.info(format!("Ignoring {} for {} days", name, health::IGNORE_DAYS));
+        let content = serde_json::to_string_pretty(&self.health).unwrap();
+        if let Err(e) = self.storage.write(health_path, content.as_bytes()).await {
+            self.error(format!("writing {}: {} — state will not be saved this session", health_path.display(), e));
+        }
+    }
+
+    // '/': edits the applied filter, which stays applied until Enter.
+    fn start_search(&mut self) {
+        self.pending_input = self.active_filter.clone();
+        self.input_mode = InputMode::Search;
+    }
+
+    // Enter while searching: what was typed becomes the filter.
+    fn submit_search(&mut self) {
+        self.active_filter = std::mem::take(&mut self.pending_input);
+        self.input_mode = InputMode::Normal;
+    }
+
+    // Esc while searching: the filter stays as it was before '/'.
+    fn cancel_search(&mut self) {
+        self.pending_input.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    // Esc in the list.
+    fn clear_filter(&mut self) {
+        if !self.active_filter.is_empty() {
+            self.active_filter.clear();
+            self.info("Filter cleared".to_string());
+        }
+    }
+
+    fn query(&self) -> Query {
+        let input = if matches!(self.input_mode, InputMode::Search) { &self.pending_input } else { &self.active_filter };
+        Query::new(input, self.case_mode(), self.search_mode)
+    }
+
+    fn case_mode(&self) -> CaseMode {
+        if self.match_case {
+            CaseMode::Match
+        } else if self.smart_case {
+            CaseMode::Smart
+        } else {
+            CaseMode::Ignore
+        }
+    }
+
+    // The unread articles currently listed, which R and O act on.
+    fn listed_unread(&self) -> Vec<usize> {
+        self.rows().iter()
+            .filter_map(|row| match row {
+                Row::Item(i) if self.all_updates[*i].is_article() && self.all_updates[*i].is_new => Some(*i),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // O and Ctrl-o: opens the unread articles listed, each in a tab of its
+    // own once confirmed, or all on one launcher page to pick from.
+    fn open_listed(&mut self, how: BulkOpen) {
+        let indices = self.listed_unread();
+        if indices.is_empty() {
+            self.info("Nothing unread to open".to_string());
+            return;
+        }
+        match how {
+            BulkOpen::Tabs => {
+                let prompt = format!("Open {} items in tabs? (y/n)", indices.len());
+                self.confirmation = Some(Confirmation { prompt, action: PendingAction::OpenTabs(indices) });
+                self.input_mode = InputMode::Confirm;
+            }
+            BulkOpen::Page => {
+                let links: Vec<launcher::Link> = indices.iter().map(|&i| {
+                    let item = &self.all_updates[i];
+                    launcher::Link {
+                        title: item.translated_title.as_deref().or(item.title.as_deref()).unwrap_or(&item.text),
+                        source: item.source.as_deref(),
+                        date: item.date.as_deref(),
+                        url: item.link.as_deref().unwrap_or_default(),
+                    }
+                }).collect();
+                match launcher::write(&launcher::page(&links)) {
+                    Ok(path) => {
+                        self.open_link(&format!("file://{}", path.display()));
+                    }
+                    Err(e) => self.info(format!("Could not write the launcher page: {}", e)),
+                }
+            }
+        }
+    }
+
+    // Opens each item in the browser and marks it read, stopping at the
+    // first that cannot be opened.
+    fn open_tabs(&mut self, indices: &[usize]) {
+        let mut opened = 0;
+        for &i in indices {
+            let link = self.all_updates[i].link.clone().unwrap_or_default();
+            match opener::open(&self.openers, &link) {
+                Ok(_) => {
+                    self.read_by_user(i, "read: opened with O".to_string());
+                    self.track(i, Activity::Opened);
+                    opened += 1;
+                }
+                Err(failures) => {
+                    self.info(format!("Could not open link ({})", failures.join("; ")));
+                    break;
+                }
+            }
+        }
+        self.info(format!("Opened {} of {} items", opened, indices.len()));
+    }
+
+    // Asks before marking the unread articles currently listed as read,
+    // naming the filter so it is clear when only part of the list is affected.
+    fn confirm_mark_read(&mut self) {
+        let indices = self.listed_unread();
+        if indices.is_empty() {
+            self.info("Nothing unread to mark".to_string());
+            return;
+        }
+        // "all" only when nothing narrows the list.
+        let mut scope = String::new();
+        if !self.active_filter.is_empty() {
+            scope.push_str(&format!(" matching '{}'", self.active_filter));
+        }
+        if let Some(range) = self.date_filter {
+            scope.push_str(&format!(" dated {}", days::describe_range(range)));
+        }
+        let prompt = if self.bookmarks_only {
+            format!("Mark {} bookmarked items{} read? (y/n)", indices.len(), scope)
+        } else if scope.is_empty() {
+            format!("Mark all {} items read? (y/n)", indices.len())
+        } else {
+            format!("Mark {} items{} read? (y/n)", indices.len(), scope)
+        };
+        self.confirmation = Some(Confirmation { prompt, action: PendingAction::MarkRead(indices) });
+        self.input_mode = InputMode::Confirm;
+    }
+
+    // Drops the handles of fetch tasks that ended. One that panicked never
+    // sent its Finished, which would leave the refresh running forever, so
+    // an error and a Finished are returned in its place.
+    async fn reap_tasks(&mut self) -> Vec<Update> {
+        let Some(refresh) = self.refresh.as_mut() else {
+            return Vec::new();
+        };
+        let (ended, running): (Vec<_>, Vec<_>) = std::mem::take(&mut refresh.tasks).into_iter().partition(|(_, task)| task.is_finished());
+        refresh.tasks = running;
+        let mut updates = Vec::new();
+        for (name, task) in ended {
+            if let Err(e) = task.await {
+                updates.push(Update::SourceError(name.clone(), format!("checking {}: the fetch task stopped ({})", name, e)));
+                updates.push(Update::Finished(0));
+            }
+        }
+        updates
+    }
+
+    // Called as each fetch task finishes. Returns the summary once the last
+    // one is done.
+    fn finish_fetch(&mut self, bytes: u64) -> Option<RefreshSummary> {
+        let refresh = self.refresh.as_mut()?;
+        refresh.bytes += bytes;
+        refresh.pending -= 1;
+        if refresh.pending > 0 {
+            return None;
+        }
+        let mut refresh = self.refresh.take()?;
+        Some(RefreshSummary {
+            clock_skew: skew::median(&mut refresh.skew_samples),
+            skipped: refresh.skipped,
+            finished_at: self.clock.now_utc(),
+            duration_ms: (self.clock.now_instant() - refresh.started).as_millis() as u64,
+            bytes: refresh.bytes,
+            errors: refresh.errors,
+            new_items: refresh.new_items,
+        })
+    }
+
+    // The status bar: what is listed, and how the running or the last
+    // refresh went, e.g. "42 items, 7 unread | 3 errors | last refresh 12:03:45".
+    fn status_line(&self) -> String {
+        let listed = self.all_updates.iter().filter(|item| item.is_article() && !item.hidden).count();
+        let mut parts = vec![format!("{} items, {} unread", listed, self.unread().count())];
+        let errors = |n: usize| format!("{} {}", n, if n == 1 { "error" } else { "errors" });
+        match (&self.refresh, self.history.last()) {
+            (Some(refresh), _) => {
+                let doing = if refresh.preview { "previewing" } else { "refreshing" };
+                parts.push(format!("{} {}/{} sources", doing, refresh.sources - refresh.pending, refresh.sources));
+                parts.push(errors(refresh.errors));
+            }
+            (None, Some(last)) => {
+                parts.push(errors(last.errors));
+                let at = last.finished_at.with_timezone(&chrono::Local);
+                let format = if days::local_day(last.finished_at) == days::local_day(self.clock.now_utc()) { "%H:%M:%S" } else { "%e %b %H:%M:%S" };
+                parts.push(format!("last refresh {}", at.format(format).to_string().trim()));
+            }
+            (None, None) => parts.push("not refreshed yet".to_string()),
+        }
+        if let Some(session) = &self.reading {
+            let elapsed = countdown(self.clock.now_instant() - session.started);
+            let limit = session.limit.map_or(String::new(), |limit| format!(" of {}", countdown(limit)));
+            parts.push(format!("session {}{}, {} read, {} opened", elapsed, limit, session.summary.read, session.summary.opened));
+        }
+        parts.join(" | ")
+    }
+
+    // Keeps the item at `i` for the desktop notification sent when the
+    // refresh is done, if it is new, not hidden by a rule, and not previewed.
+    fn note_fresh(&mut self, i: usize) {
+        let refreshing = self.refresh.as_ref().is_some_and(|refresh| !refresh.preview);
+        let item = &self.all_updates[i];
+        if !self.notifications || !refreshing || !item.is_new || item.hidden {
+            return;
+        }
+        let title = item.translated_title.clone().or(item.title.clone()).unwrap_or_else(|| item.text.clone());
+        self.fresh_posts.push(notify::Post { source: item.source.clone().unwrap_or_default(), title });
+    }
+
+    // Records that an entry a feed gave this refresh is `page`, and hides the
+    // manual change this refresh listed for the same page, if any: the feed
+    // item says more about it.
+    fn collapse_manual(&mut self, page: String, feed_name: &str) {
+        let Some(refresh) = &mut self.refresh else { return };
+        let Some(i) = refresh.manual_pages.remove(&page) else {
+            refresh.feed_pages.insert(page);
+            return;
+        };
+        refresh.feed_pages.insert(page);
+        *refresh.skipped.entry("same page as a feed item".to_string()).or_default() += 1;
+        let item = &mut self.all_updates[i];
+        if let Some(count) = item.source.as_ref().and_then(|source| refresh.new_items.get_mut(source)) {
+            *count = count.saturating_sub(1);
+        }
+        item.hidden = true;
+        item.note(self.clock.now_utc(), format!("hidden for the same page in feed {}", feed_name));
+        let (source, text) = (item.source.clone().unwrap_or_default(), item.text.clone());
+        self.fresh_posts.retain(|post| post.source != source || post.title != text);
+    }
+
+    // The current time corrected by the measured clock skew, for anything
+    // compared against dates servers give.
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.clock.now_utc() + self.clock_skew
+    }
+
+    // Takes `skew` seconds, measured by a refresh, as the clock's error from
+    // now on. A large one is warned about once a session.
+    fn set_clock_skew(&mut self, skew: i64) {
+        self.clock_skew = chrono::Duration::seconds(skew);
+        if skew.abs() >= skew::THRESHOLD_SECS && !self.skew_warned {
+            self.skew_warned = true;
+            self.info(format!("Warning: {}", skew::describe(skew)));
+        }
+    }
+
+    // Whether a timed refresh is due. It only fetches: what is listed stays
+    // new until 'u' or R. It waits while a search is typed or a refresh or
+    // preview is under way.
+    fn refresh_due(&self) -> bool {
+        self.refresh_interval.is_some_and(|interval| self.clock.now_instant() - self.last_refresh >= interval)
+            && self.refresh.is_none()
+            && self.staged.is_empty()
+            && !matches!(self.input_mode, InputMode::Search)
+    }
+
+    // The info pane's title, counting down to the next timed refresh while
+    // nothing is being fetched.
+    fn info_title(&self) -> String {
+        match self.refresh_interval {
+            Some(interval) if self.refresh.is_none() => {
+                let left = interval.saturating_sub(self.clock.now_instant() - self.last_refresh);
+                format!("Info (next refresh in {})", countdown(left))
+            }
+            _ => "Info".to_string(),
+        }
+    }
+
+    // The header of a day in the by-day view, relative to today.
+    fn day_label(&self, day: Option<NaiveDate>) -> String {
+        day.map_or_else(|| "Undated".to_string(), |day| days::day_label(day, days::local_day(self.now_utc())))
+    }
+
+    // Opens `link` with the first opener that works and logs which one it
+    // was. If none do, the link is shown in a popup so it can be copied.
+    fn open_link(&mut self, link: &str) -> bool {
+        match opener::open(&self.openers, link) {
+            Ok(name) => {
+                self.info(format!("Opened {} with {}", link, name));
+                true
+            }
+            Err(failures) => {
+                self.info(format!("Could not open link ({})", failures.join("; ")));
+                self.unopened_link = Some(link.to_string());
+                self.input_mode = InputMode::Link;
+                false
+            }
+        }
+    }
+
+    // Items listed so far stop being new, as when a refresh starts.
+    fn mark_seen(&mut self) {
+        self.new_above.clear();
+        for i in 0..self.all_updates.len() {
+            if self.all_updates[i].is_new {
+                self.set_read(i, true, "marked read by refreshing".to_string());
+            }
+        }
+    }
+
+    // Counts `activity` on item `i` towards the reading session, if one is
+    // running.
+    fn track(&mut self, i: usize, activity: Activity) {
+        if let Some(session) = &mut self.reading {
+            session.record(activity, self.all_updates[i].source.as_deref());
+        }
+    }
+
+    // set_read for what the reader does, which a reading session counts.
+    fn read_by_user(&mut self, i: usize, note: String) {
+        if self.all_updates[i].is_new {
+            self.track(i, Activity::Read);
+        }
+        self.set_read(i, true, note);
+    }
+
+    // Ends the reading session: its summary is shown, unless another popup
+    // is open, and added to stats.json.
+    async fn end_session(&mut self, stats_path: &Path, timed_out: bool) {
+        let Some(session) = self.reading.take() else {
+            return;
+        };
+        let summary = session.finish(self.clock.now_instant());
+        let ended = if timed_out { "Time's up, reading session over" } else { "Reading session ended" };
+        self.info(format!("{}: {}", ended, summary.headline()));
+        if let InputMode::Normal = self.input_mode {
+            self.report = Some(diagnose::Report { title: ended.to_string(), lines: summary.lines(), scroll: 0 });
+            self.input_mode = InputMode::FeedCheck;
+        }
+        self.stats.sessions.push(summary);
+        let content = serde_json::to_string_pretty(&self.stats).unwrap();
+        if let Err(e) = self.storage.write(stats_path, content.as_bytes()).await {
+            self.error(format!("writing {}: {} — state will not be saved this session", stats_path.display(), e));
+        }
+    }
+
+    // Marks item `i` read or unread, noting why. Feed entries are remembered
+    // in read.json; a manual site keeps its link across changes, so its
+    // items are not. An entry marked unread is also forgotten as seen, so it
+    // is still new next run.
+    fn set_read(&mut self, i: usize, read: bool, note: String) {
+        let now = self.clock.now_utc();
+        let item = &mut self.all_updates[i];
+        item.is_new = !read;
+        item.note(now, note);
+        if let (Some(key), Some(_)) = (item.key(), &item.title) {
+            self.read_changed |= if read { self.read_links.insert(key.to_string()) } else { self.read_links.remove(key) };
+            if !read {
+                self.seen_changed |= self.seen_links.remove(key);
+            }
+        }
+    }
+
+    // Applies every matching rule to item `i`, as it is listed.
+    fn apply_rules(&mut self, i: usize) {
+        let Some((source, title)) = self.all_updates[i].rule_fields() else {
+            return;
+        };
+        let actions: Vec<RuleAction> = self.rules.iter().filter(|rule| rule.matches(source, title)).map(|rule| rule.action).collect();
+        for action in actions {
+            self.apply_rule(i, action, "a rule");
+        }
+    }
+
+    fn apply_rule(&mut self, i: usize, action: RuleAction, by: &str) {
+        let now = self.clock.now_utc();
+        match action {
+            RuleAction::Hide => {
+                self.all_updates[i].hidden = true;
+                self.all_updates[i].note(now, format!("hidden by {}", by));
+            }
+            RuleAction::MarkRead => self.set_read(i, true, format!("marked read by {}", by)),
+            RuleAction::Highlight => {
+                self.all_updates[i].highlighted = true;
+                self.all_updates[i].note(now, format!("highlighted by {}", by));
+                // Only entries a refresh finds for the first time alert, not
+                // ones read or listed in an earlier run, and only once per
+                // refresh however many match.
+                if self.all_updates[i].is_new
+                    && let Some(refresh) = &mut self.refresh
+                    && !refresh.preview
+                    && !std::mem::replace(&mut refresh.alerted, true)
+                {
+                    self.alert_due = self.alert != Alert::None;
+                }
+            }
+        }
+    }
+
+    // How many feed entries the draft rule matches, out of how many, or why
+    // it cannot be used.
+    fn rule_preview(&self) -> Result<(usize, usize), String> {
+        let draft = self.rule_draft.as_ref().ok_or("no rule")?;
+        let matcher = Matcher::new(&draft.rule())?;
+        let entries = self.all_updates.iter().filter_map(Item::rule_fields);
+        let (matched, total) = entries.fold((0, 0), |(matched, total), (source, title)| {
+            (matched + matcher.matches(source, title) as usize, total + 1)
+        });
+        Ok((matched, total))
+    }
+
+    // Saves the draft rule to config.toml and applies it to what is listed.
+    fn save_rule(&mut self, config_path: &Path) {
+        let Some(draft) = self.rule_draft.take() else {
+            return;
+        };
+        if self.storage.is_ephemeral() {
+            self.info(format!("Rule {}", storage::NOT_SAVED));
+            self.input_mode = InputMode::Normal;
+            return;
+        }
+        let rule = draft.rule();
+        let matcher = match Matcher::new(&rule) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.info(format!("Rule not saved: {}", e));
+                self.rule_draft = Some(draft);
+                return;
+            }
+        };
+        if let Err(e) = config_store::add_rule(config_path, &rule) {
+            self.error(format!("saving rule: {}", e));
+            self.input_mode = InputMode::Normal;
+            return;
+        }
+        let matched: Vec<usize> = (0..self.all_updates.len())
+            .filter(|&i| self.all_updates[i].rule_fields().is_some_and(|(source, title)| matcher.matches(source, title)))
+            .collect();
+        for &i in &matched {
+            self.apply_rule(i, rule.action, "a new rule");
+        }
+        self.rules.push(matcher);
+        self.info(format!("Saved a {} rule, applied to {} items", rule.action.label(), matched.len()));
+        self.input_mode = InputMode::Normal;
+        let rows = self.rows();
+        self.clamp_selection(&rows);
+    }
+
+    fn is_bookmarked(&self, item: &Item) -> bool {
+        item.link.as_ref().is_some_and(|link| self.bookmarked_links.contains(link))
+    }
+
+    fn annotation(&self, item: &Item) -> Option<&str> {
+        self.annotations.get(item.link.as_ref()?).map(String::as_str)
+    }
+
+    fn toggle_bookmark(&mut self, i: usize) {
+        let Some(link) = self.all_updates[i].link.clone() else {
+            return;
+        };
+        if self.storage.is_ephemeral() {
+            self.info(format!("Bookmark {}", storage::NOT_SAVED));
+            return;
+        }
+        let note = if self.bookmarked_links.remove(&link) {
+            "bookmark removed"
+        } else {
+            self.bookmarked_links.insert(link);
+            self.track(i, Activity::Starred);
+            "bookmarked"
+        };
+        let now = self.clock.now_utc();
+        self.all_updates[i].note(now, note.to_string());
+        self.bookmarks_changed = true;
+    }
+
+    // Writes read.json, seen.json or bookmarks.json.
+    async fn save_links(&mut self, path: &Path, links: &HashSet<String>) {
+        let mut links: Vec<&String> = links.iter().collect();
+        links.sort();
+        let content = serde_json::to_string_pretty(&links).unwrap();
+        if let Err(e) = self.storage.write(path, content.as_bytes()).await {
+            self.error(format!("writing {}: {} — state will not be saved this session", path.display(), e));
+        }
+    }
+
+    // Lists a change of a manual site. Each is reported once by
+    // check_manual_site, so unlike feed entries they are not deduplicated
+    // by link: the site's URL is the link of every change found on it.
+    fn list_manual_change(&mut self, site_name: String, message: String, link: String, group: Option<String>) {
+        let page = same_page(&link);
+        let mut item = Item::new(format!("[MANUAL] {}", message), Some(link), true);
+        item.source = Some(site_name.clone());
+        item.group = group;
+        self.all_updates.push(item);
+        self.note_fresh(self.all_updates.len() - 1);
+        if let Some(refresh) = &mut self.refresh {
+            *refresh.new_items.entry(site_name).or_default() += 1;
+            refresh.manual_pages.insert(page, self.all_updates.len() - 1);
+        }
+    }
+
+    // The staged items that are not listed yet, by source.
+    fn preview_groups(&self) -> BTreeMap<String, Vec<String>> {
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut links = HashSet::new();
+        for update in &self.staged {
+            match update {
+                Update::NewFeedItem(name, entry) if !self.listed_links.contains(entry.key()) && links.insert(entry.key()) => {
+                    groups.entry(name.clone()).or_default().push(entry.title.clone());
+                }
+                Update::ManualChange(name, message, ..) => groups.entry(name.clone()).or_default().push(message.clone()),
+                _ => {}
+            }
+        }
+        groups
+    }
+
+    fn unread(&self) -> impl Iterator<Item = &Item> {
+        self.all_updates.iter().filter(|item| item.is_article() && item.is_new && !item.hidden)
+    }
+
+    // The answer to a control socket command.
+    fn control_response(&self, request: &Request) -> serde_json::Value {
+        match request {
+            Request::ListUnread => {
+                let items: Vec<_> = self.unread().map(|item| serde_json::json!({
+                    "title": item.title.as_deref().unwrap_or(&item.text),
+                    "source": item.source,
+                    "link": item.link,
+                    "published": item.published,
+                })).collect();
+                serde_json::json!({ "ok": true, "items": items })
+            }
+            Request::Watch => serde_json::json!({ "ok": true, "unread": self.unread().count() }),
+            Request::Refresh => serde_json::json!({ "ok": true }),
+            Request::AddFeed { .. } => serde_json::json!({ "ok": false, "error": "add_feed is handled by the socket" }),
+        }
+    }
+
+    fn info(&mut self, message: String) {
+        let shown = event_log::shown(Level::Info, &message);
+        self.log.record(Level::Info, &shown);
+        self.info_messages.push(shown);
+        if self.info_messages.len() > 5 {
+            self.info_messages.remove(0);
+        }
+    }
+
+    // Only logs `message`, for what is worth finding later but not showing.
+    fn debug(&mut self, message: String) {
+        let shown = event_log::shown(Level::Debug, &message);
+        self.log.record(Level::Debug, &shown);
+    }
+
+    // Lists an [ERROR] row, and logs it.
+    fn error(&mut self, message: String) {
+        let shown = event_log::shown(Level::Error, &message);
+        self.log.record(Level::Error, &shown);
+        self.all_updates.push(Item::new(shown, None, false));
+    }
+
+    // Whether `item` is listed under `query`, the bookmarks-only view and
+    // the date filter.
+    fn matches(&self, item: &Item, query: &Query) -> bool {
+        !item.hidden
+            && (!self.bookmarks_only || self.is_bookmarked(item))
+            && self.date_filter.is_none_or(|(from, to)| item.published.is_none_or(|published| from <= published && published < to))
+            && query.matches_note(self.annotation(item))
+            && query.matches_categories(&item.categories)
+            && query.matches_group(item.group.as_deref())
+            && (query.matches(&item.search_key)
+                || (self.search_snippets && item.snippet_key.as_ref().is_some_and(|key| query.matches(key))))
+    }
+
+    // The rows currently on screen: matching items, and in the by-day view
+    // status rows first followed by articles under newest-first day headers,
+    // with undated articles last.
+    fn rows(&self) -> Vec<Row> {
+        let query = self.query();
+        let matching = (0..self.all_updates.len()).filter(|&i| self.matches(&self.all_updates[i], &query));
+        if self.sort == SortOrder::Arrival {
+            return matching.map(Row::Item).collect();
+        }
+
+        let (status, mut articles): (Vec<usize>, Vec<usize>) = matching.partition(|&i| !self.all_updates[i].is_article());
+        let mut rows: Vec<Row> = status.into_iter().map(Row::Item).collect();
+        if self.sort != SortOrder::ByDay {
+            sort::sort(self.sort, &mut articles, &self.all_updates);
+            rows.extend(articles.into_iter().map(Row::Item));
+            return rows;
+        }
+
+        let mut days: BTreeMap<Option<NaiveDate>, Vec<usize>> = BTreeMap::new();
+        for i in articles {
+            days.entry(self.all_updates[i].published.map(days::local_day)).or_default().push(i);
+        }
+        // None sorts before every date, so reversing puts Undated last.
+        for (day, mut indices) in days.into_iter().rev() {
+            let collapsed = self.collapsed_days.contains(&day);
+            rows.push(Row::Header { day, count: indices.len(), collapsed });
+            if !collapsed {
+                sort::sort(SortOrder::ByDay, &mut indices, &self.all_updates);
+                rows.extend(indices.into_iter().map(Row::Item));
+            }
+        }
+        rows
+    }
+
+    // Switches the list order, keeping the selection on an item, and saves
+    // it for the next run.
+    async fn set_sort(&mut self, order: SortOrder, session_path: &Path) {
+        let anchor = self.selected_index(&self.rows());
+        self.sort = order;
+        if order != SortOrder::ByDay {
+            self.flat_sort = order;
+        }
+        self.reselect(anchor);
+        let content = serde_json::to_string_pretty(&session::Session { sort: order }).unwrap();
+        if let Err(e) = self.storage.write(session_path, content.as_bytes()).await {
+            self.error(format!("writing {}: {} — state will not be saved this session", session_path.display(), e));
+        }
+    }
+
+    // The list text of an item, before any chips or snippet.
+    fn label(&self, item: &Item) -> String {
+        let text = match (&item.source, &item.title) {
+            (Some(source), Some(title)) if item.text.starts_with("[FEED]") => {
+                let title = item.translated_title.as_ref().map_or_else(|| title.clone(), |t| format!("⇄ {}", t));
+                let source = columns::pad(source, self.columns.source);
+                match self.columns.date {
+                    0 => format!("[FEED] {} | {}", source, title),
+                    width => format!("[FEED] {:>width$} | {} | {}", item.date.as_deref().unwrap_or_default(), source, title),
+                }
+            }
+            _ => item.text.clone(),
+        };
+        let text = if self.annotation(item).is_some() { format!("[n] {}", text) } else { text };
+        if self.is_bookmarked(item) { format!("★ {}", text) } else { text }
+    }
+
+    fn chips(&self, item: &Item) -> Vec<String> {
+        if self.show_categories { categories::chips(&item.categories) } else { Vec::new() }
+    }
+
+    // The category chip drawn at a screen position, if any. Mirrors the
+    // layout in ui(): border, highlight symbol, label, then chips.
+    fn chip_at(&self, column: u16, row: u16) -> Option<String> {
+        let area = self.list_area;
+        if row <= area.y || row >= area.bottom().saturating_sub(1) {
+            return None;
+        }
+        let rows = self.rows();
+        let Row::Item(i) = rows.get(self.list_state.offset() + (row - area.y - 1) as usize)? else {
+            return None;
+        };
+        let item = &self.all_updates[*i];
+        let mut start = area.x as usize + 1 + HIGHLIGHT_SYMBOL.width() + self.label(item).width();
+        for (chip, category) in self.chips(item).iter().zip(&item.categories) {
+            let end = start + chip.width();
+            // Skip the space in front of the brackets.
+            if (start + 1..end).contains(&(column as usize)) {
+                return Some(category.clone());
+            }
+            start = end;
+        }
+        None
+    }
+
+    // The next @group filter for 'c': through the groups of the listed feed
+    // items in order, then uncategorized ones if any, then no group filter.
+    fn cycle_group(&mut self) {
+        let mut groups: Vec<String> = self.all_updates.iter()
+            .filter(|item| item.is_article())
+            .map(|item| item.group.as_deref().map_or(search::UNCATEGORIZED.to_string(), str::to_lowercase))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        // Uncategorized goes last rather than in alphabetical order.
+        if let Some(i) = groups.iter().position(|group| group == search::UNCATEGORIZED) {
+            let uncategorized = groups.remove(i);
+            groups.push(uncategorized);
+        }
+        let mut words: Vec<&str> = self.active_filter.split(' ').filter(|word| !word.is_empty()).collect();
+        let current = words.iter().position(|word| word.starts_with('@') && word.len() > 1);
+        let next = match current {
+            Some(i) => {
+                let current = words.remove(i)[1..].to_lowercase();
+                groups.iter().position(|group| *group == current).and_then(|i| groups.get(i + 1))
+            }
+            None => groups.first(),
+        };
+        let next = next.map(|group| format!("@{}", group));
+        if let Some(next) = &next {
+            words.push(next);
+        }
+        self.active_filter = words.join(" ");
+        self.info(match next {
+            Some(next) => format!("Showing {}", next),
+            None => "Showing all groups".to_string(),
+        });
+    }
+
+    // Selects the row of item `anchor` after the rows changed around it, so
+    // the highlight stays on the same item. Without it, or if it is no
+    // longer listed, the selection is only kept in range.
+    fn reselect(&mut self, anchor: Option<usize>) {
+        let rows = self.rows();
+        if let Some(row) = rows.iter().position(|row| matches!(row, Row::Item(i) if Some(*i) == anchor)) {
+            self.list_state.select(Some(row));
+        } else {
+            self.clamp_selection(&rows);
+        }
+    }
+
+    // The selected row and the item on it, to keep in place across a batch
+    // of updates.
+    fn anchor(&self) -> (Option<usize>, Option<usize>) {
+        (self.list_state.selected(), self.selected_index(&self.rows()))
+    }
+
+    // After a batch of updates, selects the anchored item again unless
+    // something else moved the selection meanwhile, and scrolls by as many
+    // rows as it moved so it stays where it was on screen. Items from
+    // `arrived` on that land above it count as new above.
+    fn follow_anchor(&mut self, (row, item): (Option<usize>, Option<usize>), arrived: usize) {
+        if self.list_state.selected() != row {
+            return;
+        }
+        self.reselect(item);
+        if let (Some(before), Some(after)) = (row, self.list_state.selected())
+            && after > before
+        {
+            *self.list_state.offset_mut() += after - before;
+            let rows = self.rows();
+            let above = rows[..after].iter().filter_map(|row| match row {
+                Row::Item(i) if *i >= arrived => Some(*i),
+                _ => None,
+            });
+            self.new_above.extend(above);
+        }
+    }
+
+    fn selected_index(&self, rows: &[Row]) -> Option<usize> {
+        match rows.get(self.list_state.selected()?)? {
+            Row::Item(i) => Some(*i),
+            Row::Header { .. } => None,
+        }
+    }
+
+    fn selected_item(&self, rows: &[Row]) -> Option<&Item> {
+        self.all_updates.get(self.selected_index(rows)?)
+    }
+
+    // After acting on `current` in the peek popup, moves to the next unread
+    // article below it in the listed rows, or ends triage if there is none.
+    fn triage_next(&mut self, current: usize) {
+        self.triaged += 1;
+        let rows = self.rows();
+        // A hidden item has left the list; the row now selected is the one after it.
+        let start = match rows.iter().position(|row| matches!(row, Row::Item(i) if *i == current)) {
+            Some(position) => position + 1,
+            None => self.list_state.selected().unwrap_or(0),
+        };
+        let next = (start..rows.len()).find(|&position| {
+            matches!(rows[position], Row::Item(i) if self.all_updates[i].is_new && self.all_updates[i].is_article())
+        });
+        match next {
+            Some(position) => self.list_state.select(Some(position)),
+            None => {
+                self.clamp_selection(&rows);
+                self.input_mode = InputMode::Normal;
+                self.info(format!("Triage complete: {} processed", self.triaged));
+            }
+        }
+    }
+
+    fn first(&mut self, rows: &[Row]) {
+        self.list_state.select(rows.iter().position(Row::is_selectable));
+    }
+
+    fn last(&mut self, rows: &[Row]) {
+        self.list_state.select(rows.iter().rposition(Row::is_selectable));
+    }
+
+    // Selects the topmost of the items that arrived above the selection.
+    fn jump_new_above(&mut self, rows: &[Row]) {
+        if let Some(row) = rows.iter().position(|row| matches!(row, Row::Item(i) if self.new_above.contains(i))) {
+            self.list_state.select(Some(row));
+        }
+        self.new_above.clear();
+    }
+
+    fn next(&mut self, rows: &[Row]) {
+        let Some(current) = self.list_state.selected() else {
+            return self.first(rows);
+        };
+        let i = (1..=rows.len())
+            .map(|offset| (current + offset) % rows.len())
+            .find(|&i| rows[i].is_selectable());
+        self.list_state.select(i);
+    }
+
+    fn previous(&mut self, rows: &[Row]) {
+        let Some(current) = self.list_state.selected() else {
+            return self.first(rows);
+        };
+        let i = (1..=rows.len())
+            .map(|offset| (current + rows.len() - offset % rows.len()) % rows.len())
+            .find(|&i| rows[i].is_selectable());
+        self.list_state.select(i);
+    }
+
+    // Keeps the selection on an item after the rows change underneath it,
+    // e.g. when a day is collapsed or the filter narrows.
+    fn clamp_selection(&mut self, rows: &[Row]) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        if rows.get(selected).is_some_and(Row::is_selectable) {
+            return;
+        }
+        let after = rows.iter().skip(selected).position(Row::is_selectable).map(|i| i + selected);
+        if let Some(i) = after.or_else(|| rows.iter().rposition(Row::is_selectable)) {
+            self.list_state.select(Some(i));
+        }
+    }
+}
+
+
+// What 'u' does: the items so far stop being new, and a refresh starts.
+fn refresh(app: &mut App, config: &Arc<Config>, cache: &Cache, cache_path: &str) {
+    if app.refresh.as_ref().is_some_and(|r| r.preview) || !app.staged.is_empty() {
+        app.info("A preview is running, apply or discard it first".to_string());
+        return;
+    }
+    app.mark_seen();
+    app.all_updates.push(Item::new("Checking for updates...".to_string(), None, false));
+    let checking = app.all_updates.len() - 1;
+    let rows = app.rows();
+    if let Some(i) = rows.iter().position(|row| matches!(row, Row::Item(i) if *i == checking)) {
+        app.list_state.select(Some(i));
+    }
+
+    start_refresh(app, config, cache, cache_path, false);
+    let disabled = config.feeds.iter().flatten().filter(|feed| feed.disabled).count()
+        + config.manual.iter().flatten().filter(|site| site.disabled).count();
+    if disabled > 0 {
+        app.info(format!("Skipped {} disabled {}", disabled, if disabled == 1 { "source" } else { "sources" }));
+    }
+}
+
+// What Ctrl-u does: a refresh whose new items wait in a popup.
+fn preview(app: &mut App, config: &Arc<Config>, cache: &Cache, cache_path: &str) {
+    if app.refresh.is_some() {
+        app.info("Wait for the running refresh to finish before previewing".to_string());
+        return;
+    }
+    app.info("Previewing updates...".to_string());
+    start_refresh(app, config, cache, cache_path, true);
+}
+
+// Hands the terminal to $VISUAL or $EDITOR (vi if neither is set) to edit
+// `path`, and takes it back once the editor exits.
+fn edit_file<B: Backend>(terminal: &mut Terminal<B>, path: &Path) -> io::Result<std::process::ExitStatus> {
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    let words: Vec<&str> = editor.split_whitespace().collect();
+    let Some((program, args)) = words.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "$EDITOR is empty"));
+    };
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
+    let status = std::process::Command::new(program).args(args).arg(path).status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    status
+}
+
+// The readable text of the page at `link`, for the article view.
+async fn fetch_article(http: &reqwest::Client, link: &str) -> Result<String, String> {
+    let response = http.get(link).send().await.and_then(|response| response.error_for_status()).map_err(describe_request_error)?;
+    let html = response.text().await.map_err(describe_request_error)?;
+    summary::article_text(&html).ok_or_else(|| "no text found on the page".to_string())
+}
+
+// Reads config.toml into the feeds and sections and the resolved top-level
+// options, listing what is wrong with it as [ERROR] rows. Gives None if the
+// file cannot be read or parsed.
+async fn read_config(app: &mut App, config_path: &Path) -> Option<(Config, Settings)> {
+    let config_str = match tokio::fs::read_to_string(config_path).await {
+        Ok(config_str) => config_str,
+        Err(e) => {
+            app.error(format!("reading {}: {}", config_path.display(), e));
+            return None;
+        }
+    };
+    let mut config: Config = match toml::from_str(&config_str) {
+        Ok(config) => config,
+        Err(e) => {
+            // The first line of the error has the position, the message comes last.
+            let position = e.to_string().lines().next().unwrap_or_default().to_string();
+            app.error(format!("parsing {}: {}: {} (Enter to edit)", config_path.display(), position, e.message()));
+            if let Some(item) = app.all_updates.last_mut() {
+                item.edits_config = true;
+            }
+            app.info(format!("Fix {} and press Ctrl-r to reload it", config_path.display()));
+            return None;
+        }
+    };
+    let table: toml::Table = config_str.parse().unwrap_or_default();
+    let settings = Settings::resolve(&table, &settings::env_var);
+    for warning in &settings.warnings {
+        app.error(format!("ignoring {}", warning));
+    }
+    if let Some(path) = &settings.newsboat_urls {
+        let path = import::expand_home(path);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let own = config.feeds.as_ref().map_or(0, Vec::len);
+                let (added, listed) = config.merge_newsboat(import::parse_newsboat(&content));
+                let mut message = format!("Feeds: {} from {}, {} from {}", own, config_path.display(), added, path.display());
+                if listed > 0 {
+                    message.push_str(&format!(" ({} already in {})", listed, config_path.display()));
+                }
+                app.info(message);
+            }
+            Err(e) => app.error(format!("reading newsboat_urls {}: {}", path.display(), e)),
+        }
+    }
+    let (warnings, notes) = config.check_overlaps(settings.skip_duplicate_manual);
+    for warning in warnings {
+        app.info(warning);
+    }
+    // Often a blog and its homepage, which may well be meant.
+    for note in notes {
+        app.debug(note);
+    }
+    for warning in config.disambiguate_names() {
+        app.info(warning);
+    }
+    // Likely a typo, or an option of another version; either way unused.
+    for key in table.keys().filter(|key| !SECTIONS.contains(&key.as_str()) && !settings.is_known(key)) {
+        app.debug(format!("unknown key {} in {}", key, config_path.display()));
+    }
+    Some((config, settings))
+}
+
+// Applies the options and rules that can change while br runs, at start-up
+// and on Ctrl-r.
+fn configure(app: &mut App, config: &Config, settings: &Settings) {
+    app.smart_case = settings.smart_case;
+    let (theme, errors) = Theme::resolve(&settings.theme);
+    app.palette = Palette::new(settings.color_mode, theme);
+    for e in errors {
+        app.error(format!("ignoring {}", e));
+    }
+    app.feed_colors.clear();
+    for feed in config.feeds.iter().flatten() {
+        let Some(color) = &feed.color else { continue };
+        match parse_color(color) {
+            Some(parsed) => {
+                app.feed_colors.insert(feed.name.clone(), parsed);
+            }
+            None => app.error(format!("ignoring color = \"{}\" of {}: not a color", color, feed.name)),
+        }
+    }
+    app.show_snippets = settings.show_snippets;
+    app.search_snippets = settings.search_snippets;
+    app.show_categories = settings.show_categories;
+    app.max_source_width = settings.max_source_width;
+    app.max_entries = settings.max_entries;
+    app.max_content_bytes = settings.max_content_bytes;
+    app.alert = settings.alert;
+    app.alert_command = settings.alert_command.clone();
+    app.notifications = settings.notifications;
+    app.refresh_interval = settings.refresh_interval_secs.filter(|&secs| secs > 0).map(Duration::from_secs);
+    app.openers = opener::chain(settings.browser_command.as_deref());
+    app.bulk_open = settings.bulk_open;
+    app.keymap = Keymap::new();
+    for e in app.keymap.remap(config.keys.as_ref().unwrap_or(&BTreeMap::new())) {
+        app.error(format!("ignoring {}", e));
+    }
+    for conflict in app.keymap.conflicts(&InputMode::stacks()) {
+        app.error(format!("Key conflict: {}", conflict));
+    }
+    let network = config.network.clone().unwrap_or_default();
+    match network::build_client(&network, settings.timeout_secs) {
+        Ok(client) => {
+            app.http = client;
+            app.info(format!("User-Agent: {}", network.user_agent()));
+        }
+        Err(e) => app.error(e),
+    }
+    // Fetches already running keep the slots they were started with.
+    app.fetch_slots = Arc::new(Semaphore::new(settings.max_concurrent_fetches.max(1)));
+    let (matchers, errors) = rules::matchers(config.rules.as_deref().unwrap_or_default());
+    app.rules = matchers;
+    for e in errors {
+        app.error(format!("ignoring {}", e));
+    }
+}
+
+// Spawns a fetch task for every configured feed and manual site. Each task
+// reports Update::Finished when done so the refresh can be summarized; the
+// refresh keeps their handles to notice one that panics instead.
+// Tasks share the config and wait for one of app.fetch_slots, so a config
+// with thousands of feeds neither copies it nor opens thousands of
+// connections at once.
+//
+// A preview checks manual sites against a copy of the cache and writes
+// nothing, so discarding it leaves every change to be detected again.
+fn start_refresh(app: &mut App, config: &Arc<Config>, cache: &Cache, cache_path: &str, preview: bool) {
+    if !preview {
+        app.last_refresh = app.clock.now_instant();
+    }
+    let (cache, storage) = if preview {
+        let copy = Arc::new(Mutex::new(cache.lock().unwrap().clone()));
+        app.preview_cache = Some(copy.clone());
+        (copy, Storage::discarding())
+    } else {
+        (cache.clone(), app.storage.clone())
+    };
+    let tx = &app.channel.tx;
+    let mut tasks = Vec::new();
+
+    for i in 0..config.feeds.as_ref().map_or(0, Vec::len) {
+        if config.feeds.as_ref().unwrap()[i].disabled {
+            continue;
+        }
+        let tx_clone = tx.clone();
+        let clock = app.clock.clone();
+        let config = config.clone();
+        let translations = app.translations.clone();
+        let max_entries = app.max_entries;
+        let max_content_bytes = app.max_content_bytes;
+        let http = app.http.clone();
+        let slots = app.fetch_slots.clone();
+        let cache = cache.clone();
+        let name = config.feeds.as_ref().unwrap()[i].name.clone();
+        let task = tokio::spawn(async move {
+            let _slot = slots.acquire_owned().await;
+            let feed = &config.feeds.as_ref().unwrap()[i];
+            let translation = config.translate.clone().map(|backend| (backend, translations));
+            let max_retries = config.network.clone().unwrap_or_default().max_retries();
+            let bytes = fetch_feed(&http, feed, &cache, tx_clone.clone(), clock, translation, max_entries, max_content_bytes, max_retries).await;
+            let _ = tx_clone.send(Update::Finished(bytes)).await;
+        });
+        tasks.push((name, task));
+    }
+    for i in 0..config.manual.as_ref().map_or(0, Vec::len) {
+        if config.manual.as_ref().unwrap()[i].disabled {
+            continue;
+        }
+        let tx_clone = tx.clone();
+        let cache_clone = cache.clone();
+        let cache_path_clone = cache_path.to_string();
+        let clock = app.clock.clone();
+        let storage = storage.clone();
+        let config = config.clone();
+        let http = app.http.clone();
+        let slots = app.fetch_slots.clone();
+        let name = config.manual.as_ref().unwrap()[i].name.clone();
+        let task = tokio::spawn(async move {
+            let _slot = slots.acquire_owned().await;
+            let site = config.manual.as_ref().unwrap()[i].clone();
+            let max_retries = config.network.clone().unwrap_or_default().max_retries();
+            let bytes = check_manual_site(http, site, tx_clone.clone(), cache_clone, cache_path_clone, clock, storage, max_retries).await;
+            let _ = tx_clone.send(Update::Finished(bytes)).await;
+        });
+        tasks.push((name, task));
+    }
+
+    if tasks.is_empty() {
+        return;
+    }
+    // Pressing u again mid-refresh folds the new tasks into the running one.
+    let started = app.clock.now_instant();
+    app.refresh.get_or_insert_with(|| Refresh::new(started, preview)).add_tasks(tasks);
+}
+
+async fn run_app<B: Backend>(terminal: &mut Terminal<B>, clock: Arc<dyn Clock>, config_path: PathBuf, startup_warnings: Vec<String>) -> io::Result<()> {
+    let initial_updates: Vec<Item> = vec![
+        Item::new("Press 'u' to check for updates, Ctrl-u to preview them first.".to_string(), None, false),
+        Item::new("Press 'o' or Enter to open selected link.".to_string(), None, false),
+        Item::new("Press '/' to search/filter.".to_string(), None, false),
+        Item::new("Use j/k to scroll.".to_string(), None, false),
+        Item::new("Press g or G to go to first or last item.".to_string(), None, false),
+        Item::new("Press 'D' to group items by day, z/Z to fold/unfold days.".to_string(), None, false),
+        Item::new("Press ',' to cycle the sort order.".to_string(), None, false),
+        Item::new("Press 'r' to mark the selected item read or unread.".to_string(), None, false),
+        Item::new("Press 'b' to bookmark the selected item, 'B' to list only bookmarks.".to_string(), None, false),
+        Item::new("Press 'N' to write a note on the selected item.".to_string(), None, false),
+        Item::new("Press 'R' to mark the listed items read, 'U' to undo.".to_string(), None, false),
+        Item::new("Press 'H' to show the refresh history.".to_string(), None, false),
+        Item::new("Press Space to peek at an item and triage it.".to_string(), None, false),
+        Item::new("Press 'f' to read the full article inside br.".to_string(), None, false),
+        Item::new("Press '?' to list all key bindings.".to_string(), None, false),
+        Item::new("Press 'q' to quit.".to_string(), None, false),
+    ];
+
+    let mut app = App::new(initial_updates, clock.clone());
+    app.list_state.select(Some(0));
+    for warning in startup_warnings {
+        app.info(warning);
+    }
+
+    // Without a usable file br starts with no feeds and default options. A
+    // missing one starts the first-run setup rather than an error.
+    let first_run = !config_path.exists();
+    let loaded = if first_run { None } else { read_config(&mut app, &config_path).await };
+    let (config, mut settings) = loaded
+        .unwrap_or_else(|| (Config::default(), Settings::resolve(&toml::Table::new(), &settings::env_var)));
+    let mut config = Arc::new(config);
+    configure(&mut app, &config, &settings);
+    app.show_detail = settings.show_detail;
+    if first_run {
+        app.input_mode = InputMode::Setup;
+        app.info(format!("No {} yet. Type the address of a blog or its feed below and press Enter, once per feed", config_path.display()));
+    }
+    
+    // Without a data directory nothing is saved, and the state files below
+    // are looked for in a temporary directory that does not exist.
+    let data_dir = match dirs::data_dir() {
+        Some(dir) => {
+            let data_dir = dir.join("br");
+            if let Err(e) = app.storage.probe(&data_dir) {
+                app.error(format!("{} is not writable ({}); running without saving state.", data_dir.display(), e));
+            }
+            data_dir
+        }
+        None => {
+            app.error("no data directory (set XDG_DATA_HOME or HOME); running without saving state.".to_string());
+            app.storage = Storage::discarding();
+            std::env::temp_dir().join(format!("br-{}", std::process::id()))
+        }
+    };
+
+    if app.storage.is_ephemeral() {
+        app.log.discard();
+    } else if let Err(e) = app.log.open(&data_dir.join("br.log")) {
+        app.log.discard();
+        app.error(format!("opening {}: {}", data_dir.join("br.log").display(), e));
+    }
+
+    app.content = ContentStore::new(data_dir.join("content"), app.storage.clone());
+    if !app.storage.is_ephemeral() && let Err(e) = app.content.clear() {
+        app.error(format!("clearing {}: {}", data_dir.join("content").display(), e));
+    }
+
+    let cache_path = data_dir.join(cache_file(&config_path)).to_string_lossy().to_string();
+    let cache_content = tokio::fs::read_to_string(&cache_path).await.unwrap_or_else(|_| "{}".to_string());
+    let stored: HashMap<String, StoredCacheEntry> = serde_json::from_str(&cache_content).unwrap_or_default();
+    let cache_map: HashMap<String, CacheEntry> = stored.into_iter().map(|(url, entry)| (url, entry.into())).collect();
+    let cache = Arc::new(Mutex::new(cache_map));
+
+    let session_path = data_dir.join("session.json");
+    let session = session::load(&session_path);
+    app.sort = session.sort;
+    if session.sort != SortOrder::ByDay {
+        app.flat_sort = session.sort;
+    }
+
+    let history_path = data_dir.join("history.json");
+    app.history = history::load(&history_path);
+    let health_path = data_dir.join("health.json");
+    app.health = health::load(&health_path);
+    let translations_path = data_dir.join("translations.json");
+    app.translations = Arc::new(Mutex::new(translate::load(&translations_path)));
+    let read_path = data_dir.join("read.json");
+    app.read_links = load_links(&read_path);
+    let seen_path = data_dir.join("seen.json");
+    app.seen_links = load_links(&seen_path);
+    let bookmarks_path = data_dir.join("bookmarks.json");
+    app.bookmarked_links = load_links(&bookmarks_path);
+    let stats_path = data_dir.join("stats.json");
+    app.stats = reading::load(&stats_path);
+    let annotations_path = data_dir.join("annotations.json");
+    app.annotations = std::fs::read_to_string(&annotations_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    // Kept until run_app returns; dropping it removes the socket.
+    let mut control = None;
+    if settings.control_socket {
+        match control::socket_path() {
+            Some(path) => match control::Server::start(path.clone(), app.channel.tx.clone(), config_path.clone(), app.storage.clone()).await {
+                Ok(server) => control = Some(server),
+                Err(e) => app.error(format!("control socket {}: {}", path.display(), e)),
+            },
+            None => app.error("control socket: no runtime directory (XDG_RUNTIME_DIR)".to_string()),
+        }
+    }
+    let mut last_unread = app.unread().count();
+    let mut last_title = String::new();
+
+    let mut last_tick = clock.now_instant();
+
+    loop {
+        // Below 10ms the loop would only keep a core busy.
+        let tick_rate = Duration::from_millis(settings.tick_rate_ms.max(10));
+        if std::mem::take(&mut app.read_changed) {
+            let links = app.read_links.clone();
+            app.save_links(&read_path, &links).await;
+        }
+        if std::mem::take(&mut app.seen_changed) {
+            let links = app.seen_links.clone();
+            app.save_links(&seen_path, &links).await;
+        }
+        if std::mem::take(&mut app.bookmarks_changed) {
+            let links = app.bookmarked_links.clone();
+            app.save_links(&bookmarks_path, &links).await;
+        }
+        if std::mem::take(&mut app.annotations_changed) {
+            let content = serde_json::to_string_pretty(&app.annotations).unwrap();
+            if let Err(e) = app.storage.write(&annotations_path, content.as_bytes()).await {
+                app.error(format!("writing {}: {} — state will not be saved this session", annotations_path.display(), e));
+            }
+        }
+        terminal.draw(|f| ui(f, &mut app))?;
+        if std::mem::take(&mut app.alert_due)
+            && let Err(e) = rules::ring(app.alert, app.alert_command.as_deref())
+        {
+            app.info(format!("Alert failed: {}", e));
+        }
+
+        let timeout = tick_rate.checked_sub(clock.now_instant() - last_tick).unwrap_or_else(|| Duration::from_secs(0));
+
+        let event = if crossterm::event::poll(timeout)? { Some(event::read()?) } else { None };
+        if let Some(Event::Mouse(mouse)) = &event
+            && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+            && matches!(app.input_mode, InputMode::Normal)
+            && let Some(category) = app.chip_at(mouse.column, mouse.row)
+        {
+            app.active_filter = format!("category:{}", category);
+            let rows = app.rows();
+            app.first(&rows);
+        }
+        if let Some(Event::Key(key)) = event {
+            let stack = app.contexts();
+            match app.keymap.route(stack, key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+                Some((_, Action::Quit)) => return Ok(()),
+                Some((_, Action::StartSearch)) => app.start_search(),
+                Some((_, Action::ClearFilter)) => app.clear_filter(),
+                Some((_, Action::First)) => {
+                    let rows = app.rows();
+                    app.first(&rows);
+                }
+                Some((_, Action::Last)) => {
+                    let rows = app.rows();
+                    app.last(&rows);
+                }
+                Some((_, Action::ToggleDisabled)) => {
+                    let rows = app.rows();
+                    let source = app.selected_item(&rows).filter(|item| !item.is_article()).and_then(|item| item.source.clone());
+                    match source.and_then(|name| Some((Arc::make_mut(&mut config).toggle_disabled(&name)?, name))) {
+                        Some((true, name)) => app.info(format!("Disabled {} until br quits or the config is reloaded", name)),
+                        Some((false, name)) => app.info(format!("Enabled {} again", name)),
+                        None => app.info("Select the [ERROR] row of a feed or site to disable it".to_string()),
+                    }
+                }
+                Some((_, Action::JumpNewAbove)) => {
+                    let rows = app.rows();
+                    app.jump_new_above(&rows);
+                }
+                Some((Context::History, Action::Next)) if !app.history.is_empty() => {
+                    let i = app.history_state.selected().map_or(0, |i| (i + 1).min(app.history.len() - 1));
+                    app.history_state.select(Some(i));
+                }
+                Some((Context::History, Action::Previous)) if !app.history.is_empty() => {
+                    let i = app.history_state.selected().map_or(0, |i| i.saturating_sub(1));
+                    app.history_state.select(Some(i));
+                }
+                Some((Context::History, Action::Next | Action::Previous)) => {}
+                Some((Context::Article, action @ (Action::Next | Action::Previous | Action::Expand))) => {
+                    if let Some(article) = &mut app.article {
+                        article.scroll = match action {
+                            Action::Next => article.scroll.saturating_add(1),
+                            Action::Previous => article.scroll.saturating_sub(1),
+                            _ => article.scroll.saturating_add(ARTICLE_PAGE),
+                        };
+                    }
+                }
+                Some((Context::Article, Action::Open)) => {
+                    if let Some(link) = app.article.as_ref().map(|article| article.link.clone()) {
+                        app.open_link(&link);
+                    }
+                }
+                Some((Context::FeedCheck, action @ (Action::Next | Action::Previous | Action::Expand))) => {
+                    if let Some(report) = &mut app.report {
+                        report.scroll = match action {
+                            Action::Next => report.scroll.saturating_add(1),
+                            Action::Previous => report.scroll.saturating_sub(1),
+                            _ => report.scroll.saturating_add(ARTICLE_PAGE),
+                        };
+                    }
+                }
+                Some((Context::FeedCheck, Action::Close)) => {
+                    app.report = None;
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((Context::Help, action @ (Action::Next | Action::Previous | Action::Expand))) => {
+                    app.help_scroll = match action {
+                        Action::Next => app.help_scroll.saturating_add(1),
+                        Action::Previous => app.help_scroll.saturating_sub(1),
+                        _ => app.help_scroll.saturating_add(ARTICLE_PAGE),
+                    };
+                }
+                Some((Context::Article, Action::Close)) => {
+                    app.article = None;
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::Next)) => {
+                    let rows = app.rows();
+                    app.next(&rows);
+                }
+                Some((_, Action::Previous)) => {
+                    let rows = app.rows();
+                    app.previous(&rows);
+                }
+                Some((_, Action::ToggleGroupByDay)) => {
+                    let order = if app.sort == SortOrder::ByDay { app.flat_sort } else { SortOrder::ByDay };
+                    app.set_sort(order, &session_path).await;
+                }
+                Some((_, Action::CycleSort)) => {
+                    app.set_sort(app.sort.next(), &session_path).await;
+                    app.info(format!("Sort: {}", app.sort.label()));
+                }
+                Some((_, Action::FoldDay)) => {
+                    let rows = app.rows();
+                    if app.sort == SortOrder::ByDay && let Some(item) = app.selected_item(&rows) && item.is_article() {
+                        let day = item.published.map(days::local_day);
+                        if !app.collapsed_days.remove(&day) {
+                            app.collapsed_days.insert(day);
+                        }
+                    }
+                }
+                Some((_, Action::UnfoldDays)) => {
+                    app.collapsed_days.clear();
+                }
+                Some((_, Action::MarkRead)) => {
+                    app.confirm_mark_read();
+                }
+                Some((_, Action::UndoMarkRead)) => {
+                    let restored = std::mem::take(&mut app.last_marked_read);
+                    if !restored.is_empty() {
+                        for &i in &restored {
+                            app.set_read(i, false, "restored to unread with U".to_string());
+                        }
+                        app.info(format!("Restored {} items to unread", restored.len()));
+                    }
+                }
+                Some((_, Action::ToggleRead)) => {
+                    let rows = app.rows();
+                    if let Some(i) = app.selected_index(&rows)
+                        && app.all_updates[i].is_article()
+                    {
+                        if app.all_updates[i].is_new {
+                            app.read_by_user(i, "marked read with r".to_string());
+                        } else {
+                            app.set_read(i, false, "marked unread with r".to_string());
+                        }
+                    }
+                }
+                Some((Context::Normal, Action::Star)) => {
+                    let rows = app.rows();
+                    if let Some(i) = app.selected_index(&rows)
+                        && app.all_updates[i].is_article()
+                    {
+                        app.toggle_bookmark(i);
+                    }
+                }
+                Some((_, Action::CycleGroup)) => {
+                    app.cycle_group();
+                    let rows = app.rows();
+                    app.first(&rows);
+                }
+                Some((_, Action::ShowBookmarks)) => {
+                    app.bookmarks_only = !app.bookmarks_only;
+                    let rows = app.rows();
+                    app.first(&rows);
+                }
+                Some((_, Action::ShowHistory)) => {
+                    app.history_expanded = None;
+                    app.history_state.select(if app.history.is_empty() { None } else { Some(0) });
+                    app.input_mode = InputMode::History;
+                }
+                Some((_, Action::ShowHelp)) => {
+                    app.help_scroll = 0;
+                    app.input_mode = InputMode::Help;
+                }
+                Some((_, Action::Refresh)) => {
+                    refresh(&mut app, &config, &cache, &cache_path);
+                }
+                Some((_, Action::Preview)) => {
+                    preview(&mut app, &config, &cache, &cache_path);
+                }
+                Some((_, Action::ApplyPreview)) => {
+                    let count = app.preview_groups().values().map(Vec::len).sum::<usize>();
+                    app.mark_seen();
+                    app.applying = std::mem::take(&mut app.staged);
+                    if let Some(preview_cache) = app.preview_cache.take() {
+                        let content = {
+                            let mut cache_guard = cache.lock().unwrap();
+                            *cache_guard = preview_cache.lock().unwrap().clone();
+                            serde_json::to_string_pretty(&*cache_guard).unwrap()
+                        };
+                        if let Err(e) = app.storage.write(Path::new(&cache_path), content.as_bytes()).await {
+                            app.error(format!("writing {}: {} — state will not be saved this session", cache_path, e));
+                        }
+                    }
+                    app.info(format!("Added {} items from the preview", count));
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::DiscardPreview)) => {
+                    app.staged.clear();
+                    app.preview_cache = None;
+                    app.info("Discarded the preview".to_string());
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::ToggleDetail)) => {
+                    app.show_detail = !app.show_detail;
+                }
+                Some((_, Action::ScrollDetailDown)) if app.show_detail => {
+                    app.detail_scroll.1 = app.detail_scroll.1.saturating_add(DETAIL_SCROLL);
+                }
+                Some((_, Action::ScrollDetailUp)) if app.show_detail => {
+                    app.detail_scroll.1 = app.detail_scroll.1.saturating_sub(DETAIL_SCROLL);
+                }
+                Some((_, Action::Peek)) => {
+                    let rows = app.rows();
+                    if app.selected_item(&rows).is_some_and(Item::is_article) {
+                        app.triaged = 0;
+                        app.input_mode = InputMode::Peek;
+                    }
+                }
+                Some((Context::Peek, action @ (Action::Open | Action::Star | Action::Hide | Action::MarkItemRead))) => {
+                    let rows = app.rows();
+                    if let Some(i) = app.selected_index(&rows) {
+                        let link = app.all_updates[i].link.clone().unwrap_or_default();
+                        // A link that could not be opened is shown instead of moving on.
+                        let mut advance = true;
+                        let now = app.clock.now_utc();
+                        match action {
+                            Action::Open => {
+                                app.read_by_user(i, "read: opened from peek".to_string());
+                                advance = app.open_link(&link);
+                                if advance {
+                                    app.track(i, Activity::Opened);
+                                }
+                            }
+                            Action::Star => app.toggle_bookmark(i),
+                            Action::Hide => {
+                                app.all_updates[i].hidden = true;
+                                app.all_updates[i].note(now, "hidden from peek".to_string());
+                            }
+                            _ => {
+                                app.read_by_user(i, "marked read from peek".to_string());
+                            }
+                        }
+                        if advance {
+                            app.triage_next(i);
+                        }
+                    }
+                }
+                Some((_, action @ (Action::Open | Action::ReloadConfig)))
+                    if action == Action::ReloadConfig || app.selected_item(&app.rows()).is_some_and(|item| item.edits_config) =>
+                {
+                    if action == Action::Open {
+                        match edit_file(terminal, &config_path) {
+                            Ok(status) if !status.success() => app.info(format!("The editor exited with {}", status)),
+                            Ok(_) => {}
+                            Err(e) => app.info(format!("Could not run the editor: {}", e)),
+                        }
+                    }
+                    // A file that does not parse leaves the loaded config as it is.
+                    if let Some((reloaded, reloaded_settings)) = read_config(&mut app, &config_path).await {
+                        configure(&mut app, &reloaded, &reloaded_settings);
+                        app.info(format!(
+                            "Config reloaded: {} feeds, {} manual sites",
+                            reloaded.feeds.as_ref().map_or(0, Vec::len),
+                            reloaded.manual.as_ref().map_or(0, Vec::len),
+                        ));
+                        config = Arc::new(reloaded);
+                        settings = reloaded_settings;
+                    }
+                }
+                Some((_, Action::Open)) => {
+                    let rows = app.rows();
+                    if let Some(i) = app.selected_index(&rows)
+                        && let Some(link) = app.all_updates[i].link.clone().filter(|link| !link.is_empty())
+                        && app.open_link(&link)
+                    {
+                        app.track(i, Activity::Opened);
+                    }
+                }
+                Some((_, Action::FetchArticle)) => {
+                    let rows = app.rows();
+                    if let Some(item) = app.selected_item(&rows)
+                        && let Some(link) = item.link.clone().filter(|link| !link.is_empty())
+                    {
+                        let title = item.translated_title.clone().or(item.title.clone()).unwrap_or_else(|| item.text.clone());
+                        app.info(format!("Fetching {}", link));
+                        app.article_pending = Some((link.clone(), title));
+                        let tx = app.channel.tx.clone();
+                        let http = app.http.clone();
+                        tokio::spawn(async move {
+                            let update = match fetch_article(&http, &link).await {
+                                Ok(text) => Update::FullArticle(link, text),
+                                Err(e) => Update::Info(format!("Could not fetch {}: {}", link, e)),
+                            };
+                            let _ = tx.send(update).await;
+                        });
+                    }
+                }
+                Some((_, Action::CheckFeed)) => {
+                    let rows = app.rows();
+                    let item = app.selected_item(&rows).filter(|item| item.is_article());
+                    let feed = item.and_then(|item| config.feeds.iter().flatten().find(|feed| item.source.as_ref() == Some(&feed.name)));
+                    match (item.and_then(Item::key), feed) {
+                        (Some(key), Some(feed)) => {
+                            let (key, feed) = (key.to_string(), feed.clone());
+                            app.info(format!("Checking the feed of {}", feed.name));
+                            app.report_pending = Some(key.clone());
+                            let tx = app.channel.tx.clone();
+                            let http = app.http.clone();
+                            tokio::spawn(async move {
+                                // Without the validators, so a 304 cannot
+                                // hide what the feed says now.
+                                let mut lines = Vec::new();
+                                for url in feed.url.all() {
+                                    let parsed = match feed.request(&http, url) {
+                                        Ok(request) => diagnose::check(request, url, &key, &mut lines).await,
+                                        Err(e) => {
+                                            lines.push(format!("{}: {}", url, e));
+                                            false
+                                        }
+                                    };
+                                    if parsed {
+                                        break;
+                                    }
+                                    lines.push(String::new());
+                                }
+                                let report = diagnose::Report { title: format!("Feed check: {}", feed.name), lines, scroll: 0 };
+                                let _ = tx.send(Update::FeedReport(key, report)).await;
+                            });
+                        }
+                        _ => app.info("Select an item from a feed to check that feed".to_string()),
+                    }
+                }
+                Some((_, Action::OpenListed)) => {
+                    app.open_listed(app.bulk_open);
+                }
+                Some((_, Action::OpenListedOther)) => {
+                    app.open_listed(app.bulk_open.other());
+                }
+                Some((_, Action::SubmitSearch)) => app.submit_search(),
+                Some((_, Action::CancelSearch)) => app.cancel_search(),
+                Some((_, Action::ToggleCase)) => {
+                    app.match_case = !app.match_case;
+                }
+                Some((_, Action::ToggleRegex)) => {
+                    app.search_mode = match app.search_mode {
+                        SearchMode::Substring => SearchMode::Regex,
+                        SearchMode::Regex => SearchMode::Substring,
+                    };
+                }
+                Some((_, Action::SetupUrl)) => {
+                    let input = std::mem::take(&mut app.pending_input);
+                    let input = input.trim();
+                    if !input.is_empty() {
+                        // A bare domain is taken to be served over https.
+                        let url = if input.contains("://") { input.to_string() } else { format!("https://{}", input) };
+                        app.info(format!("Looking for a feed at {}", url));
+                        app.setup_lookups += 1;
+                        let tx = app.channel.tx.clone();
+                        let http = app.http.clone();
+                        tokio::spawn(async move {
+                            let found = discover::discover(&http, &url).await;
+                            let _ = tx.send(Update::SetupFound(url, found)).await;
+                        });
+                    } else if app.setup_lookups > 0 {
+                        app.info(format!("Still looking at {} URLs, press Enter again when they are done", app.setup_lookups));
+                    } else if app.setup_feeds.is_empty() {
+                        app.info("Type the address of a blog first, or press Esc to skip the setup".to_string());
+                    } else if config_path.exists() {
+                        app.info(format!("{} was written meanwhile and is left alone, press Ctrl-r to load it", config_path.display()));
+                        app.setup_feeds.clear();
+                        app.input_mode = InputMode::Normal;
+                    } else {
+                        match config_store::create(&config_path, &app.setup_feeds) {
+                            Ok(()) => {
+                                app.info(format!("Wrote {} with {} feeds", config_path.display(), app.setup_feeds.len()));
+                                app.setup_feeds.clear();
+                                app.input_mode = InputMode::Normal;
+                                if let Some((loaded, loaded_settings)) = read_config(&mut app, &config_path).await {
+                                    configure(&mut app, &loaded, &loaded_settings);
+                                    config = Arc::new(loaded);
+                                    settings = loaded_settings;
+                                    refresh(&mut app, &config, &cache, &cache_path);
+                                }
+                            }
+                            Err(e) => app.error(e),
+                        }
+                    }
+                }
+                Some((_, Action::StartDateFilter)) => {
+                    app.pending_input.clear();
+                    app.input_mode = InputMode::DateFilter;
+                }
+                Some((_, Action::ApplyDateFilter)) => {
+                    let input = std::mem::take(&mut app.pending_input);
+                    let range = days::parse_date_filter(&input, app.clock.now_utc());
+                    if range.is_some() || input.trim().is_empty() {
+                        app.date_filter = range;
+                        app.input_mode = InputMode::Normal;
+                        let rows = app.rows();
+                        app.first(&rows);
+                    } else {
+                        app.info(format!("Not a date range: {}", input.trim()));
+                    }
+                }
+                Some((_, Action::ToggleSession)) => {
+                    if app.reading.is_some() {
+                        app.end_session(&stats_path, false).await;
+                    } else {
+                        app.pending_input.clear();
+                        app.input_mode = InputMode::Session;
+                    }
+                }
+                Some((_, Action::StartSession)) => {
+                    let input = std::mem::take(&mut app.pending_input);
+                    match input.trim() {
+                        "" => {
+                            app.reading = Some(ReadingSession::new(app.clock.now_utc(), app.clock.now_instant(), None));
+                            app.info("Reading session started, Ctrl-t to end it".to_string());
+                            app.input_mode = InputMode::Normal;
+                        }
+                        minutes => match minutes.parse::<u64>() {
+                            Ok(minutes) if minutes > 0 => {
+                                let limit = Duration::from_secs(minutes * 60);
+                                app.reading = Some(ReadingSession::new(app.clock.now_utc(), app.clock.now_instant(), Some(limit)));
+                                app.info(format!("Reading session started, {} to go, Ctrl-t to end it early", countdown(limit)));
+                                app.input_mode = InputMode::Normal;
+                            }
+                            _ => app.info(format!("Not a number of minutes: {}", minutes)),
                         },
-                        KeyCode::Char('u') => {
-                            for item in app.all_updates.iter_mut() {
-                                item.3 = false;
+                    }
+                }
+                Some((_, Action::SkipSetup)) => {
+                    app.pending_input.clear();
+                    app.setup_feeds.clear();
+                    app.setup_lookups = 0;
+                    app.input_mode = InputMode::Normal;
+                    app.info(format!("Setup skipped. Add feeds to {} and press Ctrl-r to load them", config_path.display()));
+                }
+                Some((_, Action::Annotate)) => {
+                    let rows = app.rows();
+                    if let Some(item) = app.selected_item(&rows).filter(|item| item.is_article())
+                        && let Some(link) = item.link.clone().filter(|link| !link.is_empty())
+                    {
+                        let text = app.annotations.get(&link).cloned().unwrap_or_default();
+                        app.note_draft = Some((link, text));
+                        app.input_mode = InputMode::Note;
+                    }
+                }
+                Some((_, Action::SaveNote)) => {
+                    if let Some((link, text)) = app.note_draft.take() {
+                        let text = text.trim();
+                        if text.is_empty() {
+                            if app.annotations.remove(&link).is_some() {
+                                app.info("Note deleted".to_string());
                             }
-                            app.all_updates.push(("Checking for updates...".to_string(), None, None, false));
-                            app.list_state.select(Some(app.all_updates.len().saturating_sub(1)));
-                            
-                            if let Some(feeds) = config.feeds.clone() {
-                                for feed in feeds {
-                                    let tx_clone = tx.clone();
-                                    tokio::spawn(fetch_feed(feed, tx_clone));
+                        } else {
+                            app.annotations.insert(link, text.to_string());
+                            app.info("Note saved".to_string());
+                        }
+                        app.annotations_changed = true;
+                    }
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((Context::Note, Action::InsertChar(c))) => {
+                    if let Some((_, text)) = &mut app.note_draft {
+                        text.push(c);
+                    }
+                }
+                Some((Context::Note, Action::DeleteChar)) => {
+                    if let Some((_, text)) = &mut app.note_draft {
+                        text.pop();
+                    }
+                }
+                Some((Context::Rule, Action::InsertChar(c))) => {
+                    if let Some(draft) = &mut app.rule_draft {
+                        draft.editing().push(c);
+                    }
+                }
+                Some((Context::Rule, Action::DeleteChar)) => {
+                    if let Some(draft) = &mut app.rule_draft {
+                        draft.editing().pop();
+                    }
+                }
+                Some((_, Action::InsertChar(c))) => {
+                    app.pending_input.push(c);
+                }
+                Some((_, Action::DeleteChar)) => {
+                    app.pending_input.pop();
+                }
+                Some((_, Action::BuildRule)) => {
+                    let rows = app.rows();
+                    if let Some((source, title)) = app.selected_item(&rows).and_then(Item::rule_fields) {
+                        app.rule_draft = Some(RuleDraft::new(source, title));
+                        app.input_mode = InputMode::Rule;
+                    }
+                }
+                Some((_, Action::SwitchField)) => {
+                    if let Some(draft) = &mut app.rule_draft {
+                        draft.editing_title = !draft.editing_title;
+                    }
+                }
+                Some((_, Action::ToggleField)) => {
+                    if let Some(draft) = &mut app.rule_draft {
+                        if draft.editing_title {
+                            draft.use_title = !draft.use_title;
+                        } else {
+                            draft.use_source = !draft.use_source;
+                        }
+                    }
+                }
+                Some((_, Action::CycleRuleKind)) => {
+                    if let Some(draft) = &mut app.rule_draft {
+                        draft.action = draft.action.next();
+                    }
+                }
+                Some((_, Action::SaveRule)) => {
+                    app.save_rule(&config_path);
+                }
+                Some((_, Action::Accept)) => {
+                    app.input_mode = InputMode::Normal;
+                    if let Some(confirmation) = app.confirmation.take() {
+                        match confirmation.action {
+                            PendingAction::MarkRead(indices) => {
+                                let note = if app.active_filter.is_empty() {
+                                    "marked read with R".to_string()
+                                } else {
+                                    format!("marked read with R, filter '{}'", app.active_filter)
+                                };
+                                for &i in &indices {
+                                    app.read_by_user(i, note.clone());
                                 }
+                                app.info(format!("Marked {} items read, U to undo", indices.len()));
+                                app.last_marked_read = indices;
                             }
-                            if let Some(manual_sites) = config.manual.clone() {
-                                for site in manual_sites {
-                                    let tx_clone = tx.clone();
-                                    let cache_clone = cache.clone();
-                                    let cache_path_clone = cache_path.clone();
-                                    tokio::spawn(check_manual_site(site, tx_clone, cache_clone, cache_path_clone));
+                            PendingAction::OpenTabs(indices) => app.open_tabs(&indices),
+                            PendingAction::RemoveFeed(name, key) => {
+                                if app.remove_feed(&config_path, &name, &key) && let Some(feeds) = &mut Arc::make_mut(&mut config).feeds {
+                                    feeds.retain(|feed| feed.key() != key);
                                 }
                             }
-                        },
-                        KeyCode::Char('o') | KeyCode::Enter => {
-                            if let Some(selected_index) = app.list_state.selected() {
-                                let filtered_updates: Vec<_> = app.all_updates.iter()
-                                    .filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase()))
-                                    .collect();
-
-                                if let Some((_, Some(link), _, _)) = filtered_updates.get(selected_index) {
-                                    if !link.is_empty() {
-                                        match open::that(link) {
-                                            Ok(_) => { let _ = tx.try_send(Update::Info(format!("Opened {}", link))); },
-                                            Err(e) => { let _ = tx.try_send(Update::Error(format!("Failed to open link: {}", e))); }
-                                        }
+                        }
+                    }
+                }
+                Some((_, Action::Cancel)) => {
+                    app.confirmation = None;
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::RemoveFeed)) => app.confirm_remove_feed(),
+                Some((_, Action::IgnoreFeed)) => app.ignore_gone_feed(&health_path).await,
+                Some((_, Action::Close)) => {
+                    app.rule_draft = None;
+                    app.note_draft = None;
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::CopyLink)) => {
+                    if let Some(link) = app.unopened_link.take() {
+                        match opener::copy(&link) {
+                            Ok(()) => app.info(format!("Copied {}", link)),
+                            Err(e) => app.info(format!("Could not copy link: {}", e)),
+                        }
+                    }
+                    app.input_mode = InputMode::Normal;
+                }
+                Some((_, Action::Expand)) => {
+                    let selected = app.history_state.selected();
+                    app.history_expanded = if app.history_expanded == selected { None } else { selected };
+                }
+                _ => {}
+            }
+        }
+
+        // Apply everything that arrived since the last frame. While a preview
+        // runs, new items are staged instead until it is applied or discarded.
+        // Tasks that died are looked for first, so whatever they sent before
+        // is applied ahead of their stand-in Finished.
+        let died = app.reap_tasks().await;
+        let mut updates = std::mem::take(&mut app.applying);
+        loop {
+            match app.channel.rx.try_recv() {
+                Ok(update @ (Update::NewFeedItem(..) | Update::ManualChange(..))) if app.refresh.as_ref().is_some_and(|r| r.preview) => {
+                    app.staged.push(update);
+                }
+                Ok(update) => updates.push(update),
+                // App holds a sender, so this is only ever Empty.
+                Err(_) => break,
+            }
+        }
+        updates.extend(died);
+        // Items arriving in a sorted view land above or below the selection;
+        // it follows its item unless something else moved it meanwhile, and
+        // the list scrolls along so the row stays where it was on screen.
+        let anchor = (!updates.is_empty()).then(|| app.anchor());
+        let arrived = app.all_updates.len();
+        for update in updates {
+            match update {
+                Update::NewFeedItem(blog_name, entry) => {
+                    app.collapse_manual(same_page(&entry.link), &blog_name);
+                    let key = entry.key().to_string();
+                    let is_duplicate = !app.listed_links.insert(key.clone());
+                    let new_link = Some(entry.link);
+                    if is_duplicate && let Some(refresh) = &mut app.refresh {
+                        *refresh.skipped.entry("already listed".to_string()).or_default() += 1;
+                    }
+                    if !is_duplicate {
+                        // Format the date into a string if it exists
+                        let date_str = entry.published.map(|dt| dt.format("%e %b %y").to_string());
+
+                        // A translated title is listed instead of the original, marked with ⇄.
+                        let translated = entry.translated.filter(translate::Translated::is_translation);
+                        let title = match &translated {
+                            Some(translated) => format!("⇄ {}", translated.title),
+                            None => entry.title.clone(),
+                        };
+                        
+                        // The searchable text; the list lays the columns out per frame.
+                        let display_text = format!("[FEED] {} | {} | {}", date_str.as_deref().unwrap_or_default(), blog_name, title);
+
+                        let summary = translated.as_ref().and_then(|t| t.summary.clone()).or(entry.summary);
+                        if let (Some(link), Some(summary)) = (&new_link, &summary)
+                            && let Err(e) = app.content.put(link, summary).await
+                        {
+                            app.error(format!("writing item content: {} — state will not be saved this session", e));
+                        }
+
+                        let read = app.read_links.contains(&key);
+                        let seen = !app.seen_links.insert(key);
+                        app.seen_changed |= !seen;
+                        let mut item = Item::new(display_text, new_link, !read && !seen);
+                        if read {
+                            item.note(app.clock.now_utc(), "read in an earlier run".to_string());
+                        } else if seen {
+                            item.note(app.clock.now_utc(), "listed in an earlier run".to_string());
+                        }
+                        if translated.is_some() {
+                            // The original title stays searchable.
+                            item.search_key = SearchKey::new(&format!("{} {}", item.text, entry.title));
+                        }
+                        item.translated_title = translated.map(|t| t.title);
+                        item.published = entry.published;
+                        item.date = date_str;
+                        item.snippet = summary.as_deref().map(summary::first_sentence);
+                        item.snippet_key = item.snippet.as_deref().map(SearchKey::new);
+                        item.summary_bytes = entry.summary_bytes;
+                        item.title = Some(entry.title);
+                        item.source = Some(blog_name.clone());
+                        item.permalink = entry.permalink;
+                        item.alternate = entry.alternate;
+                        item.author = entry.author;
+                        item.group = entry.group;
+                        item.categories = entry.categories.kept;
+                        item.category_total = entry.categories.total;
+                        if let Some(server) = &control {
+                            server.publish(serde_json::json!({
+                                "event": "item", "title": item.title, "source": item.source, "link": item.link,
+                            }));
+                        }
+                        app.all_updates.push(item);
+                        app.apply_rules(app.all_updates.len() - 1);
+                        app.note_fresh(app.all_updates.len() - 1);
+                        if let Some(refresh) = &mut app.refresh {
+                            *refresh.new_items.entry(blog_name).or_default() += 1;
+                        }
+                    }
+                }
+                Update::ManualChange(site_name, message, link, group) => {
+                    // A feed item of this refresh already is this page.
+                    let page = same_page(&link);
+                    if let Some(refresh) = &mut app.refresh
+                        && refresh.feed_pages.contains(&page)
+                    {
+                        *refresh.skipped.entry("same page as a feed item".to_string()).or_default() += 1;
+                        continue;
+                    }
+                    if let Some(server) = &control {
+                        server.publish(serde_json::json!({ "event": "item", "title": message, "source": site_name, "link": link }));
+                    }
+                    app.list_manual_change(site_name, message, link, group);
+                }
+                Update::Error(e) => {
+                    app.error(e);
+                    if let Some(refresh) = &mut app.refresh {
+                        refresh.errors += 1;
+                    }
+                }
+                Update::SourceError(name, e) => {
+                    app.error(e);
+                    // So 'x' knows which feed or site to disable.
+                    if let Some(item) = app.all_updates.last_mut() {
+                        item.source = Some(name);
+                    }
+                    if let Some(refresh) = &mut app.refresh {
+                        refresh.errors += 1;
+                    }
+                }
+                Update::Finished(bytes) => {
+                    let preview = app.refresh.as_ref().is_some_and(|r| r.preview);
+                    if let Some(summary) = app.finish_fetch(bytes) {
+                        if preview {
+                            app.info(format!("Preview done: {}", summary.headline()));
+                            app.input_mode = InputMode::Preview;
+                        } else {
+                            app.info(format!("Refresh done: {}", summary.headline()));
+                            let posts = std::mem::take(&mut app.fresh_posts);
+                            if !posts.is_empty() {
+                                let tx = app.channel.tx.clone();
+                                tokio::task::spawn_blocking(move || {
+                                    if let Err(e) = notify::send(&posts) {
+                                        let _ = tx.blocking_send(Update::Info(format!("Could not show a desktop notification: {}", e)));
                                     }
-                                }
+                                });
+                            }
+                            if summary.errors == 0 {
+                                // Entries the feeds no longer list would only pile up.
+                                let before = app.seen_links.len();
+                                app.seen_links.retain(|link| app.listed_links.contains(link));
+                                app.seen_changed |= app.seen_links.len() != before;
                             }
                         }
-                        _ => {}
-                    },
-                    InputMode::Search => match key.code {
-                        KeyCode::Enter => {
-                            app.input_mode = InputMode::Normal;
+                        if let Some(skew) = summary.clock_skew {
+                            app.set_clock_skew(skew);
                         }
-                        KeyCode::Char(c) => {
-                            app.input.push(c);
+                        if !preview {
+                            history::record(&mut app.history, summary);
+                            let content = serde_json::to_string_pretty(&app.history).unwrap();
+                            if let Err(e) = app.storage.write(&history_path, content.as_bytes()).await {
+                                app.error(format!("writing {}: {} — state will not be saved this session", history_path.display(), e));
+                            }
                         }
-                        KeyCode::Backspace => {
-                            app.input.pop();
+                        let content = serde_json::to_string_pretty(&app.health).unwrap();
+                        if let Err(e) = app.storage.write(&health_path, content.as_bytes()).await {
+                            app.error(format!("writing {}: {} — state will not be saved this session", health_path.display(), e));
                         }
-                        KeyCode::Esc => {
-                            app.input_mode = InputMode::Normal;
-                            app.input.clear();
+                        if config.translate.is_some() {
+                            // Items the feeds no longer list are not needed again.
+                            let content = {
+                                let mut translations = app.translations.lock().unwrap();
+                                translations.retain(|link, _| app.listed_links.contains(link));
+                                serde_json::to_string_pretty(&*translations).unwrap()
+                            };
+                            if let Err(e) = app.storage.write(&translations_path, content.as_bytes()).await {
+                                app.error(format!("writing {}: {} — state will not be saved this session", translations_path.display(), e));
+                            }
                         }
-                        _ => {}
-                    },
+                    }
+                }
+                Update::FeedStatus(name, key, gone) => {
+                    let threshold = settings.gone_after;
+                    let now = app.clock.now_utc();
+                    if health::record(&mut app.health, &key, gone, threshold, now) && !app.gone_feeds.iter().any(|(_, gone)| *gone == key) {
+                        app.gone_feeds.push((name, key));
+                    }
+                }
+                Update::HealthWarning(name, url, warning) => {
+                    if health::warn(&mut app.health, &url, warning.as_deref()) {
+                        app.info(format!("{}: {}", name, warning.unwrap_or_default()));
+                    }
+                }
+                Update::TranslateError(name, e) => {
+                    let reported = app.refresh.as_ref().is_some_and(|refresh| refresh.translate_failed);
+                    if !reported {
+                        app.error(format!("translating titles for {}: {} — showing originals", name, e));
+                    }
+                    if let Some(refresh) = &mut app.refresh {
+                        refresh.translate_failed = true;
+                    }
+                }
+                Update::ServerDate(server, local) => {
+                    if let Some(refresh) = &mut app.refresh {
+                        refresh.skew_samples.push((server - local).num_seconds());
+                    }
+                }
+                Update::Info(msg) => {
+                    app.info(msg);
+                }
+                Update::FullArticle(link, text) => {
+                    // Only the last page asked for is shown, and not over
+                    // something else that was opened meanwhile.
+                    if let Some((pending, title)) = app.article_pending.take_if(|(pending, _)| *pending == link)
+                        && matches!(app.input_mode, InputMode::Normal)
+                    {
+                        app.article = Some(Article { link: pending, title, text, scroll: 0 });
+                        app.input_mode = InputMode::Article;
+                    }
+                }
+                Update::FeedReport(key, report) => {
+                    if app.report_pending.take_if(|pending| *pending == key).is_some()
+                        && matches!(app.input_mode, InputMode::Normal)
+                    {
+                        app.report = Some(report);
+                        app.input_mode = InputMode::FeedCheck;
+                    }
+                }
+                Update::Control(request, reply) => {
+                    if let Request::Refresh = request {
+                        refresh(&mut app, &config, &cache, &cache_path);
+                    }
+                    let _ = reply.send(app.control_response(&request));
+                }
+                // Lookups still running when the setup was skipped are dropped.
+                Update::SetupFound(url, found) if matches!(app.input_mode, InputMode::Setup) => {
+                    app.setup_lookups = app.setup_lookups.saturating_sub(1);
+                    match found {
+                        Ok((feed_url, _)) if app.setup_feeds.iter().any(|(_, url)| *url == feed_url) => {
+                            app.info(format!("{} is added already", feed_url));
+                        }
+                        Ok((feed_url, title)) => {
+                            let name = title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).unwrap_or_else(|| feed_url.clone());
+                            app.info(format!("Found {} at {}", name, feed_url));
+                            app.setup_feeds.push((name, feed_url));
+                        }
+                        Err(e) => app.error(format!("no feed found at {}: {}", url, e)),
+                    }
+                }
+                Update::SetupFound(..) => {}
+                Update::Subscribed(feed) => {
+                    app.info(format!("Subscribed to {}, press u to fetch it", feed.name));
+                    let config = Arc::make_mut(&mut config);
+                    config.feeds.get_or_insert_with(Vec::new).push(feed);
+                    for warning in config.disambiguate_names() {
+                        app.info(warning);
+                    }
+                }
+            }
+        }
+        if let Some(anchor) = anchor {
+            app.follow_anchor(anchor, arrived);
+        }
+
+        let unread = app.unread().count();
+        if unread != last_unread {
+            last_unread = unread;
+            if let Some(server) = &control {
+                server.publish(serde_json::json!({ "event": "unread", "unread": unread }));
+            }
+        }
+        // Written only when it changes, between frames like the bell.
+        if settings.set_title {
+            let title = match &app.refresh {
+                Some(_) => "br — refreshing…".to_string(),
+                None => format!("br — {} unread", unread),
+            };
+            if title != last_title {
+                let _ = execute!(io::stdout(), SetTitle(&title));
+                last_title = title;
+            }
+        }
+
+        let now = clock.now_instant();
+        if now - last_tick >= tick_rate {
+            last_tick = now;
+            if app.reading.as_ref().is_some_and(|session| session.is_over(now)) {
+                app.end_session(&stats_path, true).await;
+            }
+            if app.refresh_due() {
+                start_refresh(&mut app, &config, &cache, &cache_path, false);
+            }
+        }
+    }
+}
+
+const HIGHLIGHT_SYMBOL: &str = ">> ";
+
+// From this many columns on, the detail pane is beside the list, not under it.
+const DETAIL_BESIDE_WIDTH: u16 = 160;
+
+// Lines the article view moves per Space.
+const ARTICLE_PAGE: u16 = 20;
+
+// Lines the detail pane moves per Ctrl-d or Ctrl-b.
+const DETAIL_SCROLL: u16 = 5;
+
+// "42s", "14m 05s" or "1h 02m".
+fn countdown(left: Duration) -> String {
+    let secs = left.as_secs_f64().ceil() as u64;
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
+    let beside = f.size().width >= DETAIL_BESIDE_WIDTH;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Min(0),
+                Constraint::Percentage(if app.show_detail && !beside { 30 } else { 0 }),
+                Constraint::Length(3),
+                Constraint::Length(7),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+    // On a wide terminal the detail pane goes to the right of the list.
+    let (list_area, detail_area) = if app.show_detail && beside {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(chunks[0]);
+        (halves[0], halves[1])
+    } else {
+        (chunks[0], chunks[1])
+    };
+        
+    let rows = app.rows();
+    app.clamp_selection(&rows);
+    let palette = app.palette;
+
+    let feed_items = || {
+        rows.iter()
+            .filter_map(|row| if let Row::Item(i) = row { Some(&app.all_updates[*i]) } else { None })
+            .filter(|item| item.text.starts_with("[FEED]"))
+    };
+    let columns = Columns::fit(
+        feed_items().filter_map(|item| item.date.as_deref()),
+        feed_items().filter_map(|item| item.source.as_deref()),
+        app.max_source_width,
+        list_area.width.saturating_sub(5) as usize,
+    );
+    app.columns = columns;
+
+    let show_snippets = app.show_snippets.enabled(f.size().width);
+    // Inside the borders and the highlight symbol.
+    let text_width = list_area.width.saturating_sub(5) as usize;
+
+    // Only the rows that fit are built, so long lists draw as fast as short
+    // ones. The window follows the selection the way List would scroll.
+    let height = list_area.height.saturating_sub(2).max(1) as usize;
+    // A filter that leaves nothing to select leaves the selection past the end.
+    let selected = app.list_state.selected().filter(|&selected| selected < rows.len());
+    let mut offset = app.list_state.offset().min(rows.len().saturating_sub(height));
+    if let Some(selected) = selected {
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+    }
+    // New items stop counting as above once they have been scrolled to.
+    let above: HashSet<usize> = rows[..offset]
+        .iter()
+        .filter_map(|row| match row {
+            Row::Item(i) if app.new_above.contains(i) => Some(*i),
+            _ => None,
+        })
+        .collect();
+    app.new_above = above;
+    let items: Vec<ListItem> = rows[offset..(offset + height).min(rows.len())]
+        .iter()
+        .map(|row| {
+            let item = match row {
+                Row::Item(i) => &app.all_updates[*i],
+                Row::Header { day, count, collapsed } => {
+                    let marker = if *collapsed { '▸' } else { '▾' };
+                    let label = app.day_label(*day);
+                    return ListItem::new(format!("{} {} ({})", marker, label, count))
+                        .style(palette.fg(palette.theme.day).add_modifier(Modifier::BOLD));
+                }
+            };
+            let text = app.label(item);
+            let chips = app.chips(item);
+            let is_article = item.is_article();
+            let bookmarked = app.is_bookmarked(item);
+            
+            let theme = &palette.theme;
+            let base_color = if bookmarked {
+                theme.bookmarked
+            } else if item.highlighted {
+                theme.highlighted
+            } else if item.text.starts_with("[FEED]") {
+                item.source.as_ref().and_then(|source| app.feed_colors.get(source)).copied().unwrap_or(theme.feed)
+            } else if item.text.starts_with("[MANUAL]") {
+                theme.manual
+            } else if text.starts_with("[ERROR]") {
+                theme.error
+            } else if text.starts_with("Checking") {
+                theme.status
+            } else {
+                theme.text
+            };
+
+            let style = if is_article {
+                if item.is_new {
+                    palette.new_item(base_color)
+                } else if bookmarked || item.highlighted {
+                    palette.fg(base_color)
+                } else {
+                    palette.fg(theme.read)
                 }
+            } else {
+                palette.fg(base_color)
+            };
+
+            let used = text.width() + chips.iter().map(|chip| chip.width()).sum::<usize>();
+            let mut spans = vec![Span::raw(text)];
+            spans.extend(chips.into_iter().map(|chip| Span::styled(chip, Style::default().add_modifier(Modifier::DIM))));
+            if let Some(snippet) = &item.snippet && show_snippets {
+                let room = text_width.saturating_sub(used + 3);
+                spans.push(Span::styled(format!(" — {}", summary::truncate(snippet, room)), Style::default().add_modifier(Modifier::DIM)));
+            }
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+        
+    // "12/87" over the selectable rows of the same snapshot that is drawn.
+    let total = rows.iter().filter(|row| row.is_selectable()).count();
+    let position = app.list_state.selected().filter(|&i| rows.get(i).is_some_and(Row::is_selectable)).map(|i| {
+        rows[..=i].iter().filter(|row| row.is_selectable()).count()
+    });
+    let position_title = match position {
+        Some(n) => format!(" {}/{} ", n, total),
+        None => format!(" -/{} ", total),
+    };
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(if app.storage.is_ephemeral() { "Blog Updates [ephemeral: not saving]" } else { "Blog Updates" })
+        .title(Title::from(position_title).position(Position::Bottom).alignment(Alignment::Right))
+        .border_style(palette.fg(palette.theme.text));
+    if !app.new_above.is_empty() {
+        let banner = Span::styled(format!(" ({} new above, n to jump) ", app.new_above.len()), palette.fg(palette.theme.secondary));
+        block = block.title(Title::from(banner).alignment(Alignment::Right));
+    }
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(palette.highlight())
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+    let mut window = ListState::default().with_selected(selected.map(|selected| selected - offset));
+    f.render_stateful_widget(list, list_area, &mut window);
+    *app.list_state.offset_mut() = offset;
+    app.list_area = list_area;
+    
+    let notice = app.gone_notice();
+    let (search_text, search_style) = match app.input_mode {
+        InputMode::Normal if notice.is_some() => (
+            notice.as_deref().unwrap_or(""),
+            palette.fg(palette.theme.prompt).add_modifier(Modifier::BOLD),
+        ),
+        InputMode::Normal | InputMode::History | InputMode::Peek | InputMode::Link | InputMode::Preview | InputMode::Rule | InputMode::Article | InputMode::FeedCheck | InputMode::Help => (app.active_filter.as_str(), Style::default()),
+        InputMode::Search | InputMode::Setup | InputMode::Session | InputMode::DateFilter => (app.pending_input.as_str(), palette.fg(palette.theme.prompt)),
+        InputMode::Note => (app.note_draft.as_ref().map_or("", |(_, text)| text.as_str()), palette.fg(palette.theme.prompt)),
+        InputMode::Confirm => (
+            app.confirmation.as_ref().map_or("", |c| c.prompt.as_str()),
+            palette.fg(palette.theme.prompt).add_modifier(Modifier::BOLD),
+        ),
+    };
+    // A regex that does not compile is searched for as text, and shown in red.
+    let showing_query = match app.input_mode {
+        InputMode::Normal => notice.is_none(),
+        InputMode::Note | InputMode::Confirm | InputMode::Setup | InputMode::Session | InputMode::DateFilter => false,
+        _ => true,
+    };
+    let query = app.query();
+    let error = query.error().filter(|_| showing_query);
+    let search_style = if error.is_some() { palette.fg(palette.theme.error) } else { search_style };
+    let mut mode = app.case_mode().name().to_string();
+    if app.search_mode == SearchMode::Regex {
+        mode.push_str(", regex");
+    }
+    // Counted as typed, so the query can be tried out before Enter.
+    if showing_query && !search_text.is_empty() {
+        let matches = rows.iter().filter(|row| matches!(row, Row::Item(_))).count();
+        mode.push_str(&format!(", {} matching", matches));
+    }
+    if let Some(error) = error {
+        mode.push_str(&format!(", bad regex: {}", error));
+    }
+    let mut search_title = if let InputMode::Note = app.input_mode {
+        "Note (Enter to save, empty to delete, Esc to cancel)".to_string()
+    } else if let InputMode::DateFilter = app.input_mode {
+        "Date range: 7d, 2w, 2024-01-01..2024-06-30 or 2024-06-01.. (empty clears it, Esc cancels)".to_string()
+    } else if let InputMode::Session = app.input_mode {
+        "Reading session length in minutes (empty for no limit, Esc to cancel)".to_string()
+    } else if let InputMode::Setup = app.input_mode {
+        format!("Blog or feed URL ({} found; Enter on an empty line writes config.toml, Esc skips)", app.setup_feeds.len())
+    } else if app.active_filter.is_empty() {
+        format!("Search [{}]", mode)
+    } else {
+        format!("Search [{}] (active: {})", mode, app.active_filter)
+    };
+    if app.bookmarks_only {
+        search_title.push_str(" [bookmarks, B to show all]");
+    }
+    if let Some(range) = app.date_filter {
+        search_title.push_str(&format!(" [dates {}, d to change]", days::describe_range(range)));
+    }
+    let search_bar = Paragraph::new(search_text)
+        .style(search_style)
+        .block(Block::default().borders(Borders::ALL).title(search_title));
+    f.render_widget(search_bar, chunks[2]);
+    
+    if let InputMode::Search | InputMode::Note | InputMode::Setup | InputMode::Session | InputMode::DateFilter = app.input_mode {
+        f.set_cursor(
+            chunks[2].x + search_text.width() as u16 + 1,
+            chunks[2].y + 1,
+        )
+    }
+
+    let info_items: Vec<ListItem> = app.info_messages
+        .iter()
+        .map(|msg| ListItem::new(msg.clone()).style(palette.fg(palette.theme.info)))
+        .collect();
+
+    let info_list = List::new(info_items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(app.info_title())
+            .border_style(palette.fg(palette.theme.info)),
+    );
+
+    f.render_widget(info_list, chunks[3]);
+    f.render_widget(Paragraph::new(app.status_line()).style(palette.fg(palette.theme.secondary)), chunks[4]);
+
+    if app.show_detail {
+        let selected = app.selected_index(&rows).filter(|&i| app.all_updates[i].is_article());
+        // In the color of the selected item's feed, as it is listed.
+        let color = selected
+            .and_then(|i| app.all_updates[i].source.as_ref())
+            .and_then(|source| app.feed_colors.get(source))
+            .copied()
+            .unwrap_or(palette.theme.feed);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Detail (p to hide, Ctrl-d/Ctrl-b to scroll)")
+            .title_style(palette.fg(color))
+            .border_style(palette.fg(color));
+        // Another item starts at the top.
+        if app.detail_scroll.0 != selected {
+            app.detail_scroll = (selected, 0);
+        }
+        let lines = match selected {
+            Some(i) => {
+                let summary = app.all_updates[i].link.clone().and_then(|link| app.content.get(&link));
+                detail_lines(&app.all_updates[i], app.annotation(&app.all_updates[i]), summary.as_deref(), usize::MAX, palette)
+            }
+            None => vec![Line::styled("No item selected.", palette.fg(palette.theme.secondary))],
+        };
+        // Wrapped lines are not counted, so this only keeps the last
+        // paragraph from scrolling out of sight entirely.
+        app.detail_scroll.1 = app.detail_scroll.1.min(lines.len().saturating_sub(1) as u16);
+        let paragraph = Paragraph::new(lines).block(block).wrap(Wrap { trim: true }).scroll((app.detail_scroll.1, 0));
+        f.render_widget(paragraph, detail_area);
+    }
+
+    match app.input_mode {
+        InputMode::History => render_history(f, app),
+        InputMode::Link => {
+            if let Some(link) = &app.unopened_link {
+                render_link(f, link, palette);
+            }
+        }
+        InputMode::Preview => render_preview(f, &app.preview_groups(), palette),
+        InputMode::Rule => {
+            if let Some(draft) = &app.rule_draft {
+                render_rule(f, draft, app.rule_preview(), palette);
+            }
+        }
+        InputMode::Article => {
+            if let Some(article) = &mut app.article {
+                render_article(f, article, palette);
+            }
+        }
+        InputMode::FeedCheck => {
+            if let Some(report) = &mut app.report {
+                render_report(f, report, palette);
+            }
+        }
+        InputMode::Help => render_help(f, app),
+        InputMode::Peek => {
+            if let Some(i) = app.selected_index(&rows) {
+                let summary = app.all_updates[i].link.clone().and_then(|link| app.content.get(&link));
+                render_peek(f, &app.all_updates[i], app.annotation(&app.all_updates[i]), summary.as_deref(), palette);
             }
         }
+        _ => {}
+    }
+}
+
+// The full text of a page over the whole screen.
+fn render_article(f: &mut Frame, article: &mut Article, palette: Palette) {
+    let area = f.size();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{} (j/k/Space scroll, o open, q close)", article.title))
+        .border_style(palette.fg(palette.theme.feed));
+    // Wrapped lines are not counted; this only stops at the last paragraph.
+    article.scroll = article.scroll.min(article.text.lines().count().saturating_sub(1) as u16);
+    let lines: Vec<Line> = article.text.lines().flat_map(|line| [Line::from(line.to_string()), Line::from("")]).collect();
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: true }).scroll((article.scroll, 0)), area);
+}
+
+// A quick look at the selected article over the list.
+fn render_peek(f: &mut Frame, item: &Item, annotation: Option<&str>, summary: Option<&str>, palette: Palette) {
+    let area = centered_rect(70, 60, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Peek (o open, s star, d hide, r read, Esc close)")
+        .border_style(palette.fg(palette.theme.feed));
+    let lines = detail_lines(item, annotation, summary, 20, palette);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: true }), area);
+}
+
+// What the peek popup and the detail pane show of an article: its title,
+// source, date, author, categories, the note written on it, why its state
+// changed, and up to `max_summary` lines of its summary.
+fn detail_lines(item: &Item, annotation: Option<&str>, summary: Option<&str>, max_summary: usize, palette: Palette) -> Vec<Line<'static>> {
+    let mut details = Vec::new();
+    details.extend(item.source.clone());
+    details.extend(item.published.map(|date| date.format("%e %b %Y").to_string().trim().to_string()));
+    details.extend(item.author.clone());
+
+    let title = item.translated_title.clone().or(item.title.clone()).unwrap_or_else(|| item.text.clone());
+    let mut lines = vec![Line::styled(title, Style::default().add_modifier(Modifier::BOLD))];
+    if let (Some(_), Some(original)) = (&item.translated_title, &item.title) {
+        lines.push(Line::styled(format!("⇄ {}", original), palette.fg(palette.theme.secondary)));
+    }
+    lines.push(Line::styled(details.join(" · "), palette.fg(palette.theme.secondary)));
+    if !item.categories.is_empty() {
+        let mut categories = item.categories.join(", ");
+        if item.category_total > item.categories.len() {
+            categories.push_str(&format!(" (+{} more)", item.category_total - item.categories.len()));
+        }
+        lines.push(Line::styled(categories, palette.fg(palette.theme.secondary)));
+    }
+    // What 'o' opens, when the feed's <link> and permalink guid differ.
+    if let (Some(link), Some(alternate)) = (&item.link, &item.alternate) {
+        let (feed_link, permalink) = if item.permalink.as_ref() == Some(link) { (alternate, link) } else { (link, alternate) };
+        let opens = |url: &String| if url == link { " (o opens)" } else { "" };
+        lines.push(Line::styled(format!("Link: {}{}", feed_link, opens(feed_link)), palette.fg(palette.theme.secondary)));
+        lines.push(Line::styled(format!("Permalink: {}{}", permalink, opens(permalink)), palette.fg(palette.theme.secondary)));
+    }
+    if let Some(note) = annotation {
+        lines.push(Line::styled(format!("Note: {}", note), palette.fg(palette.theme.prompt)));
+    }
+    // Why it was hidden or read, so that is never a mystery.
+    lines.extend(item.notes.iter().map(|note| Line::styled(note.clone(), palette.fg(palette.theme.secondary))));
+    lines.push(Line::from(""));
+    match summary {
+        Some(summary) => lines.extend(summary.lines().take(max_summary).map(|line| Line::from(line.to_string()))),
+        None => lines.push(Line::styled("No summary.", palette.fg(palette.theme.secondary))),
+    }
+    if let Some(bytes) = item.summary_bytes {
+        let size = format!("{} KB", bytes.div_ceil(1024));
+        lines.push(Line::styled(format!("Summary cut from {}; f fetches the full article.", size), palette.fg(palette.theme.secondary)));
+    }
+    lines
+}
 
-        if let Ok(update) = rx.try_recv() {
-            match update {
-                Update::NewFeedItem(blog_name, title, link, date) => {
-                    let new_link = Some(link);
-                    let is_duplicate = app.all_updates.iter().any(|(_, l, ..)| l == &new_link);
-                    if !is_duplicate {
-                        // Format the date into a string if it exists
-                        let date_str = date.map(|dt| dt.format("%e %b %y").to_string());
-                        
-                        // Create the final display text including the date
-                        let display_text = if let Some(d) = &date_str {
-                            format!("[FEED] {:>10} | {:<20} | {}", d, blog_name, title)
-                        } else {
-                            format!("[FEED] {:>10} | {:<20} | {}", " ".repeat(10), blog_name, title)
-                        };
-                        
-                        app.all_updates.push((display_text, new_link, date_str, true));
-                    }
+// What a preview would add, by source.
+fn render_preview(f: &mut Frame, groups: &BTreeMap<String, Vec<String>>, palette: Palette) {
+    let area = centered_rect(80, 70, f.size());
+    let total: usize = groups.values().map(Vec::len).sum();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Preview: {} new (a add, d discard)", total))
+        .border_style(palette.fg(palette.theme.status));
+
+    let mut lines = Vec::new();
+    if groups.is_empty() {
+        lines.push(Line::styled("Nothing new.", palette.fg(palette.theme.secondary)));
+    }
+    for (source, titles) in groups {
+        lines.push(Line::styled(format!("{} ({})", source, titles.len()), Style::default().add_modifier(Modifier::BOLD)));
+        lines.extend(titles.iter().map(|title| Line::from(format!("    {}", title))));
+    }
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+// The last resort when no browser could be started: the URL itself.
+fn render_link(f: &mut Frame, link: &str, palette: Palette) {
+    let area = centered_rect(70, 30, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Could not open a browser (y copy, Esc close)")
+        .border_style(palette.fg(palette.theme.error));
+    let lines = vec![
+        Line::from(""),
+        Line::styled(link.to_string(), Style::default().add_modifier(Modifier::BOLD)),
+        Line::from(""),
+        Line::styled("Set browser_command in config.toml to choose a browser.", palette.fg(palette.theme.secondary)),
+    ];
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).alignment(Alignment::Center).wrap(Wrap { trim: false }), area);
+}
+
+// The rule builder: both patterns, which of them are used, the action, and
+// how many listed feed items the rule would match right now.
+fn render_rule(f: &mut Frame, draft: &RuleDraft, preview: Result<(usize, usize), String>, palette: Palette) {
+    let area = centered_rect(70, 40, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("New rule (Tab switch field, Ctrl-t use field, Ctrl-a action, Enter save, Esc cancel)")
+        .border_style(palette.fg(palette.theme.status));
+
+    let field = |name: &str, pattern: &str, used: bool, editing: bool| {
+        let marker = if editing { ">" } else { " " };
+        let check = if used { "[x]" } else { "[ ]" };
+        let style = if used { Style::default() } else { palette.fg(palette.theme.secondary) };
+        Line::styled(format!("{} {} {:<7} {}", marker, check, name, pattern), style)
+    };
+    let preview = match preview {
+        Ok((matched, total)) => Line::styled(format!("Matches {} of {} listed feed items", matched, total), palette.fg(palette.theme.info)),
+        Err(e) => Line::styled(e, palette.fg(palette.theme.error)),
+    };
+    let lines = vec![
+        field("source", &draft.source, draft.use_source, !draft.editing_title),
+        field("title", &draft.title, draft.use_title, draft.editing_title),
+        Line::from(""),
+        Line::from(format!("  Action: {}", draft.action.label())),
+        Line::from(""),
+        preview,
+    ];
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+// What F found out about a feed, over the list.
+fn render_report(f: &mut Frame, report: &mut diagnose::Report, palette: Palette) {
+    let area = centered_rect(80, 80, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{} (j/k/Space scroll, Esc close)", report.title))
+        .border_style(palette.fg(palette.theme.feed));
+    report.scroll = report.scroll.min(report.lines.len().saturating_sub(1) as u16);
+    let lines: Vec<Line> = report.lines.iter().map(|line| Line::from(line.clone())).collect();
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }).scroll((report.scroll, 0)), area);
+}
+
+// Every context's keys, as the keymap has them after [keys] remapping.
+fn render_help(f: &mut Frame, app: &mut App) {
+    let area = centered_rect(70, 80, f.size());
+    let palette = app.palette;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Key bindings (j/k/Space scroll, ? or Esc close)")
+        .border_style(palette.fg(palette.theme.feed));
+
+    let mut lines = Vec::new();
+    for context in Context::ALL {
+        let entries = app.keymap.help(context);
+        if entries.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::styled(context.name().to_string(), Style::default().add_modifier(Modifier::BOLD)));
+        let width = entries.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+        for (keys, description) in entries {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", keys, width = width), palette.fg(palette.theme.prompt)),
+                Span::raw(description),
+            ]));
+        }
+    }
+    app.help_scroll = app.help_scroll.min(lines.len().saturating_sub(1) as u16);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block).scroll((app.help_scroll, 0)), area);
+}
+
+// A rectangle of the given percentage size centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+// Newest refresh first; Enter expands the selected one into per-source counts.
+fn render_history(f: &mut Frame, app: &mut App) {
+    let area = centered_rect(80, 70, f.size());
+    let palette = app.palette;
+    let title = if app.storage.is_ephemeral() {
+        "Refresh history (not being saved: data directory is not writable)"
+    } else {
+        "Refresh history (Enter to expand, Esc to close)"
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(palette.fg(palette.theme.status));
+
+    f.render_widget(Clear, area);
+    if app.history.is_empty() {
+        f.render_widget(Paragraph::new("No refreshes recorded yet.").block(block), area);
+        return;
+    }
+
+    let items: Vec<ListItem> = app.history
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, summary)| {
+            let mut lines = vec![Line::from(format!("{}  {}", summary.timestamp(), summary.headline()))];
+            if app.history_expanded == Some(i) {
+                for (reason, count) in &summary.skipped {
+                    lines.push(Line::styled(format!("    skipped {}: {}", count, reason), palette.fg(palette.theme.secondary)));
                 }
-                Update::ManualUpdate(message, link) => {
-                    let new_link = Some(link);
-                    let is_duplicate = app.all_updates.iter().any(|(_, l, ..)| l == &new_link);
-                    if !is_duplicate {
-                        app.all_updates.push((format!("[MANUAL] {}", message), new_link, None, true));
-                    }
+                if let Some(skew) = summary.clock_skew {
+                    let label = if skew.abs() >= skew::THRESHOLD_SECS { skew::describe(skew) } else { format!("clock skew {}s", skew) };
+                    lines.push(Line::styled(format!("    {}", label), palette.fg(palette.theme.secondary)));
                 }
-                Update::Error(e) => {
-                    app.all_updates.push((format!("[ERROR] {}", e), None, None, false));
+                if summary.new_items.is_empty() {
+                    lines.push(Line::styled("    nothing new", palette.fg(palette.theme.secondary)));
                 }
-                Update::Info(msg) => {
-                    app.info_messages.push(format!("[INFO] {}", msg));
-                    if app.info_messages.len() > 5 {
-                        app.info_messages.remove(0);
-                    }
+                for (source, count) in &summary.new_items {
+                    lines.push(Line::styled(format!("    {:<30} {}", source, count), palette.fg(palette.theme.secondary)));
+                }
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(palette.highlight());
+    f.render_stateful_widget(list, area, &mut app.history_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{SystemClock, TestClock};
+    use crate::testing::{self, MockServer, Response};
+    use crossterm::event::KeyCode;
+
+    fn app() -> App {
+        App::new(Vec::new(), Arc::new(SystemClock))
+    }
+
+    // Runs reap_tasks until every task of the refresh has ended.
+    async fn reap_all(app: &mut App) -> Vec<Update> {
+        let mut updates = Vec::new();
+        while app.refresh.as_ref().is_some_and(|refresh| !refresh.tasks.is_empty()) {
+            tokio::task::yield_now().await;
+            updates.extend(app.reap_tasks().await);
+        }
+        updates
+    }
+
+    fn article(text: &str, published: Option<DateTime<Utc>>) -> Item {
+        let mut item = Item::new(text.to_string(), Some(format!("https://example.com/{}", text)), false);
+        item.published = published;
+        item
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Local.with_ymd_and_hms(y, m, d, h, min, sec).earliest().unwrap().with_timezone(&Utc)
+    }
+
+    // The rows as text: headers with their day and count, items by text.
+    fn snapshot(app: &App) -> Vec<String> {
+        app.rows()
+            .iter()
+            .map(|row| match row {
+                Row::Header { day, count, collapsed } => {
+                    let day = day.map_or_else(|| "Undated".to_string(), |day| day.to_string());
+                    format!("== {} ({}){}", day, count, if *collapsed { " folded" } else { "" })
                 }
+                Row::Item(i) => app.all_updates[*i].text.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn day_headers_split_at_local_midnight_and_skip_empty_days() {
+        let mut app = App::new(
+            vec![
+                Item::new("status row".to_string(), None, false),
+                article("undated", None),
+                article("before midnight", Some(local(2024, 3, 10, 23, 59, 59))),
+                article("after midnight", Some(local(2024, 3, 11, 0, 0, 0))),
+                article("late", Some(local(2024, 3, 11, 22, 0, 0))),
+                // Nothing on the 9th, which gets no header.
+                article("two days back", Some(local(2024, 3, 8, 12, 0, 0))),
+            ],
+            Arc::new(SystemClock),
+        );
+        app.sort = SortOrder::ByDay;
+        assert_eq!(
+            snapshot(&app),
+            [
+                "status row",
+                "== 2024-03-11 (2)",
+                "late",
+                "after midnight",
+                "== 2024-03-10 (1)",
+                "before midnight",
+                "== 2024-03-08 (1)",
+                "two days back",
+                "== Undated (1)",
+                "undated",
+            ]
+        );
+
+        app.collapsed_days.insert(Some(NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()));
+        app.active_filter = "midnight".to_string();
+        assert_eq!(snapshot(&app), ["== 2024-03-11 (1) folded", "== 2024-03-10 (1)", "before midnight"]);
+    }
+
+    #[test]
+    fn changes_during_the_cooldown_are_reported_once_with_a_count() {
+        let cooldown = chrono::Duration::minutes(60);
+        let start = Utc::now();
+        let mut entry = CacheEntry::default();
+        let reported: Vec<Option<String>> = [0, 5, 10, 15, 59, 61, 62]
+            .into_iter()
+            .map(|minutes| entry.record_change("Flappy", start + chrono::Duration::minutes(minutes), cooldown))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                Some("New content detected on Flappy".to_string()),
+                None,
+                None,
+                None,
+                None,
+                Some("New content detected on Flappy (+4 more changes during cooldown)".to_string()),
+                None,
+            ]
+        );
+        assert_eq!(entry.suppressed, 1);
+    }
+
+    #[test]
+    fn a_zero_cooldown_reports_every_change() {
+        let start = Utc::now();
+        let mut entry = CacheEntry::default();
+        for seconds in 0..3 {
+            let at = start + chrono::Duration::seconds(seconds);
+            assert_eq!(entry.record_change("Site", at, chrono::Duration::zero()).as_deref(), Some("New content detected on Site"));
+        }
+    }
+
+    #[test]
+    fn every_manual_change_of_a_site_is_listed() {
+        let mut app = app();
+        let url = "https://example.com/news".to_string();
+        app.list_manual_change("Site".to_string(), "New content detected on Site".to_string(), url.clone(), None);
+        let later = "New content detected on Site (+3 more changes during cooldown)".to_string();
+        app.list_manual_change("Site".to_string(), later, url, None);
+        let texts: Vec<&str> = app.all_updates.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["[MANUAL] New content detected on Site", "[MANUAL] New content detected on Site (+3 more changes during cooldown)"]
+        );
+    }
+
+    fn app_at(now: DateTime<Utc>) -> (App, Arc<TestClock>) {
+        let clock = Arc::new(TestClock::new(now));
+        (App::new(Vec::new(), clock.clone()), clock)
+    }
+
+    #[test]
+    fn timed_refreshes_follow_the_clock() {
+        let (mut app, clock) = app_at(local(2024, 3, 11, 9, 0, 0));
+        app.refresh_interval = Some(Duration::from_secs(300));
+        assert!(!app.refresh_due());
+        assert_eq!(app.info_title(), "Info (next refresh in 5m 00s)");
+
+        clock.advance(chrono::Duration::seconds(270));
+        assert!(!app.refresh_due());
+        assert_eq!(app.info_title(), "Info (next refresh in 30s)");
+
+        clock.advance(chrono::Duration::seconds(30));
+        assert!(app.refresh_due());
+        // Not while one is running, or while a query is being typed.
+        app.refresh = Some(Refresh::new(app.clock.now_instant(), false));
+        assert!(!app.refresh_due());
+        assert_eq!(app.info_title(), "Info");
+        app.refresh = None;
+        app.input_mode = InputMode::Search;
+        assert!(!app.refresh_due());
+        app.input_mode = InputMode::Normal;
+
+        // Starting one, by hand or on time, resets the countdown.
+        app.last_refresh = app.clock.now_instant();
+        assert!(!app.refresh_due());
+        assert_eq!(app.info_title(), "Info (next refresh in 5m 00s)");
+    }
+
+    #[test]
+    fn day_labels_move_on_with_the_clock() {
+        let (app, clock) = app_at(local(2024, 3, 11, 23, 59, 0));
+        let day = Some(NaiveDate::from_ymd_opt(2024, 3, 11).unwrap());
+        assert_eq!(app.day_label(day), "Today");
+        clock.advance(chrono::Duration::minutes(1));
+        assert_eq!(app.day_label(day), "Yesterday");
+        clock.set(local(2024, 3, 14, 12, 0, 0));
+        assert_eq!(app.day_label(day), "Monday 11 Mar");
+        assert_eq!(app.day_label(None), "Undated");
+    }
+
+    #[test]
+    fn the_manual_cooldown_runs_on_the_clock() {
+        let (app, clock) = app_at(local(2024, 3, 11, 9, 0, 0));
+        let cooldown = chrono::Duration::minutes(60);
+        let mut entry = CacheEntry::default();
+        assert!(entry.record_change("Site", app.clock.now_utc(), cooldown).is_some());
+        clock.advance(chrono::Duration::minutes(59));
+        assert!(entry.record_change("Site", app.clock.now_utc(), cooldown).is_none());
+        clock.advance(chrono::Duration::minutes(1));
+        assert!(entry.record_change("Site", app.clock.now_utc(), cooldown).is_some_and(|message| message.ends_with("(+1 more changes during cooldown)")));
+    }
+
+    #[test]
+    fn an_ignored_gone_feed_is_noticed_again_after_30_days() {
+        let (mut app, clock) = app_at(local(2024, 3, 11, 9, 0, 0));
+        let feed = "https://example.com/feed.xml";
+        assert!(health::record(&mut app.health, feed, Some(410), 1, app.clock.now_utc()));
+        health::ignore(&mut app.health, feed, app.clock.now_utc());
+        clock.advance(chrono::Duration::days(health::IGNORE_DAYS) - chrono::Duration::seconds(1));
+        assert!(!health::record(&mut app.health, feed, Some(410), 1, app.clock.now_utc()));
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(health::record(&mut app.health, feed, Some(410), 1, app.clock.now_utc()));
+    }
+
+    // Routes `code` like run_app does for the search transitions and the
+    // gone-feed notice.
+    fn press(app: &mut App, code: KeyCode) {
+        match app.keymap.route(app.contexts(), code, false) {
+            Some((_, Action::StartSearch)) => app.start_search(),
+            Some((_, Action::SubmitSearch)) => app.submit_search(),
+            Some((_, Action::CancelSearch)) => app.cancel_search(),
+            Some((_, Action::ClearFilter)) => app.clear_filter(),
+            Some((_, Action::InsertChar(c))) => app.pending_input.push(c),
+            Some((_, Action::RemoveFeed)) => app.confirm_remove_feed(),
+            Some((_, Action::Cancel)) => {
+                app.confirmation = None;
+                app.input_mode = InputMode::Normal;
             }
+            _ => {}
         }
+    }
+
+    #[test]
+    fn the_filters_combine() {
+        let mut hidden = article("rust hidden", Some(local(2024, 3, 11, 12, 0, 0)));
+        hidden.hidden = true;
+        let mut app = App::new(
+            vec![
+                article("rust early", Some(local(2024, 3, 9, 12, 0, 0))),
+                article("rust in range", Some(local(2024, 3, 10, 0, 0, 0))),
+                article("go in range", Some(local(2024, 3, 10, 18, 0, 0))),
+                article("rust undated", None),
+                article("rust at the end", Some(local(2024, 3, 11, 0, 0, 0))),
+                hidden,
+            ],
+            Arc::new(SystemClock),
+        );
+        let visible = |app: &App| -> Vec<String> {
+            let query = app.query();
+            app.all_updates.iter().filter(|item| app.matches(item, &query)).map(|item| item.text.clone()).collect()
+        };
+        // Hidden items never show.
+        assert_eq!(visible(&app), ["rust early", "rust in range", "go in range", "rust undated", "rust at the end"]);
 
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
+        // The range includes its start but not its end; undated items pass.
+        app.date_filter = Some((local(2024, 3, 10, 0, 0, 0), local(2024, 3, 11, 0, 0, 0)));
+        assert_eq!(visible(&app), ["rust in range", "go in range", "rust undated"]);
+
+        app.active_filter = "rust".to_string();
+        assert_eq!(visible(&app), ["rust in range", "rust undated"]);
+
+        app.bookmarked_links.insert("https://example.com/rust undated".to_string());
+        app.bookmarked_links.insert("https://example.com/rust early".to_string());
+        app.bookmarks_only = true;
+        assert_eq!(visible(&app), ["rust undated"]);
+
+        app.date_filter = None;
+        assert_eq!(visible(&app), ["rust early", "rust undated"]);
+        app.active_filter.clear();
+        app.bookmarks_only = false;
+        assert_eq!(visible(&app).len(), 5);
+    }
+
+    #[test]
+    fn the_mark_read_prompt_names_every_filter() {
+        let mut app = app();
+        for (text, day) in [("rust one", 10), ("rust two", 11), ("go", 11)] {
+            let mut item = article(text, Some(local(2024, 3, day, 12, 0, 0)));
+            item.is_new = true;
+            app.all_updates.push(item);
         }
+        let prompt = |app: &mut App| {
+            app.confirm_mark_read();
+            app.confirmation.take().map(|confirmation| confirmation.prompt)
+        };
+        assert_eq!(prompt(&mut app).as_deref(), Some("Mark all 3 items read? (y/n)"));
+
+        app.date_filter = Some((local(2024, 3, 11, 0, 0, 0), local(2024, 3, 12, 0, 0, 0)));
+        assert_eq!(prompt(&mut app).as_deref(), Some("Mark 2 items dated 2024-03-11..2024-03-11 read? (y/n)"));
+        app.active_filter = "rust".to_string();
+        assert_eq!(prompt(&mut app).as_deref(), Some("Mark 1 items matching 'rust' dated 2024-03-11..2024-03-11 read? (y/n)"));
+
+        app.date_filter = None;
+        app.bookmarked_links.insert("https://example.com/rust one".to_string());
+        app.bookmarks_only = true;
+        assert_eq!(prompt(&mut app).as_deref(), Some("Mark 1 bookmarked items matching 'rust' read? (y/n)"));
     }
-}
 
+    #[test]
+    fn changes_that_cannot_be_saved_are_refused() {
+        let mut app = app();
+        app.storage = Storage::discarding();
+        app.all_updates.push(article("post", None));
+        app.toggle_bookmark(0);
+        assert!(app.bookmarked_links.is_empty());
 
-fn ui(f: &mut Frame, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints(
+        let config_path = testing::temp_dir("ephemeral-rule").join("config.toml");
+        app.rule_draft = Some(RuleDraft::new("Blog", "post"));
+        app.input_mode = InputMode::Rule;
+        app.save_rule(&config_path);
+        assert!(!config_path.exists());
+        assert!(app.rules.is_empty());
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(
+            app.info_messages,
+            ["[INFO] Bookmark not saved: data directory is not writable", "[INFO] Rule not saved: data directory is not writable"]
+        );
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+    }
+
+    #[test]
+    fn search_transitions() {
+        let mut app = app();
+        // Enter applies what was typed.
+        press(&mut app, KeyCode::Char('/'));
+        assert!(matches!(app.input_mode, InputMode::Search));
+        type_text(&mut app, "rust");
+        assert_eq!((app.pending_input.as_str(), app.active_filter.as_str()), ("rust", ""));
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!((app.pending_input.as_str(), app.active_filter.as_str()), ("", "rust"));
+
+        // '/' starts from the applied filter; Esc drops the edit and keeps it.
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(app.pending_input, "rust");
+        type_text(&mut app, " async");
+        assert!(app.query().matches(&SearchKey::new("rust async")));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!((app.pending_input.as_str(), app.active_filter.as_str()), ("", "rust"));
+        assert!(!app.query().matches(&SearchKey::new("go")));
+
+        // Esc in the list clears the filter and says so; again does nothing.
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.active_filter, "");
+        assert_eq!(app.info_messages.last().map(String::as_str), Some("[INFO] Filter cleared"));
+        let shown = app.info_messages.len();
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.info_messages.len(), shown);
+
+        // Enter on an emptied query removes the filter.
+        app.active_filter = "rust".to_string();
+        press(&mut app, KeyCode::Char('/'));
+        app.pending_input.clear();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.active_filter, "");
+    }
+
+    // Fetches `feed` once without retries and returns what it sent.
+    async fn fetch(feed: &Feed, cache: &Cache) -> Vec<Update> {
+        let (tx, mut rx) = mpsc::channel(100);
+        fetch_feed(&reqwest::Client::new(), feed, cache, tx, Arc::new(SystemClock), None, 0, 0, 0).await;
+        let mut updates = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            updates.push(update);
+        }
+        updates
+    }
+
+    fn mirrors(server: &MockServer, paths: &[&str]) -> Feed {
+        let mut feed = Feed::new("Mirrored".to_string(), String::new(), None);
+        feed.url = FeedUrls::Mirrors(paths.iter().map(|path| server.url(path)).collect());
+        feed
+    }
+
+    #[tokio::test]
+    async fn a_failing_mirror_falls_back_to_the_next() {
+        let server = MockServer::start(|request| match (request.path.as_str(), request.headers.get("if-none-match")) {
+            ("/primary.xml", _) => Response::new(500, "down"),
+            ("/mirror.xml", Some(etag)) if etag == "\"v1\"" => Response::new(304, ""),
+            ("/mirror.xml", _) => Response::new(200, testing::rss(&["one", "two"])).header("ETag", "\"v1\""),
+            _ => Response::new(404, ""),
+        });
+        let feed = mirrors(&server, &["/primary.xml", "/mirror.xml"]);
+        let cache: Cache = Arc::default();
+
+        let updates = fetch(&feed, &cache).await;
+        let titles: Vec<&str> = updates.iter().filter_map(|update| match update {
+            Update::NewFeedItem(_, entry) => Some(entry.title.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(titles, ["one", "two"]);
+        assert!(updates.iter().any(|update| matches!(update, Update::Info(message) if *message == format!("Mirrored served by mirror {}", server.url("/mirror.xml")))));
+        assert!(!updates.iter().any(|update| matches!(update, Update::SourceError(..))));
+        // Served, so the feed's health is reset under its first URL.
+        assert!(updates.iter().any(|update| matches!(update, Update::FeedStatus(_, key, None) if *key == server.url("/primary.xml"))));
+
+        // The validators are kept for the mirror that sent them.
+        assert!(cache.lock().unwrap().get(&server.url("/mirror.xml")).is_some_and(|entry| entry.etag.as_deref() == Some("\"v1\"")));
+        assert!(!cache.lock().unwrap().contains_key(&server.url("/primary.xml")));
+        let updates = fetch(&feed, &cache).await;
+        assert!(updates.iter().any(|update| matches!(update, Update::Info(message) if message == "No changes for Mirrored")));
+        let sent: Vec<(String, Option<String>)> = server.requests().into_iter().map(|request| (request.path, request.headers.get("if-none-match").cloned())).collect();
+        assert_eq!(
+            sent,
             [
-                Constraint::Min(0),
-                Constraint::Length(3),
-                Constraint::Length(7),
+                ("/primary.xml".to_string(), None),
+                ("/mirror.xml".to_string(), None),
+                ("/primary.xml".to_string(), None),
+                ("/mirror.xml".to_string(), Some("\"v1\"".to_string())),
             ]
-            .as_ref(),
-        )
-        .split(f.size());
-        
-    let updates: Vec<_> = app.all_updates
-        .iter()
-        .filter(|(text, ..)| text.to_lowercase().contains(&app.input.to_lowercase()))
-        .collect();
-    
-    if let Some(selected) = app.list_state.selected() {
-        if selected >= updates.len() {
-            app.list_state.select(Some(updates.len().saturating_sub(1)));
+        );
+    }
+
+    #[tokio::test]
+    async fn enormous_summaries_are_cut_at_a_char_boundary() {
+        // Multi-byte text past the limit, and an image inlined into the text.
+        let image = format!("![chart](data:image/png;base64,{})", "iVBORw0KGgo".repeat(1000));
+        let body = format!("<p>Café crème {} {} done.</p>", image, "ünïcödé ".repeat(500));
+        let xml = format!(
+            "<?xml version=\"1.0\"?><rss version=\"2.0\"><channel><title>Big</title>\
+             <item><title>huge</title><link>https://example.com/huge</link><description><![CDATA[{}]]></description></item>\
+             </channel></rss>",
+            body
+        );
+        let server = MockServer::start(move |_| Response::new(200, xml.clone()));
+        let feed = Feed::new("Big".to_string(), server.url("/feed.xml"), None);
+        async fn fetch_capped(feed: &Feed, max_content_bytes: usize) -> Entry {
+            let (tx, mut rx) = mpsc::channel(100);
+            fetch_feed(&reqwest::Client::new(), feed, &Cache::default(), tx, Arc::new(SystemClock), None, 0, max_content_bytes, 0).await;
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .find_map(|update| match update {
+                    Update::NewFeedItem(_, entry) => Some(entry),
+                    _ => None,
+                })
+                .unwrap()
         }
+
+        let whole = fetch_capped(&feed, 0).await;
+        let whole_summary = whole.summary.unwrap();
+        assert_eq!(whole.summary_bytes, None);
+        assert!(whole_summary.starts_with("Café crème ünïcödé") && whole_summary.ends_with("done."));
+
+        let entry = fetch_capped(&feed, 1001).await;
+        let summary = entry.summary.unwrap();
+        assert!(summary.ends_with(" (truncated)"), "{}", summary);
+        assert!(summary.len() <= 1001 + " (truncated)".len());
+        assert!(whole_summary.starts_with(summary.trim_end_matches(" (truncated)")));
+        assert_eq!(entry.summary_bytes, Some(whole_summary.len()));
+
+        // What the list searches is built from the cut text.
+        let snippet = summary::first_sentence(&summary);
+        assert!(Query::new("ünïcödé", CaseMode::Ignore, SearchMode::Substring).matches(&SearchKey::new(&snippet)));
+        let mut text = "é".repeat(10);
+        assert_eq!(summary::cap(&mut text, 0), None);
+        assert_eq!(summary::cap(&mut text, 5), Some(20));
+        assert_eq!(text, "éé (truncated)");
     }
 
-    let items: Vec<ListItem> = updates
-        .iter()
-        .map(|(text, _, _, is_new)| { // Unpack the new tuple
-            let is_article = text.starts_with("[FEED]") || text.starts_with("[MANUAL]");
-            
-            let base_color = if text.starts_with("[FEED]") {
-                Color::Cyan
-            } else if text.starts_with("[MANUAL]") {
-                Color::Yellow
-            } else if text.starts_with("[ERROR]") {
-                Color::Red
-            } else if text.starts_with("Checking") {
-                Color::Magenta
-            } else {
-                Color::White
-            };
+    #[tokio::test]
+    async fn all_mirrors_failing_lists_each_attempt() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/broken.xml" => Response::new(500, ""),
+            _ => Response::new(404, ""),
+        });
+        let updates = fetch(&mirrors(&server, &["/broken.xml", "/gone.xml"]), &Arc::default()).await;
+        let expected = format!(
+            "fetching Mirrored: all 2 mirrors failed ({}: HTTP 500; {}: HTTP 404)",
+            server.url("/broken.xml"),
+            server.url("/gone.xml")
+        );
+        assert!(updates.iter().any(|update| matches!(update, Update::SourceError(_, message) if *message == expected)));
+        // One mirror failing otherwise does not make the feed look gone.
+        assert!(!updates.iter().any(|update| matches!(update, Update::FeedStatus(..))));
 
-            let style = if is_article {
-                if *is_new { // Dereference the borrowed bool
-                    Style::default().fg(base_color)
-                } else {
-                    Style::default().fg(Color::Gray)
+        let updates = fetch(&mirrors(&server, &["/gone.xml", "/also-gone.xml"]), &Arc::default()).await;
+        assert!(updates.iter().any(|update| matches!(update, Update::FeedStatus(_, _, Some(404)))));
+    }
+
+    #[tokio::test]
+    async fn a_panicking_fetch_task_still_finishes_the_refresh() {
+        let mut app = app();
+        let tx = app.channel.tx.clone();
+        let healthy = tokio::spawn(async move {
+            let _ = tx.send(Update::Finished(10)).await;
+        });
+        let dying = tokio::spawn(async { panic!("boom") });
+        let mut refresh = Refresh::new(Instant::now(), false);
+        refresh.add_tasks(vec![("Healthy".to_string(), healthy), ("Dying".to_string(), dying)]);
+        app.refresh = Some(refresh);
+
+        let died = reap_all(&mut app).await;
+        assert!(matches!(&died[..], [Update::SourceError(name, _), Update::Finished(0)] if name == "Dying"));
+        assert!(matches!(app.channel.rx.try_recv(), Ok(Update::Finished(10))));
+        assert!(app.finish_fetch(10).is_none());
+        let summary = app.finish_fetch(0).expect("both tasks accounted for");
+        assert_eq!(summary.bytes, 10);
+        assert!(app.refresh.is_none());
+
+        // The next refresh goes through the same channel as before.
+        let tx = app.channel.tx.clone();
+        let next = tokio::spawn(async move {
+            let _ = tx.send(Update::Finished(5)).await;
+        });
+        let mut refresh = Refresh::new(Instant::now(), false);
+        refresh.add_tasks(vec![("Healthy".to_string(), next)]);
+        app.refresh = Some(refresh);
+        assert!(reap_all(&mut app).await.is_empty());
+        assert!(matches!(app.channel.rx.try_recv(), Ok(Update::Finished(5))));
+        assert_eq!(app.finish_fetch(5).map(|summary| summary.bytes), Some(5));
+    }
+
+    #[tokio::test]
+    async fn the_gone_feed_notice_asks_before_removing() {
+        let dir = testing::temp_dir("notice");
+        let (config_path, health_path) = (dir.join("config.toml"), dir.join("health.json"));
+        let (old, dead) = ("https://old.example/feed".to_string(), "https://dead.example/feed".to_string());
+        std::fs::write(&config_path, format!("[[feeds]]\nname = \"Old\"\nurl = \"{}\"\n", old)).unwrap();
+        let mut app = app();
+        for key in [&old, &dead] {
+            assert!(health::record(&mut app.health, key, Some(410), 1, app.clock.now_utc()));
+        }
+        app.gone_feeds = vec![("Old".to_string(), old.clone()), ("Dead".to_string(), dead.clone())];
+        assert!(app.gone_notice().unwrap().starts_with("Feed 'Old' appears gone (410, 1 failures)"));
+
+        // The normal keys keep working under the notice.
+        assert_eq!(app.keymap.route(app.contexts(), KeyCode::Char('d'), false), Some((Context::Normal, Action::StartDateFilter)));
+        assert_eq!(app.keymap.route(app.contexts(), KeyCode::Char('i'), false), None);
+
+        // X asks first; anything but y keeps the feed and the notice.
+        press(&mut app, KeyCode::Char('X'));
+        assert_eq!(app.confirmation.as_ref().unwrap().prompt, format!("Remove 'Old' ({}) from config.toml? (y/n)", old));
+        press(&mut app, KeyCode::Char('n'));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.gone_feeds.len(), 2);
+        assert!(std::fs::read_to_string(&config_path).unwrap().contains(&old));
+
+        // y removes it from config.toml and moves on to the next feed.
+        press(&mut app, KeyCode::Char('X'));
+        let Some(Confirmation { action: PendingAction::RemoveFeed(name, key), .. }) = app.confirmation.take() else {
+            panic!("no removal to confirm");
+        };
+        assert!(app.remove_feed(&config_path, &name, &key));
+        assert!(!std::fs::read_to_string(&config_path).unwrap().contains(&old));
+        assert!(!app.health.contains_key(&old));
+        assert!(app.gone_notice().unwrap().starts_with("Feed 'Dead'"));
+
+        // I ignores it, and the notice is gone.
+        app.input_mode = InputMode::Normal;
+        assert_eq!(app.keymap.route(app.contexts(), KeyCode::Char('I'), false), Some((Context::Notice, Action::IgnoreFeed)));
+        app.ignore_gone_feed(&health_path).await;
+        assert!(app.gone_notice().is_none());
+        assert_eq!(app.contexts(), [Context::Normal]);
+        let saved: health::Health = serde_json::from_str(&std::fs::read_to_string(&health_path).unwrap()).unwrap();
+        assert!(saved[&dead].ignored_until.is_some());
+    }
+
+    fn manual(url: String) -> Manual {
+        Manual {
+            name: "Site".to_string(),
+            url,
+            cooldown_minutes: Some(0),
+            method: None,
+            headers: None,
+            selector: None,
+            max_retries: None,
+            group: None,
+            disabled: false,
+        }
+    }
+
+    // Runs check_manual_site once, without retries or saving the cache.
+    async fn check(site: &Manual, cache: &Cache) -> Vec<Update> {
+        let (tx, mut rx) = mpsc::channel(100);
+        let storage = Storage::discarding();
+        check_manual_site(reqwest::Client::new(), site.clone(), tx, cache.clone(), String::new(), Arc::new(SystemClock), storage, 0).await;
+        let mut updates = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            updates.push(update);
+        }
+        updates
+    }
+
+    // The ticker stands in for the UI. On a single-threaded runtime it only
+    // runs while check_manual_site waits, so hashing the page on the runtime
+    // itself would stop it for as long as the hashing takes.
+    #[tokio::test(flavor = "current_thread")]
+    async fn hashing_a_large_page_does_not_starve_the_runtime() {
+        let page = "x".repeat(16 << 20); // about half a second to hash in a debug build
+        let server = MockServer::start(move |_| Response::new(200, page.clone()));
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        let ticker = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(5));
+            let (mut last, mut longest) = (Instant::now(), Duration::ZERO);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        longest = longest.max(last.elapsed());
+                        last = Instant::now();
+                    }
+                    _ = &mut stopped => return longest.max(last.elapsed()),
                 }
-            } else {
-                Style::default().fg(base_color)
-            };
+            }
+        });
 
-            ListItem::new(text.clone()).style(style)
-        })
-        .collect();
-        
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Blog Updates")
-                .border_style(Style::default().fg(Color::White)),
-        )
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ");
+        let updates = check(&manual(server.url("/big")), &Cache::default()).await;
+        stop.send(()).unwrap();
+        let longest = ticker.await.unwrap();
+        assert!(matches!(updates.as_slice(), [Update::ManualChange(..)]));
+        assert!(longest < Duration::from_millis(150), "the ticker stalled for {:?}", longest);
+    }
 
-    f.render_stateful_widget(list, chunks[0], &mut app.list_state);
-    
-    let search_bar = Paragraph::new(app.input.as_str())
-        .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
-            InputMode::Search => Style::default().fg(Color::Yellow),
+    #[tokio::test]
+    async fn a_head_check_compares_only_the_watched_headers() {
+        let version = Arc::new(std::sync::atomic::AtomicUsize::new(1));
+        let served = version.clone();
+        let server = MockServer::start(move |_| {
+            let etag = format!("\"v{}\"", served.load(std::sync::atomic::Ordering::Relaxed));
+            Response::new(200, "page").header("ETag", &etag).header("Date", &Utc::now().to_rfc2822())
+        });
+        let mut site = manual(server.url("/page"));
+        site.method = Some(Method::Head);
+        let cache = Cache::default();
+
+        assert!(matches!(check(&site, &cache).await.as_slice(), [Update::ManualChange(..)]));
+        // A Date that moves on is not a watched header.
+        assert!(matches!(check(&site, &cache).await.as_slice(), [Update::Info(message)] if message == "No changes for Site"));
+        version.store(2, std::sync::atomic::Ordering::Relaxed);
+        assert!(matches!(check(&site, &cache).await.as_slice(), [Update::ManualChange(..)]));
+
+        assert!(server.requests().iter().all(|request| request.method == "HEAD"));
+        let entry = &cache.lock().unwrap()[&site.url];
+        assert_eq!(entry.headers.get("ETag").map(String::as_str), Some("\"v2\""));
+    }
+
+    #[tokio::test]
+    async fn a_head_check_falls_back_to_the_page_on_405() {
+        let server = MockServer::start(|request| match request.method.as_str() {
+            "HEAD" => Response::new(405, ""),
+            _ => Response::new(200, "<p>the page</p>"),
+        });
+        let mut site = manual(server.url("/page"));
+        site.method = Some(Method::Head);
+
+        let updates = check(&site, &Cache::default()).await;
+        let [Update::HealthWarning(_, url, Some(warning)), Update::ManualChange(..)] = updates.as_slice() else {
+            panic!("unexpected updates: {:?}", updates.len());
+        };
+        assert_eq!(url, &site.url);
+        assert!(warning.starts_with("server does not allow HEAD (405)"));
+        let methods: Vec<String> = server.requests().into_iter().map(|request| request.method).collect();
+        assert_eq!(methods, ["HEAD", "GET"]);
+    }
+
+    #[tokio::test]
+    async fn clock_skew_from_date_headers_corrects_today() {
+        let (mut app, clock) = app_at(local(2024, 3, 11, 23, 40, 0));
+        let server_time = clock.now_utc() + chrono::Duration::minutes(37);
+        let server = MockServer::start(move |_| Response::new(200, testing::rss(&["one"])).header("Date", &server_time.to_rfc2822()));
+        let (tx, mut rx) = mpsc::channel(100);
+        let feed = Feed::new("Skewed".to_string(), server.url("/feed.xml"), None);
+        fetch_feed(&reqwest::Client::new(), &feed, &Cache::default(), tx, clock.clone(), None, 0, 0, 0).await;
+
+        // Sampled as run_app does, next to two servers that agree with us.
+        let mut refresh = Refresh::new(Instant::now(), false);
+        refresh.pending = 1;
+        refresh.skew_samples = vec![0, 37 * 60 + 1];
+        while let Ok(update) = rx.try_recv() {
+            if let Update::ServerDate(server, local) = update {
+                refresh.skew_samples.push((server - local).num_seconds());
+            }
+        }
+        assert_eq!(refresh.skew_samples.len(), 3);
+        app.refresh = Some(refresh);
+        let skew = app.finish_fetch(0).unwrap().clock_skew.unwrap();
+        assert_eq!(skew, 37 * 60);
+
+        let tomorrow = Some(NaiveDate::from_ymd_opt(2024, 3, 12).unwrap());
+        assert_eq!(app.day_label(tomorrow), "Tuesday 12 Mar");
+        app.set_clock_skew(skew);
+        assert_eq!(app.now_utc(), server_time);
+        assert_eq!(app.day_label(tomorrow), "Today");
+        assert_eq!(app.info_messages, ["[INFO] Warning: system clock appears 37 minutes behind server time"]);
+        app.set_clock_skew(skew);
+        assert_eq!(app.info_messages.len(), 1);
+    }
+
+    // Run with `cargo test -- --ignored`. A refresh of 2,000 feeds has to
+    // finish, and drawing the growing list has to fit in the default tick.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "stress test, takes a while"]
+    async fn thousands_of_feeds_refresh_within_the_frame_budget() {
+        const FEEDS: usize = 2000;
+        const FRAME_BUDGET: Duration = Duration::from_millis(250);
+        let server = MockServer::start(|request| {
+            let n = request.path.trim_start_matches("/feed");
+            Response::new(200, testing::rss(&[&format!("{}-a", n), &format!("{}-b", n), &format!("{}-c", n)]))
+        });
+        let feeds = (0..FEEDS).map(|i| Feed::new(format!("Feed {}", i), server.url(&format!("/feed{}", i)), None)).collect();
+        let config = Arc::new(Config { feeds: Some(feeds), ..Config::default() });
+        let mut app = app();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(160, 50)).unwrap();
+
+        let started = Instant::now();
+        start_refresh(&mut app, &config, &Cache::default(), "", false);
+        let (mut frames, mut slowest) = (0, Duration::ZERO);
+        let summary = loop {
+            assert!(started.elapsed() < Duration::from_secs(300), "the refresh did not finish");
+            let mut updates = app.reap_tasks().await;
+            while let Ok(update) = app.channel.rx.try_recv() {
+                updates.push(update);
+            }
+            // The parts of run_app's handling that grow the list.
+            let mut finished = None;
+            for update in updates {
+                match update {
+                    Update::NewFeedItem(source, entry) if app.listed_links.insert(entry.key().to_string()) => {
+                        let mut item = Item::new(format!("[FEED] {} | {}", source, entry.title), Some(entry.link), true);
+                        item.source = Some(source);
+                        item.published = entry.published;
+                        app.all_updates.push(item);
+                    }
+                    Update::Finished(bytes) => finished = finished.or(app.finish_fetch(bytes)),
+                    Update::SourceError(_, e) | Update::Error(e) => panic!("{}", e),
+                    _ => {}
+                }
+            }
+            let frame = Instant::now();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+            slowest = slowest.max(frame.elapsed());
+            frames += 1;
+            if let Some(summary) = finished {
+                break summary;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        assert_eq!(summary.errors, 0);
+        assert_eq!(app.all_updates.iter().filter(|item| item.is_article()).count(), FEEDS * 3);
+        assert!(slowest < FRAME_BUDGET, "the slowest of {} frames took {:?}", frames, slowest);
+    }
+
+    #[tokio::test]
+    async fn feeds_that_share_a_name_stay_apart() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/gone.xml" => Response::new(410, "gone"),
+            _ => Response::new(200, testing::rss(&["post"])),
+        });
+        let mut config: Config = toml::from_str(&format!(
+            "[[feeds]]\nname = \"Blog\"\nurl = \"{}\"\ncolor = \"red\"\n\n\
+             [[feeds]]\nname = \"Blog\"\nurl = \"{}\"\ncolor = \"blue\"\n\n\
+             [[feeds]]\nname = \"Blog\"\nurl = \"https://elsewhere.example/feed\"\n\n\
+             [[manual]]\nname = \"Other\"\nurl = \"https://other.example/\"\n",
+            server.url("/ok.xml"),
+            server.url("/gone.xml"),
+        ))
+        .unwrap();
+
+        // Two share a host too, so they get their URL.
+        assert_eq!(config.disambiguate_names(), ["3 feeds are named 'Blog'; listing each with its host or URL"]);
+        let names: Vec<&str> = config.feeds.iter().flatten().map(|feed| feed.name.as_str()).collect();
+        let (ok, gone) = (format!("Blog ({})", server.url("/ok.xml")), format!("Blog ({})", server.url("/gone.xml")));
+        assert_eq!(names, [ok.as_str(), gone.as_str(), "Blog (elsewhere.example)"]);
+        assert_eq!(config.manual.as_ref().unwrap()[0].name, "Other");
+
+        let mut app = app();
+        configure(&mut app, &config, &Settings::resolve(&toml::Table::new(), &|_| None));
+        assert_eq!(app.feed_colors.get(&ok), Some(&Color::Red));
+        assert_eq!(app.feed_colors.get(&gone), Some(&Color::Blue));
+
+        // Health is kept by URL, so only the gone feed counts failures.
+        let feeds = config.feeds.as_ref().unwrap();
+        let mut statuses = Vec::new();
+        for feed in &feeds[..2] {
+            for update in fetch(feed, &Cache::default()).await {
+                if let Update::FeedStatus(name, key, status) = update {
+                    statuses.push((name.clone(), key.clone(), status));
+                    health::record(&mut app.health, &key, status, 1, app.clock.now_utc());
+                }
+            }
+        }
+        assert_eq!(statuses, [(ok.clone(), server.url("/ok.xml"), None), (gone.clone(), server.url("/gone.xml"), Some(410))]);
+        assert_eq!(app.health.keys().collect::<Vec<_>>(), [&server.url("/gone.xml")]);
+
+        // A rule built from one feed's item leaves the other's alone.
+        let matcher = rules::Matcher::new(&RuleDraft::new(&ok, "post").rule()).unwrap();
+        assert!(matcher.matches(&ok, "post"));
+        assert!(!matcher.matches(&gone, "post"));
+        assert!(!matcher.matches("Blog", "post"));
+    }
+
+    #[tokio::test]
+    async fn a_refresh_shows_and_logs_each_message_once() {
+        let server = MockServer::start(|request| match (request.path.as_str(), request.headers.get("if-none-match")) {
+            ("/ok.xml", Some(_)) => Response::new(304, ""),
+            ("/ok.xml", None) => Response::new(200, testing::rss(&["post"])).header("ETag", "\"v1\""),
+            _ => Response::new(410, "gone"),
+        });
+        let config: Config = toml::from_str(&format!(
+            "[network]\nmax_retries = 0\n\n\
+             [[feeds]]\nname = \"Good\"\nurl = \"{}\"\n\n\
+             [[feeds]]\nname = \"Gone\"\nurl = \"{}\"\n",
+            server.url("/ok.xml"),
+            server.url("/gone.xml"),
+        ))
+        .unwrap();
+        let config = Arc::new(config);
+        let log_path = testing::temp_dir("event-log").join("br.log");
+        let mut app = app();
+        app.log.open(&log_path).unwrap();
+
+        // What run_app does with the messages of two refreshes, the second
+        // finding nothing new on Good.
+        let cache = Cache::default();
+        for _ in 0..2 {
+            start_refresh(&mut app, &config, &cache, "", false);
+            let mut finished = None;
+            while finished.is_none() {
+                let mut updates = reap_all(&mut app).await;
+                while let Ok(update) = app.channel.rx.try_recv() {
+                    updates.push(update);
+                }
+                for update in updates {
+                    match update {
+                        Update::Info(message) => app.info(message),
+                        Update::SourceError(_, e) | Update::Error(e) => app.error(e),
+                        Update::Finished(bytes) => finished = finished.or(app.finish_fetch(bytes)),
+                        _ => {}
+                    }
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+
+        let error = "[ERROR] fetching Gone: HTTP 410 Gone";
+        assert_eq!(app.info_messages, ["[INFO] No changes for Good"]);
+        let rows: Vec<&str> = app.all_updates.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(rows, [error, error]);
+        // The level, then the text as shown; the fetches end in any order.
+        let mut logged: Vec<String> = std::fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|line| line.split_once('\t').unwrap().1.to_string())
+            .collect();
+        logged.sort();
+        assert_eq!(logged, [format!("ERROR\t{}", error), format!("ERROR\t{}", error), "INFO\t[INFO] No changes for Good".to_string()]);
+    }
+
+    #[test]
+    fn a_bad_regex_is_drawn_in_the_theme_error_color() {
+        let mut app = app();
+        let error = Color::Rgb(200, 40, 90);
+        app.palette = Palette::new(ColorMode::TrueColor, Theme { error, ..Theme::DARK });
+        app.search_mode = SearchMode::Regex;
+        app.active_filter = "(unclosed".to_string();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let colors: HashSet<Color> = buffer.content.iter().map(|cell| cell.fg).collect();
+        assert!(colors.contains(&error));
+        assert!(!colors.contains(&Color::Red));
+    }
+
+    #[test]
+    fn the_detail_pane_takes_the_selected_feed_color() {
+        let mut app = app();
+        app.show_detail = true;
+        app.palette = Palette::new(ColorMode::TrueColor, Theme::DARK);
+        let orange = Color::Rgb(255, 102, 0);
+        app.feed_colors.insert("Colored".to_string(), orange);
+        for source in ["Colored", "Plain"] {
+            let mut item = article(source, None);
+            item.source = Some(source.to_string());
+            app.all_updates.push(item);
+        }
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 40)).unwrap();
+        // The colors of the pane's title and the border before it.
+        let mut title = |app: &mut App| {
+            terminal.draw(|f| ui(f, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            let (x, y) = (0..buffer.area.height)
+                .find_map(|y| {
+                    let line: String = (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect();
+                    line.find("Detail (p").map(|i| (line[..i].chars().count() as u16, y))
+                })
+                .unwrap();
+            (buffer.get(x, y).fg, buffer.get(x - 1, y).fg)
+        };
+        app.list_state.select(Some(0));
+        assert_eq!(title(&mut app), (orange, orange));
+        app.list_state.select(Some(1));
+        assert_eq!(title(&mut app), (Theme::DARK.feed, Theme::DARK.feed));
+    }
+
+    // The screen line the selected row is drawn on.
+    fn selected_line(terminal: &mut Terminal<ratatui::backend::TestBackend>, app: &mut App) -> Option<u16> {
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height).find(|&y| {
+            let line: String = (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect();
+            line.contains(HIGHLIGHT_SYMBOL)
         })
-        .block(Block::default().borders(Borders::ALL).title("Search"));
-    f.render_widget(search_bar, chunks[1]);
-    
-    if let InputMode::Search = app.input_mode {
-        f.set_cursor(
-            chunks[1].x + app.input.len() as u16 + 1,
-            chunks[1].y + 1,
-        )
     }
 
-    let info_items: Vec<ListItem> = app.info_messages
-        .iter()
-        .map(|msg| ListItem::new(msg.clone()).style(Style::default().fg(Color::Green)))
-        .collect();
+    #[test]
+    fn the_selected_row_stays_put_when_items_arrive_above() {
+        let (mut app, clock) = app_at(local(2024, 3, 11, 12, 0, 0));
+        let hour = |h: i64| Some(clock.now_utc() - chrono::Duration::hours(h));
+        app.all_updates = (0..30).map(|i| article(&format!("old {}", i), hour(100 + i))).collect();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 30)).unwrap();
+        let rows = app.rows();
+        app.first(&rows);
+        for _ in 0..12 {
+            app.next(&app.rows());
+        }
+        let line = selected_line(&mut terminal, &mut app);
+        let offset = app.list_state.offset();
+        assert!(line.is_some() && offset > 0);
 
-    let info_list = List::new(info_items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Info")
-            .border_style(Style::default().fg(Color::Green)),
-    );
+        // Three newer items sort in above, one older below.
+        let anchor = app.anchor();
+        let arrived = app.all_updates.len();
+        app.all_updates.extend([article("new 1", hour(1)), article("new 2", hour(2)), article("older", hour(500)), article("new 3", hour(3))]);
+        app.follow_anchor(anchor, arrived);
+        assert_eq!(app.selected_item(&app.rows()).unwrap().text, "old 12");
+        assert_eq!(app.list_state.offset(), offset + 3);
+        assert_eq!(app.new_above.len(), 3);
+        assert_eq!(selected_line(&mut terminal, &mut app), line);
 
-    f.render_widget(info_list, chunks[2]);
+        // n jumps to the topmost of them.
+        app.jump_new_above(&app.rows());
+        assert_eq!(app.selected_item(&app.rows()).unwrap().text, "new 1");
+        assert!(app.new_above.is_empty());
+    }
+
+    #[test]
+    fn a_selection_moved_meanwhile_is_left_alone() {
+        let (mut app, clock) = app_at(local(2024, 3, 11, 12, 0, 0));
+        app.all_updates = (0..5).map(|i| article(&format!("old {}", i), Some(clock.now_utc() - chrono::Duration::days(i)))).collect();
+        let rows = app.rows();
+        app.first(&rows);
+        let anchor = app.anchor();
+        app.next(&app.rows());
+        let arrived = app.all_updates.len();
+        app.all_updates.push(article("new", Some(clock.now_utc())));
+        app.follow_anchor(anchor, arrived);
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert!(app.new_above.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_permalink_guid_keeps_an_entry_apart_when_its_link_changes() {
+        let fetches = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let server = MockServer::start(move |_| {
+            let n = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Response::new(200, format!(
+                "<?xml version=\"1.0\"?><rss version=\"2.0\"><channel><title>Blog</title>\
+                 <item><title>Post</title><link>https://feedproxy.example/~r/blog/{0}/post</link>\
+                 <guid>https://blog.example/post</guid></item>\
+                 <item><title>Other</title><link>https://blog.example/other?utm_source={0}</link>\
+                 <guid isPermaLink=\"false\">other-1</guid></item></channel></rss>",
+                n
+            ))
+        });
+        let feed = Feed::new("Blog".to_string(), server.url("/feed.xml"), None);
+        let mut keys = Vec::new();
+        for _ in 0..2 {
+            let updates = fetch(&feed, &Cache::default()).await;
+            let entries: Vec<&Entry> = updates.iter().filter_map(|update| match update {
+                Update::NewFeedItem(_, entry) => Some(entry),
+                _ => None,
+            }).collect();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].permalink.as_deref(), Some("https://blog.example/post"));
+            assert_eq!(entries[1].permalink, None);
+            keys.push(entries.iter().map(|entry| entry.key().to_string()).collect::<Vec<_>>());
+        }
+        // The permalink is stable; a marked guid leaves only the link.
+        assert_eq!(keys[0][0], keys[1][0]);
+        assert_ne!(keys[0][1], keys[1][1]);
+    }
+
+    #[test]
+    fn no_mode_hides_a_key_by_accident() {
+        assert_eq!(app().keymap.conflicts(&InputMode::stacks()), Vec::<String>::new());
+        // A key remapped onto one the notice binds is caught.
+        let mut keymap = Keymap::new();
+        keymap.remap(&BTreeMap::from([("search".to_string(), "I".to_string())]));
+        assert_eq!(keymap.conflicts(&InputMode::stacks()), ["'I' for 'ignore for 30 days' in notice mode hides 'search/filter' of normal mode"]);
+    }
 }
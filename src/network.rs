@@ -0,0 +1,117 @@
+use serde::Deserialize;
+use std::{
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// What br sends as its User-Agent unless `user_agent` is set.
+pub const USER_AGENT: &str = concat!("blogreader/", env!("CARGO_PKG_VERSION"), " (+https://github.com/weirdsmiley/blogreader)");
+
+/// How long the first retry of a failed fetch waits; each later one waits
+/// twice as long as the one before.
+pub const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The `[network]` section of config.toml.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Network {
+    pub proxy: Option<String>, // e.g. "http://proxy.corp:3128" or "socks5://localhost:1080", for every request
+    pub user_agent: Option<String>, // for hosts that turn away the default one
+    pub max_retries: Option<u32>, // default 3, unless a feed or site sets its own
+}
+
+impl Network {
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(USER_AGENT)
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(3)
+    }
+}
+
+/// The client every request goes through, so the proxy and the timeout
+/// apply to all of them alike. A `timeout_secs` of 0 waits as long as the
+/// server takes. Without a `proxy`, reqwest follows HTTP_PROXY, HTTPS_PROXY
+/// and NO_PROXY; with one, NO_PROXY still names the hosts to reach directly.
+pub fn build_client(network: &Network, timeout_secs: u64) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder().user_agent(network.user_agent());
+    if timeout_secs > 0 {
+        builder = builder.timeout(Duration::from_secs(timeout_secs));
+    }
+    if let Some(url) = &network.proxy {
+        let proxy = reqwest::Proxy::all(url).map_err(|e| format!("[network] proxy = \"{}\": {}", url, e))?;
+        builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_env()));
+    }
+    builder.build().map_err(|e| format!("setting up HTTP: {}", e))
+}
+
+/// Runs `attempt` until it succeeds, fails with an error `transient` does
+/// not accept, or has been retried `retries` times. The waits start at
+/// `initial` and double each time, with up to half as much again at random
+/// so the feeds of one host that failed together do not retry together.
+pub async fn retry_with_backoff<T, E, F, Fut>(mut attempt: F, retries: u32, initial: Duration, transient: impl Fn(&E) -> bool) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut delay = initial;
+    for _ in 0..retries {
+        match attempt().await {
+            Err(e) if transient(&e) => {
+                tokio::time::sleep(delay + jitter(delay / 2)).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    attempt().await
+}
+
+// Anywhere from nothing to `max`, seeded afresh by the standard library's
+// randomly keyed hasher.
+fn jitter(max: Duration) -> Duration {
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    max.mul_f64(random as f64 / u64::MAX as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockServer, Response};
+    use std::cell::Cell;
+
+    async fn user_agent_sent(network: &Network) -> Option<String> {
+        let server = MockServer::start(|_| Response::new(200, "ok"));
+        let client = build_client(network, 5).unwrap();
+        client.get(server.url("/")).send().await.unwrap();
+        server.requests()[0].headers.get("user-agent").cloned()
+    }
+
+    #[tokio::test]
+    async fn requests_send_the_configured_user_agent() {
+        assert_eq!(user_agent_sent(&Network::default()).await.as_deref(), Some(USER_AGENT));
+        let custom = Network { user_agent: Some("Mozilla/5.0 (compatible; br)".to_string()), ..Network::default() };
+        assert_eq!(user_agent_sent(&custom).await.as_deref(), Some("Mozilla/5.0 (compatible; br)"));
+    }
+
+    #[test]
+    fn a_bad_proxy_is_named_in_the_error() {
+        let network = Network { proxy: Some("not a url".to_string()), ..Network::default() };
+        assert!(build_client(&network, 0).unwrap_err().starts_with("[network] proxy = \"not a url\": "));
+    }
+
+    #[tokio::test]
+    async fn only_transient_errors_are_retried() {
+        let attempts = Cell::new(0);
+        let attempt = || {
+            attempts.set(attempts.get() + 1);
+            async { Err::<(), _>(attempts.get()) }
+        };
+        assert_eq!(retry_with_backoff(attempt, 3, Duration::ZERO, |_| true).await, Err(4));
+        attempts.set(0);
+        assert_eq!(retry_with_backoff(attempt, 3, Duration::ZERO, |&n| n < 2).await, Err(2));
+        let succeed = || async { Ok::<_, ()>("fetched") };
+        assert_eq!(retry_with_backoff(succeed, 3, Duration::ZERO, |_| true).await, Ok("fetched"));
+    }
+}
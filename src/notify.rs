@@ -0,0 +1,30 @@
+use notify_rust::Notification;
+use std::collections::BTreeSet;
+
+/// A refresh with more new items than this sends one summary notification
+/// instead of one per item.
+pub const MAX_SINGLE: usize = 3;
+
+/// A new item to tell the desktop about.
+pub struct Post {
+    pub source: String,
+    pub title: String,
+}
+
+/// Shows the new items of a refresh as desktop notifications. Talking to the
+/// notification daemon blocks, so this is called off the UI thread.
+pub fn send(posts: &[Post]) -> Result<(), notify_rust::error::Error> {
+    if posts.len() > MAX_SINGLE {
+        let sources: BTreeSet<&str> = posts.iter().map(|post| post.source.as_str()).collect();
+        let summary = format!("{} new items from {} feeds", posts.len(), sources.len());
+        return Notification::new().appname("br").summary(&summary).show().map(|_| ());
+    }
+    for post in posts {
+        Notification::new()
+            .appname("br")
+            .summary(&format!("New post from {}", post.source))
+            .body(&post.title)
+            .show()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// One way of opening a URL in a browser.
+pub trait Opener: Send + Sync {
+    /// How it is named when logging which opener worked.
+    fn name(&self) -> String;
+    fn open(&self, url: &str) -> io::Result<()>;
+}
+
+/// A command line from config.toml or $BROWSER. "%s" is replaced by the
+/// URL; without one the URL is appended.
+pub struct CommandOpener {
+    command: String,
+}
+
+impl Opener for CommandOpener {
+    fn name(&self) -> String {
+        format!("`{}`", self.command)
+    }
+
+    fn open(&self, url: &str) -> io::Result<()> {
+        let mut words: Vec<String> = self.command.split_whitespace().map(String::from).collect();
+        if words.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty command"));
+        }
+        if words.iter().any(|word| word.contains("%s")) {
+            words = words.into_iter().map(|word| word.replace("%s", url)).collect();
+        } else {
+            words.push(url.to_string());
+        }
+        Command::new(&words[0])
+            .args(&words[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(|_| ())
+    }
+}
+
+/// The desktop's default handler (xdg-open, open, start) via the open crate.
+pub struct SystemOpener;
+
+impl Opener for SystemOpener {
+    fn name(&self) -> String {
+        "the system opener".to_string()
+    }
+
+    fn open(&self, url: &str) -> io::Result<()> {
+        open::that(url)
+    }
+}
+
+/// The openers to try, in order: `browser_command` from config.toml, the
+/// system opener, then each command in $BROWSER (colon-separated).
+pub fn chain(browser_command: Option<&str>) -> Vec<Box<dyn Opener>> {
+    let mut openers: Vec<Box<dyn Opener>> = Vec::new();
+    if let Some(command) = browser_command {
+        openers.push(Box::new(CommandOpener { command: command.to_string() }));
+    }
+    openers.push(Box::new(SystemOpener));
+    if let Ok(browser) = std::env::var("BROWSER") {
+        for command in browser.split(':').filter(|c| !c.trim().is_empty()) {
+            openers.push(Box::new(CommandOpener { command: command.to_string() }));
+        }
+    }
+    openers
+}
+
+/// Tries each opener until one works. Returns the name of the one that did,
+/// or why each failed.
+pub fn open(openers: &[Box<dyn Opener>], url: &str) -> Result<String, Vec<String>> {
+    let mut failures = Vec::new();
+    for opener in openers {
+        match opener.open(url) {
+            Ok(()) => return Ok(opener.name()),
+            Err(e) => failures.push(format!("{}: {}", opener.name(), e)),
+        }
+    }
+    Err(failures)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Puts `text` on the clipboard with the OSC 52 escape sequence, which most
+/// terminal emulators (and tmux with set-clipboard on) pass to the system
+/// clipboard, including over SSH.
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // Each opener tried and the URL it got, in order.
+    type Opened = Arc<Mutex<Vec<String>>>;
+
+    // Records the URLs it is asked to open, and fails if told to.
+    struct Fake {
+        name: &'static str,
+        works: bool,
+        opened: Opened,
+    }
+
+    impl Opener for Fake {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn open(&self, url: &str) -> io::Result<()> {
+            self.opened.lock().unwrap().push(format!("{} {}", self.name, url));
+            if self.works { Ok(()) } else { Err(io::Error::new(io::ErrorKind::NotFound, "not installed")) }
+        }
+    }
+
+    fn fakes(openers: &[(&'static str, bool)]) -> (Vec<Box<dyn Opener>>, Opened) {
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let openers = openers
+            .iter()
+            .map(|&(name, works)| Box::new(Fake { name, works, opened: opened.clone() }) as Box<dyn Opener>)
+            .collect();
+        (openers, opened)
+    }
+
+    #[test]
+    fn the_first_opener_that_works_is_the_last_tried() {
+        let (openers, opened) = fakes(&[("firefox", false), ("xdg-open", true), ("lynx", true)]);
+        assert_eq!(open(&openers, "https://example.com/").unwrap(), "xdg-open");
+        assert_eq!(*opened.lock().unwrap(), ["firefox https://example.com/", "xdg-open https://example.com/"]);
+    }
+
+    #[test]
+    fn every_failure_is_listed_when_none_works() {
+        let (mut openers, opened) = fakes(&[("firefox", false), ("xdg-open", false)]);
+        openers.push(Box::new(CommandOpener { command: "  ".to_string() }));
+        assert_eq!(
+            open(&openers, "https://example.com/").unwrap_err(),
+            ["firefox: not installed", "xdg-open: not installed", "`  `: empty command"]
+        );
+        assert_eq!(opened.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn the_configured_command_comes_before_the_system_opener() {
+        let openers = chain(Some("firefox --new-tab %s"));
+        let names: Vec<String> = openers.iter().take(2).map(|opener| opener.name()).collect();
+        assert_eq!(names, ["`firefox --new-tab %s`", "the system opener"]);
+        assert_eq!(chain(None)[0].name(), "the system opener");
+    }
+
+    #[test]
+    fn base64_pads_to_whole_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("é".as_bytes()), "w6k=");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0x00]), "//79AA==");
+    }
+}
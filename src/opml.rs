@@ -0,0 +1,38 @@
+/// Escapes text for an XML attribute value, or HTML text.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An OPML 2.0 document listing `feeds` as rss outlines and `manual` sites,
+/// which have no feed, as plain links in a folder of their own. Both are
+/// (name, url) pairs.
+pub fn export(feeds: &[(&str, &str)], manual: &[(&str, &str)]) -> String {
+    let mut opml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    opml.push_str("  <head>\n    <title>br subscriptions</title>\n  </head>\n  <body>\n");
+    for (name, url) in feeds {
+        let name = escape(name);
+        opml.push_str(&format!("    <outline text=\"{}\" title=\"{}\" type=\"rss\" xmlUrl=\"{}\"/>\n", name, name, escape(url)));
+    }
+    if !manual.is_empty() {
+        opml.push_str("    <outline text=\"Manual\" title=\"Manual\">\n");
+        for (name, url) in manual {
+            let name = escape(name);
+            opml.push_str(&format!("      <outline text=\"{}\" title=\"{}\" type=\"link\" htmlUrl=\"{}\"/>\n", name, name, escape(url)));
+        }
+        opml.push_str("    </outline>\n");
+    }
+    opml.push_str("  </body>\n</opml>\n");
+    opml
+}
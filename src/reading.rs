@@ -0,0 +1,124 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// What a reading session counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Read,
+    Opened,
+    Starred,
+}
+
+/// A reading session started with Ctrl-t, time-boxed or not, and what was
+/// done during it.
+pub struct ReadingSession {
+    pub started: Instant,
+    pub limit: Option<Duration>,
+    pub summary: SessionSummary,
+}
+
+impl ReadingSession {
+    pub fn new(started_at: DateTime<Utc>, started: Instant, limit: Option<Duration>) -> ReadingSession {
+        let summary = SessionSummary {
+            started_at,
+            duration_secs: 0,
+            limit_mins: limit.map(|limit| limit.as_secs() / 60),
+            read: 0,
+            opened: 0,
+            starred: 0,
+            sources: BTreeMap::new(),
+        };
+        ReadingSession { started, limit, summary }
+    }
+
+    /// Counts `activity` on an item from `source`.
+    pub fn record(&mut self, activity: Activity, source: Option<&str>) {
+        let summary = &mut self.summary;
+        match activity {
+            Activity::Read => summary.read += 1,
+            Activity::Opened => summary.opened += 1,
+            Activity::Starred => summary.starred += 1,
+        }
+        if let Some(source) = source {
+            *summary.sources.entry(source.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Whether the time box is used up at `now`.
+    pub fn is_over(&self, now: Instant) -> bool {
+        self.limit.is_some_and(|limit| now - self.started >= limit)
+    }
+
+    pub fn finish(mut self, now: Instant) -> SessionSummary {
+        self.summary.duration_secs = (now - self.started).as_secs();
+        self.summary
+    }
+}
+
+/// A finished reading session, as kept in stats.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_mins: Option<u64>,
+    pub read: usize,
+    pub opened: usize,
+    pub starred: usize,
+    pub sources: BTreeMap<String, usize>, // source name -> items read, opened or starred
+}
+
+impl SessionSummary {
+    pub fn headline(&self) -> String {
+        format!(
+            "{} read, {} opened, {} starred in {}m {:02}s",
+            self.read,
+            self.opened,
+            self.starred,
+            self.duration_secs / 60,
+            self.duration_secs % 60
+        )
+    }
+
+    /// The summary popup: the counts, then the sources most acted on first.
+    pub fn lines(&self) -> Vec<String> {
+        let started = self.started_at.with_timezone(&Local).format("%a %e %b %H:%M");
+        let mut lines = vec![
+            format!("Started:    {}", started),
+            format!("Time spent: {}m {:02}s", self.duration_secs / 60, self.duration_secs % 60),
+            format!("Read:       {}", self.read),
+            format!("Opened:     {}", self.opened),
+            format!("Starred:    {}", self.starred),
+            String::new(),
+        ];
+        if self.sources.is_empty() {
+            lines.push("Nothing was read, opened or starred.".to_string());
+            return lines;
+        }
+        lines.push("Spent on:".to_string());
+        let mut sources: Vec<(&String, &usize)> = self.sources.iter().collect();
+        sources.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        lines.extend(sources.into_iter().map(|(source, count)| format!("  {:>3}  {}", count, source)));
+        lines
+    }
+}
+
+/// Reading statistics kept across runs, persisted as stats.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    #[serde(default)]
+    pub sessions: Vec<SessionSummary>, // oldest first
+}
+
+/// A missing or unreadable file is empty stats.
+pub fn load(path: &Path) -> Stats {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
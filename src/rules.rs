@@ -0,0 +1,171 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Patterns longer than this are refused rather than compiled.
+pub const MAX_PATTERN_LEN: usize = 1000;
+
+/// Bounds the memory a compiled pattern may take, so a pathological one fails
+/// to compile instead of slowing every refresh, or every keystroke of a
+/// regex search, down.
+pub const SIZE_LIMIT: usize = 1 << 20;
+
+/// What a rule does to the feed items it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    #[default]
+    Hide,
+    MarkRead,
+    Highlight,
+}
+
+impl RuleAction {
+    pub fn next(self) -> RuleAction {
+        match self {
+            RuleAction::Hide => RuleAction::MarkRead,
+            RuleAction::MarkRead => RuleAction::Highlight,
+            RuleAction::Highlight => RuleAction::Hide,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleAction::Hide => "hide",
+            RuleAction::MarkRead => "mark read",
+            RuleAction::Highlight => "highlight",
+        }
+    }
+}
+
+/// What happens when a refresh highlights an item, from `alert` in
+/// config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alert {
+    #[default]
+    None,
+    Bell,
+    Command,
+}
+
+/// Rings the terminal bell, or starts `command` (`alert_command`) without
+/// waiting for it. The bell is written between frames, like the clipboard
+/// escape, and moves no cursor, so the screen is left as drawn.
+pub fn ring(alert: Alert, command: Option<&str>) -> io::Result<()> {
+    match alert {
+        Alert::None => Ok(()),
+        Alert::Bell => {
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()
+        }
+        Alert::Command => {
+            let words: Vec<&str> = command.unwrap_or_default().split_whitespace().collect();
+            let Some((program, args)) = words.split_first() else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "alert = \"command\" needs alert_command"));
+            };
+            Command::new(program)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map(|_| ())
+        }
+    }
+}
+
+/// A `[[rules]]` entry of config.toml. Each given field is a regex that has
+/// to match somewhere in the item's source name or original title.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub action: RuleAction,
+}
+
+/// A rule with its patterns compiled. The rule builder's preview counts
+/// matches with this too, so it shows exactly what the rule will do.
+pub struct Matcher {
+    source: Option<Regex>,
+    title: Option<Regex>,
+    pub action: RuleAction,
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    if pattern.len() > MAX_PATTERN_LEN {
+        return Err(format!("pattern longer than {} bytes", MAX_PATTERN_LEN));
+    }
+    RegexBuilder::new(pattern).size_limit(SIZE_LIMIT).build().map_err(|e| e.to_string())
+}
+
+impl Matcher {
+    pub fn new(rule: &Rule) -> Result<Matcher, String> {
+        if rule.source.is_none() && rule.title.is_none() {
+            return Err("a rule needs a source or a title pattern".to_string());
+        }
+        Ok(Matcher {
+            source: rule.source.as_deref().map(compile).transpose()?,
+            title: rule.title.as_deref().map(compile).transpose()?,
+            action: rule.action,
+        })
+    }
+
+    pub fn matches(&self, source: &str, title: &str) -> bool {
+        self.source.as_ref().is_none_or(|re| re.is_match(source)) && self.title.as_ref().is_none_or(|re| re.is_match(title))
+    }
+}
+
+/// Compiles the configured rules, describing the ones that fail.
+pub fn matchers(rules: &[Rule]) -> (Vec<Matcher>, Vec<String>) {
+    let mut matchers = Vec::new();
+    let mut errors = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        match Matcher::new(rule) {
+            Ok(matcher) => matchers.push(matcher),
+            Err(e) => errors.push(format!("rule {}: {}", i + 1, e)),
+        }
+    }
+    (matchers, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title(pattern: &str) -> Rule {
+        Rule { title: Some(pattern.to_string()), ..Rule::default() }
+    }
+
+    #[test]
+    fn an_overlong_pattern_is_rejected_at_load() {
+        let (matchers, errors) = matchers(&[title("rust"), title(&"a".repeat(MAX_PATTERN_LEN + 1))]);
+        assert_eq!(matchers.len(), 1);
+        assert_eq!(errors, [format!("rule 2: pattern longer than {} bytes", MAX_PATTERN_LEN)]);
+    }
+
+    #[test]
+    fn a_pattern_that_compiles_too_big_is_rejected_at_load() {
+        // Short, but the repetition blows up into far more than SIZE_LIMIT.
+        let (matchers, errors) = matchers(&[title(r"\w{50}{50}{50}")]);
+        assert!(matchers.is_empty());
+        assert!(errors[0].starts_with("rule 1: "), "{:?}", errors);
+    }
+
+    #[test]
+    fn a_rule_needs_a_pattern_and_every_given_one_must_match() {
+        assert_eq!(matchers(&[Rule::default()]).1, ["rule 1: a rule needs a source or a title pattern"]);
+        let rule = Rule { source: Some("^Blog$".to_string()), ..title("(?i)release") };
+        let matcher = Matcher::new(&rule).unwrap();
+        assert!(matcher.matches("Blog", "New Release out"));
+        assert!(!matcher.matches("Blog 2", "New Release out"));
+        assert!(!matcher.matches("Blog", "Nothing new"));
+    }
+}
@@ -0,0 +1,238 @@
+use regex::{Regex, RegexBuilder};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+// Decompose (NFKD) and drop combining marks, so "Muñoz" becomes "Munoz".
+fn strip_marks(text: &str) -> String {
+    text.nfkd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+// Lowercase before decomposing: "İ".to_lowercase() is "i" plus a combining
+// dot, which strip_marks then removes. Lowercasing is not full case
+// folding, so the letters that fold differently in everyday text are
+// mapped as well: "ß" (and "ẞ") to "ss", and a final "ς" to "σ". Regexes
+// only fold case letter by letter, so there "ß" still does not match "ss".
+fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in strip_marks(&text.to_lowercase()).chars() {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Normalized forms of an item's text, computed once when the item is added.
+#[derive(Debug, Clone)]
+pub struct SearchKey {
+    exact: String,
+    folded: String,
+}
+
+impl SearchKey {
+    pub fn new(text: &str) -> SearchKey {
+        SearchKey {
+            exact: strip_marks(text),
+            folded: fold(text),
+        }
+    }
+}
+
+/// How a query treats case: `Ignore` always ignores it, `Smart` matches it
+/// only when the query has an uppercase letter, and `Match` always does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseMode {
+    Ignore,
+    Smart,
+    Match,
+}
+
+impl CaseMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CaseMode::Ignore => "ignore case",
+            CaseMode::Smart => "smart case",
+            CaseMode::Match => "match case",
+        }
+    }
+}
+
+/// Whether a query's text is looked for as it is or read as a regex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Substring,
+    Regex,
+}
+
+/// A search query normalized the same way as the keys it is matched against.
+///
+/// The `CaseMode` decides whether the text matches case sensitively. In
+/// `SearchMode::Regex` the text is a regular expression, and one that does
+/// not compile is searched for as plain text instead. `category:name` words
+/// restrict the query to items the feed filed under all of those
+/// categories, an `@group` word to items from feeds in that group (or
+/// without one, for `@uncategorized`), and `note:text` words to items whose
+/// note contains each text (any note, for a bare `note:`). None of them is
+/// searched for in the text.
+pub struct Query {
+    needle: String,
+    pattern: Option<Regex>,
+    error: Option<String>, // why the regex did not compile
+    case_sensitive: bool,
+    categories: Vec<String>,
+    group: Option<String>,
+    notes: Vec<String>,
+}
+
+/// The group of items from feeds and sites that are not in one.
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+impl Query {
+    pub fn new(input: &str, case: CaseMode, mode: SearchMode) -> Query {
+        let mut categories = Vec::new();
+        let mut group = None;
+        let mut notes = Vec::new();
+        let mut words = Vec::new();
+        for word in input.split(' ') {
+            if let Some(category) = word.strip_prefix("category:").filter(|c| !c.is_empty()) {
+                categories.push(category.to_lowercase());
+            } else if let Some(note) = word.strip_prefix("note:") {
+                notes.push(fold(note));
+            } else if let Some(name) = word.strip_prefix('@').filter(|g| !g.is_empty()) {
+                group = Some(name.to_lowercase());
+            } else {
+                words.push(word);
+            }
+        }
+        let input = words.join(" ");
+        let input = if categories.is_empty() && group.is_none() && notes.is_empty() { input.as_str() } else { input.trim() };
+        let case_sensitive = match case {
+            CaseMode::Ignore => false,
+            CaseMode::Smart => input.chars().any(char::is_uppercase),
+            CaseMode::Match => true,
+        };
+        let needle = if case_sensitive { strip_marks(input) } else { fold(input) };
+        let (pattern, error) = if mode == SearchMode::Substring || input.is_empty() {
+            (None, None)
+        } else {
+            match RegexBuilder::new(&strip_marks(input)).case_insensitive(!case_sensitive).size_limit(crate::rules::SIZE_LIMIT).build() {
+                Ok(pattern) => (Some(pattern), None),
+                // The last line of a syntax error says what is wrong, the
+                // ones above draw where.
+                Err(e) => (None, e.to_string().lines().last().map(|line| line.trim_start_matches("error: ").to_string())),
+            }
+        };
+        Query { needle, pattern, error, case_sensitive, categories, group, notes }
+    }
+
+    /// Why the text, meant as a regex, does not compile.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Whether an item from `group` passes the `@group` part of the query.
+    /// Groups compare without regard to case.
+    pub fn matches_group(&self, group: Option<&str>) -> bool {
+        match &self.group {
+            None => true,
+            Some(wanted) => group.map_or(wanted == UNCATEGORIZED, |group| group.to_lowercase() == *wanted),
+        }
+    }
+
+    /// Whether an item filed under `categories` (already lowercased) passes
+    /// the `category:` part of the query.
+    pub fn matches_categories(&self, categories: &[String]) -> bool {
+        self.categories.iter().all(|wanted| categories.contains(wanted))
+    }
+
+    /// Whether an item with `note` passes the `note:` part of the query.
+    /// Notes always match without regard to case.
+    pub fn matches_note(&self, note: Option<&str>) -> bool {
+        if self.notes.is_empty() {
+            return true;
+        }
+        note.map(fold).is_some_and(|note| self.notes.iter().all(|wanted| note.contains(wanted)))
+    }
+
+    pub fn matches(&self, key: &SearchKey) -> bool {
+        if let Some(pattern) = &self.pattern {
+            pattern.is_match(&key.exact)
+        } else if self.case_sensitive {
+            key.exact.contains(&self.needle)
+        } else {
+            key.folded.contains(&self.needle)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(query: &str, case: CaseMode, text: &str) -> bool {
+        Query::new(query, case, SearchMode::Substring).matches(&SearchKey::new(text))
+    }
+
+    #[test]
+    fn accents_are_ignored_both_ways() {
+        assert!(matches("munoz", CaseMode::Ignore, "Interview with Muñoz"));
+        assert!(matches("muñoz", CaseMode::Ignore, "MUNOZ on compilers"));
+        assert!(matches("cafe", CaseMode::Ignore, "Café culture"));
+        // NFKD also splits compatibility forms such as ligatures.
+        assert!(matches("office", CaseMode::Ignore, "An o\u{FB03}ce tour"));
+    }
+
+    #[test]
+    fn turkish_dotted_i_folds_to_plain_i() {
+        assert!(matches("istanbul", CaseMode::Ignore, "İstanbul meetup"));
+        assert!(matches("İstanbul", CaseMode::Ignore, "istanbul meetup"));
+        assert!(matches("DIŞ", CaseMode::Ignore, "dis"));
+    }
+
+    #[test]
+    fn sharp_s_and_final_sigma_fold_like_their_other_forms() {
+        assert!(matches("strasse", CaseMode::Ignore, "Große Straße"));
+        assert!(matches("STRASSE", CaseMode::Ignore, "Straße"));
+        assert!(matches("straße", CaseMode::Ignore, "STRASSE"));
+        assert!(matches("ΟΔΟΣ", CaseMode::Ignore, "μια οδος"));
+        assert!(matches("οδοσ", CaseMode::Ignore, "ΟΔΟΣ"));
+        assert!(!matches("strasse", CaseMode::Match, "Straße"));
+    }
+
+    #[test]
+    fn ignore_case_matches_any_case() {
+        assert!(matches("suse", CaseMode::Ignore, "SUSE releases"));
+        assert!(matches("SUSE", CaseMode::Ignore, "opensuse news"));
+    }
+
+    #[test]
+    fn smart_case_matches_case_only_with_an_uppercase_letter() {
+        assert!(matches("suse", CaseMode::Smart, "SUSE releases"));
+        assert!(matches("SUSE", CaseMode::Smart, "SUSE releases"));
+        assert!(!matches("SUSE", CaseMode::Smart, "opensuse news"));
+        assert!(matches("Muñoz", CaseMode::Smart, "Munoz"));
+        assert!(!matches("Muñoz", CaseMode::Smart, "munoz"));
+    }
+
+    #[test]
+    fn match_case_always_matches_case() {
+        assert!(!matches("suse", CaseMode::Match, "SUSE releases"));
+        assert!(matches("suse", CaseMode::Match, "opensuse news"));
+    }
+
+    #[test]
+    fn regex_follows_the_case_mode() {
+        let key = SearchKey::new("Rust 1.80 released");
+        assert!(Query::new("rust \\d+", CaseMode::Ignore, SearchMode::Regex).matches(&key));
+        assert!(!Query::new("rust \\d+", CaseMode::Match, SearchMode::Regex).matches(&key));
+        let bad = Query::new("rust (", CaseMode::Ignore, SearchMode::Regex);
+        assert!(bad.error().is_some());
+    }
+
+    #[test]
+    fn a_regex_that_compiles_too_big_is_an_error() {
+        let huge = Query::new(r"\w{50}{50}{50}", CaseMode::Ignore, SearchMode::Regex);
+        assert!(huge.error().is_some_and(|error| error.contains("size limit")), "{:?}", huge.error());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::sort::SortOrder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// View choices that carry over to the next run, persisted as session.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(default)]
+    pub sort: SortOrder,
+}
+
+/// A missing or unreadable file is the default view.
+pub fn load(path: &Path) -> Session {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,235 @@
+use crate::{
+    launcher::BulkOpen,
+    rules::Alert,
+    summary::ShowSnippets,
+    theme::{ColorMode, ThemeConfig},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Where an effective setting came from. Later layers win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    Default,
+    File,
+    Env,
+}
+
+impl Layer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Default => "default",
+            Layer::File => "file",
+            Layer::Env => "env",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub value: toml::Value,
+    pub source: Layer,
+}
+
+/// The top-level options of config.toml after applying, in order, the
+/// built-in defaults, the file, and `BR_<KEY>` environment variables.
+///
+/// Everything reads these instead of the raw file, so the precedence is
+/// decided here and nowhere else.
+pub struct Settings {
+    pub smart_case: bool,
+    pub color_mode: ColorMode,
+    pub theme: ThemeConfig,
+    pub gone_after: u32, // refreshes answering 404/410 before offering to unsubscribe
+    pub show_snippets: ShowSnippets,
+    pub search_snippets: bool,
+    pub show_categories: bool,
+    pub show_detail: bool, // the pane under the list, until 'p'
+    pub max_source_width: usize,
+    pub max_entries: usize, // entries taken from the top of each feed, 0 for all
+    pub max_content_bytes: usize, // of each entry's stored summary, 0 for no limit
+    pub control_socket: bool,
+    pub set_title: bool, // unread count and refresh state in the terminal title
+    pub browser_command: Option<String>,
+    pub bulk_open: BulkOpen, // what O does; Ctrl-o does the other
+    pub refresh_interval_secs: Option<u64>, // None or 0 refreshes only on 'u'
+    pub tick_rate_ms: u64, // how often the screen is redrawn while idle
+    pub timeout_secs: u64, // per request, 0 for none
+    pub max_concurrent_fetches: usize, // feeds and sites fetched at the same time
+    pub skip_duplicate_manual: bool, // manual sites at a feed's URL
+    pub newsboat_urls: Option<String>, // newsboat's urls file, read for more feeds
+    pub alert: Alert, // once per refresh that highlights something
+    pub alert_command: Option<String>,
+    pub notifications: bool, // on the desktop, when a refresh brings new items
+    /// What each option resolved to and which layer set it, for
+    /// `br config show --effective`. Unset optional values are left out.
+    pub sources: BTreeMap<&'static str, Provenance>,
+    /// Values that were ignored because they did not parse.
+    pub warnings: Vec<String>,
+    known: BTreeSet<&'static str>, // every key read, set or not
+}
+
+// Reads each key from the layers, recording where the value came from.
+struct Resolver<'a> {
+    file: &'a toml::Table,
+    env: &'a dyn Fn(&str) -> Option<String>,
+    sources: BTreeMap<&'static str, Provenance>,
+    warnings: Vec<String>,
+    known: BTreeSet<&'static str>,
+}
+
+impl Resolver<'_> {
+    // An environment value is read as a TOML value, and as a plain string if
+    // it is not one, so BR_BROWSER_COMMAND=firefox needs no quotes.
+    fn env_value(&self, key: &str) -> Option<(String, toml::Value)> {
+        let name = format!("BR_{}", key.to_uppercase());
+        let raw = (self.env)(&name)?;
+        let value = format!("v = {}", raw)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut table| table.remove("v"))
+            .unwrap_or(toml::Value::String(raw));
+        Some((name, value))
+    }
+
+    fn get_opt<T: DeserializeOwned>(&mut self, key: &'static str) -> Option<T> {
+        self.known.insert(key);
+        let mut found = None;
+        if let Some(value) = self.file.get(key) {
+            match value.clone().try_into() {
+                Ok(parsed) => found = Some((parsed, value.clone(), Layer::File)),
+                Err(e) => self.warnings.push(format!("{} in config.toml: {}", key, e)),
+            }
+        }
+        if let Some((name, value)) = self.env_value(key) {
+            match value.clone().try_into() {
+                Ok(parsed) => found = Some((parsed, value, Layer::Env)),
+                Err(e) => self.warnings.push(format!("{}: {}", name, e)),
+            }
+        }
+        let (parsed, value, source) = found?;
+        self.sources.insert(key, Provenance { value, source });
+        Some(parsed)
+    }
+
+    fn get<T: DeserializeOwned>(&mut self, key: &'static str, default: impl Into<toml::Value>) -> T {
+        if let Some(parsed) = self.get_opt(key) {
+            return parsed;
+        }
+        let value = default.into();
+        let parsed = value.clone().try_into().expect("built-in default must parse");
+        self.sources.insert(key, Provenance { value, source: Layer::Default });
+        parsed
+    }
+}
+
+impl Settings {
+    /// `env` looks up an environment variable; std::env::var in practice.
+    pub fn resolve(file: &toml::Table, env: &dyn Fn(&str) -> Option<String>) -> Settings {
+        let mut r = Resolver { file, env, sources: BTreeMap::new(), warnings: Vec::new(), known: BTreeSet::new() };
+        Settings {
+            smart_case: r.get("smart_case", false),
+            color_mode: r.get("color_mode", "auto"),
+            theme: r.get("theme", "dark"),
+            gone_after: r.get("gone_after", 5),
+            show_snippets: r.get("show_snippets", "auto"),
+            search_snippets: r.get("search_snippets", false),
+            show_categories: r.get("show_categories", true),
+            show_detail: r.get("show_detail", true),
+            max_source_width: r.get("max_source_width", 30),
+            max_entries: r.get("max_entries", 5),
+            max_content_bytes: r.get("max_content_bytes", 256 * 1024),
+            control_socket: r.get("control_socket", false),
+            set_title: r.get("set_title", true),
+            browser_command: r.get_opt("browser_command"),
+            bulk_open: r.get("bulk_open", "tabs"),
+            refresh_interval_secs: r.get_opt("refresh_interval_secs"),
+            tick_rate_ms: r.get("tick_rate_ms", 250),
+            timeout_secs: r.get("timeout_secs", 15),
+            max_concurrent_fetches: r.get("max_concurrent_fetches", 8),
+            skip_duplicate_manual: r.get("skip_duplicate_manual", true),
+            newsboat_urls: r.get_opt("newsboat_urls"),
+            alert: r.get("alert", "none"),
+            alert_command: r.get_opt("alert_command"),
+            notifications: r.get("notifications", false),
+            sources: r.sources,
+            warnings: r.warnings,
+            known: r.known,
+        }
+    }
+
+    /// Whether `key` is one of the top-level options.
+    pub fn is_known(&self, key: &str) -> bool {
+        self.known.contains(key)
+    }
+
+    /// One `key = value  # layer` line per option.
+    pub fn to_toml(&self) -> String {
+        self.sources
+            .iter()
+            .map(|(key, p)| format!("{} = {}  # {}\n", key, p.value, p.source.name()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.sources).unwrap()
+    }
+}
+
+/// The process environment, for Settings::resolve.
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(file: &str, env: &[(&str, &str)]) -> Settings {
+        let file: toml::Table = file.parse().unwrap();
+        let env: BTreeMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Settings::resolve(&file, &|name| env.get(name).cloned())
+    }
+
+    fn source(settings: &Settings, key: &str) -> Option<Layer> {
+        settings.sources.get(key).map(|p| p.source)
+    }
+
+    #[test]
+    fn the_environment_beats_the_file_which_beats_the_default() {
+        let settings = resolve("gone_after = 3\nmax_entries = 10\n", &[("BR_MAX_ENTRIES", "20")]);
+        assert_eq!((settings.gone_after, source(&settings, "gone_after")), (3, Some(Layer::File)));
+        assert_eq!((settings.max_entries, source(&settings, "max_entries")), (20, Some(Layer::Env)));
+        assert_eq!((settings.tick_rate_ms, source(&settings, "tick_rate_ms")), (250, Some(Layer::Default)));
+        assert!(settings.warnings.is_empty());
+    }
+
+    #[test]
+    fn environment_strings_need_no_quotes() {
+        let settings = resolve("", &[("BR_BROWSER_COMMAND", "firefox --new-tab"), ("BR_SMART_CASE", "true")]);
+        assert_eq!(settings.browser_command.as_deref(), Some("firefox --new-tab"));
+        assert!(settings.smart_case);
+    }
+
+    #[test]
+    fn a_bad_value_warns_and_falls_back_a_layer() {
+        let settings = resolve("max_entries = \"many\"\ngone_after = 4\n", &[("BR_GONE_AFTER", "soon")]);
+        assert_eq!((settings.max_entries, source(&settings, "max_entries")), (5, Some(Layer::Default)));
+        assert_eq!((settings.gone_after, source(&settings, "gone_after")), (4, Some(Layer::File)));
+        assert_eq!(settings.warnings.len(), 2);
+        // In the order the options are resolved.
+        assert!(settings.warnings[0].starts_with("BR_GONE_AFTER: "));
+        assert!(settings.warnings[1].starts_with("max_entries in config.toml: "));
+    }
+
+    #[test]
+    fn unset_options_are_left_out_but_known() {
+        let settings = resolve("", &[]);
+        assert_eq!(settings.refresh_interval_secs, None);
+        assert!(!settings.sources.contains_key("refresh_interval_secs"));
+        assert!(settings.is_known("refresh_interval_secs"));
+        assert!(!settings.is_known("refres_interval"));
+        assert!(settings.to_toml().contains("gone_after = 5  # default\n"));
+    }
+}
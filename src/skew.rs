@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+
+/// Skew beyond this is warned about. Date headers only have one-second
+/// resolution and some servers are a little off themselves.
+pub const THRESHOLD_SECS: i64 = 5 * 60;
+
+/// The Date header of a response, if it has a valid one.
+pub fn server_date(response: &reqwest::Response) -> Option<DateTime<Utc>> {
+    let date = response.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(date).ok().map(|date| date.with_timezone(&Utc))
+}
+
+/// The median of the server-minus-local offsets seen in a refresh, in
+/// seconds. The median keeps one badly set server from skewing the rest.
+pub fn median(samples: &mut [i64]) -> Option<i64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let mid = samples.len() / 2;
+    Some(if samples.len().is_multiple_of(2) { (samples[mid - 1] + samples[mid]) / 2 } else { samples[mid] })
+}
+
+/// "system clock appears 37 minutes behind server time"
+pub fn describe(secs: i64) -> String {
+    let minutes = (secs.unsigned_abs() + 30) / 60;
+    let unit = if minutes == 1 { "minute" } else { "minutes" };
+    let direction = if secs > 0 { "behind" } else { "ahead of" };
+    format!("system clock appears {} {} {} server time", minutes, unit, direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_median_ignores_one_badly_set_server() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [2220, -3, 5]), Some(5));
+        assert_eq!(median(&mut [10, 2220, 20, 0]), Some(15));
+    }
+
+    #[test]
+    fn skew_is_described_in_whole_minutes() {
+        assert_eq!(describe(37 * 60 + 10), "system clock appears 37 minutes behind server time");
+        assert_eq!(describe(-60), "system clock appears 1 minute ahead of server time");
+        assert_eq!(describe(-89), "system clock appears 1 minute ahead of server time");
+    }
+}
@@ -0,0 +1,162 @@
+use crate::Item;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How articles are ordered in the list. Status rows (help, errors,
+/// progress) stay in arrival order ahead of the articles in every order but
+/// `Arrival`, and undated articles always come last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    Arrival,
+    #[default]
+    DateDesc,
+    DateAsc,
+    Source,
+    ByDay, // newest day first under day headers, newest first within a day
+}
+
+impl SortOrder {
+    /// The order ',' switches to.
+    pub fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Arrival => SortOrder::DateDesc,
+            SortOrder::DateDesc => SortOrder::DateAsc,
+            SortOrder::DateAsc => SortOrder::Source,
+            SortOrder::Source => SortOrder::ByDay,
+            SortOrder::ByDay => SortOrder::Arrival,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Arrival => "arrival",
+            SortOrder::DateDesc => "newest first",
+            SortOrder::DateAsc => "oldest first",
+            SortOrder::Source => "source",
+            SortOrder::ByDay => "by day",
+        }
+    }
+}
+
+// Dated before undated, whichever way the dates run.
+fn by_date(a: &Item, b: &Item, newest_first: bool) -> Ordering {
+    match (a.published, b.published) {
+        (Some(a), Some(b)) if newest_first => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Compares two articles. Ties are Equal so that the stable sort keeps them
+/// in arrival order and the list does not reshuffle between frames.
+pub fn compare(order: SortOrder, a: &Item, b: &Item) -> Ordering {
+    match order {
+        SortOrder::Arrival => Ordering::Equal,
+        SortOrder::DateDesc | SortOrder::ByDay => by_date(a, b, true),
+        SortOrder::DateAsc => by_date(a, b, false),
+        SortOrder::Source => {
+            let source = |item: &Item| item.source.as_deref().map(str::to_lowercase);
+            source(a).cmp(&source(b)).then_with(|| by_date(a, b, true))
+        }
+    }
+}
+
+/// Sorts indices into `items` in place.
+pub fn sort(order: SortOrder, indices: &mut [usize], items: &[Item]) {
+    indices.sort_by(|&a, &b| compare(order, &items[a], &items[b]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    const ORDERS: [SortOrder; 5] = [SortOrder::Arrival, SortOrder::DateDesc, SortOrder::DateAsc, SortOrder::Source, SortOrder::ByDay];
+
+    // Items with few distinct sources and dates, some missing, so that ties
+    // are common. A fixed LCG keeps the runs repeatable.
+    fn items(seed: u64, n: usize) -> Vec<Item> {
+        let mut state = seed;
+        let mut next = move |bound: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+        (0..n)
+            .map(|i| {
+                let mut item = Item::new(format!("item {}", i), None, false);
+                item.published = match next(4) {
+                    0 => None,
+                    _ => DateTime::<Utc>::from_timestamp(1_700_000_000 + next(5) as i64 * 3600, 0),
+                };
+                item.source = ["Blog", "blog", "News", "Zine"].get(next(5) as usize).map(|s| s.to_string());
+                item
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_order_is_a_total_order() {
+        let items = items(7, 40);
+        for order in ORDERS {
+            for a in &items {
+                assert_eq!(compare(order, a, a), Ordering::Equal);
+                for b in &items {
+                    assert_eq!(compare(order, a, b), compare(order, b, a).reverse());
+                    for c in &items {
+                        if compare(order, a, b).is_le() && compare(order, b, c).is_le() {
+                            assert!(compare(order, a, c).is_le(), "{:?} is not transitive", order);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ties_keep_arrival_order() {
+        for seed in 0..20 {
+            let items = items(seed, 60);
+            for order in ORDERS {
+                let mut indices: Vec<usize> = (0..items.len()).collect();
+                sort(order, &mut indices, &items);
+                for pair in indices.windows(2) {
+                    let (a, b) = (&items[pair[0]], &items[pair[1]]);
+                    assert!(compare(order, a, b).is_le());
+                    if compare(order, a, b).is_eq() {
+                        assert!(pair[0] < pair[1], "{:?} reordered a tie", order);
+                    }
+                }
+                // Sorting what is sorted changes nothing, so frames agree.
+                let again = indices.clone();
+                sort(order, &mut indices, &items);
+                assert_eq!(indices, again);
+            }
+        }
+    }
+
+    #[test]
+    fn undated_items_come_last() {
+        let items = items(3, 30);
+        for order in [SortOrder::DateDesc, SortOrder::DateAsc, SortOrder::ByDay] {
+            let mut indices: Vec<usize> = (0..items.len()).collect();
+            sort(order, &mut indices, &items);
+            let first_undated = indices.iter().position(|&i| items[i].published.is_none()).unwrap();
+            assert!(indices[first_undated..].iter().all(|&i| items[i].published.is_none()));
+        }
+    }
+
+    #[test]
+    fn the_orders_cycle_through_all_five() {
+        let mut order = SortOrder::default();
+        let mut seen = Vec::new();
+        for _ in 0..ORDERS.len() {
+            seen.push(order);
+            order = order.next();
+        }
+        assert_eq!(order, SortOrder::default());
+        assert!(ORDERS.iter().all(|order| seen.contains(order)));
+    }
+}
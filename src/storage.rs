@@ -0,0 +1,198 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Why a change is refused once the store is ephemeral, after what it was,
+/// e.g. "Bookmark not saved: ...".
+pub const NOT_SAVED: &str = "not saved: data directory is not writable";
+
+/// Where a Storage puts the bytes of a file: the disk in practice, and in
+/// tests something that fails on demand.
+pub trait Backend: Send + Sync {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+struct Disk;
+
+impl Backend for Disk {
+    fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+}
+
+/// A temporary file beside `path` to write it through, named after this
+/// process and a counter so that concurrent writers of the same file, in
+/// this br or another, never share one.
+pub fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = path.file_name().map_or_else(Default::default, |name| name.to_string_lossy());
+    path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Replaces `path` with what `write` puts in the temporary file it is given,
+/// by renaming that over it, so a reader never sees half a file and a
+/// failure part way (e.g. a full disk) leaves the old one intact.
+pub fn replace_with<T, E: From<io::Error>>(path: &Path, write: impl FnOnce(&Path) -> Result<T, E>) -> Result<T, E> {
+    let tmp = temp_path(path);
+    let result = write(&tmp).and_then(|value| std::fs::rename(&tmp, path).map(|_| value).map_err(E::from));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Replaces `path` with `contents`; see replace_with.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    replace_with(path, |tmp| std::fs::write(tmp, contents))
+}
+
+/// Writes app state under the data directory.
+///
+/// Once a write fails the store turns ephemeral: later writes are skipped
+/// instead of failing again on every refresh, and the UI says that nothing
+/// is being saved. Clones share that state.
+#[derive(Clone)]
+pub struct Storage {
+    ephemeral: Arc<AtomicBool>,
+    backend: Arc<dyn Backend>,
+}
+
+impl Default for Storage {
+    fn default() -> Storage {
+        Storage { ephemeral: Arc::default(), backend: Arc::new(Disk) }
+    }
+}
+
+impl Storage {
+    /// A store that never writes, for work whose results may be thrown away.
+    pub fn discarding() -> Storage {
+        Storage { ephemeral: Arc::new(AtomicBool::new(true)), ..Storage::default() }
+    }
+
+    #[cfg(test)]
+    pub fn with_backend(backend: Arc<dyn Backend>) -> Storage {
+        Storage { ephemeral: Arc::default(), backend }
+    }
+
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral.load(Ordering::Relaxed)
+    }
+
+    /// Checks up front that `dir` can be written to, creating it if needed.
+    pub fn probe(&self, dir: &Path) -> io::Result<()> {
+        let probe = dir.join(".write-test");
+        let result = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&probe, b""))
+            .and_then(|_| std::fs::remove_file(&probe));
+        if result.is_err() {
+            self.ephemeral.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Replaces `path` with `contents` like write_atomic, through the backend.
+    ///
+    /// Only the write that switches the store to ephemeral returns an error;
+    /// after that writes are skipped and return Ok.
+    pub async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if self.is_ephemeral() {
+            return Ok(());
+        }
+        let (backend, path, contents) = (self.backend.clone(), path.to_path_buf(), contents.to_vec());
+        let result = tokio::task::spawn_blocking(move || replace_with(&path, |tmp| backend.write_file(tmp, &contents)))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e)));
+        if result.is_err() && self.ephemeral.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use std::sync::atomic::AtomicUsize;
+
+    // Writes half of each file, then fails the way a full disk does.
+    #[derive(Default)]
+    struct FullDisk {
+        attempts: AtomicUsize,
+    }
+
+    impl Backend for FullDisk {
+        fn write_file(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.attempts.fetch_add(1, Ordering::Relaxed);
+            std::fs::write(path, &contents[..contents.len() / 2])?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "No space left on device"))
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failed_write_keeps_the_old_file_and_stops_writing() {
+        let dir = temp_dir("storage-full");
+        let path = dir.join("read.json");
+        std::fs::write(&path, "[\"old\"]").unwrap();
+        let disk = Arc::new(FullDisk::default());
+        let storage = Storage::with_backend(disk.clone());
+
+        let error = storage.write(&path, b"[\"new\", \"entries\"]").await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[\"old\"]");
+        assert!(storage.is_ephemeral());
+        // The half-written temporary file is gone too.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Later writes, from any clone, are skipped without another attempt.
+        assert!(storage.clone().write(&path, b"[]").await.is_ok());
+        assert_eq!(disk.attempts.load(Ordering::Relaxed), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[\"old\"]");
+    }
+
+    #[tokio::test]
+    async fn concurrent_writes_of_one_file_do_not_collide() {
+        let dir = temp_dir("storage-race");
+        let path = dir.join("cache.json");
+        let storage = Storage::default();
+        let writes: Vec<_> = (0..32)
+            .map(|i| {
+                let (storage, path) = (storage.clone(), path.clone());
+                tokio::spawn(async move { storage.write(&path, format!("{{\"write\": {}}}", i).as_bytes()).await })
+            })
+            .collect();
+        for write in writes {
+            write.await.unwrap().unwrap();
+        }
+        assert!(!storage.is_ephemeral());
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("{\"write\": "));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_failed_replace_leaves_the_old_file() {
+        let path = temp_dir("storage-replace").join("config.toml");
+        write_atomic(&path, b"old = 1\n").unwrap();
+        let result = replace_with(&path, |tmp| -> io::Result<()> {
+            std::fs::write(tmp, b"new")?;
+            Err(io::Error::other("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old = 1\n");
+        assert_eq!(std::fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn temporary_files_are_unique_and_beside_the_file() {
+        let path = Path::new("/data/br/cache.json");
+        let (a, b) = (temp_path(path), temp_path(path));
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), path.parent());
+        assert!(a.extension().is_some_and(|ext| ext == "tmp"));
+    }
+}
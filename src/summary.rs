@@ -0,0 +1,112 @@
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Deserialize;
+
+/// Terminals at least this wide show snippets with `show_snippets = "auto"`.
+pub const AUTO_WIDTH: u16 = 140;
+
+/// When to show the summary snippet after each title, from `show_snippets` in
+/// config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShowSnippets {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ShowSnippets {
+    pub fn enabled(self, width: u16) -> bool {
+        match self {
+            ShowSnippets::Auto => width > AUTO_WIDTH,
+            ShowSnippets::Always => true,
+            ShowSnippets::Never => false,
+        }
+    }
+}
+
+const BLOCKS: [&str; 12] = ["p", "div", "br", "li", "blockquote", "pre", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+// Elements whose text is not part of what is being read.
+const SKIPPED: [&str; 7] = ["script", "style", "noscript", "nav", "header", "footer", "aside"];
+
+/// An entry summary as plain text: markup stripped, one line per paragraph.
+pub fn plain_text(html: &str) -> Option<String> {
+    text_of(Html::parse_fragment(html).root_element())
+}
+
+/// The text of a web page's `<article>`, else its `<main>`, else its
+/// `<body>`, one line per paragraph, without scripts, styles or navigation.
+pub fn article_text(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    ["article", "main", "body"].into_iter().find_map(|name| {
+        let selector = Selector::parse(name).ok()?;
+        text_of(document.select(&selector).next()?)
+    })
+}
+
+fn text_of(root: ElementRef) -> Option<String> {
+    let mut text = String::new();
+    for node in root.descendants() {
+        match node.value() {
+            Node::Text(t) => {
+                let skipped = node.ancestors().any(|a| a.value().as_element().is_some_and(|e| SKIPPED.contains(&e.name())));
+                if !skipped {
+                    text.push_str(t);
+                }
+            }
+            Node::Element(e) if BLOCKS.contains(&e.name()) => text.push('\n'),
+            _ => {}
+        }
+    }
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| line.split_whitespace().filter(|word| !is_data_uri(word)).collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+// An inline image or file, e.g. "data:image/png;base64,iVBOR...", which
+// some feeds escape into the text and which is never worth reading.
+fn is_data_uri(word: &str) -> bool {
+    word.split(['"', '\'', '(', '='])
+        .any(|part| part.strip_prefix("data:").is_some_and(|rest| rest.contains(',')))
+}
+
+/// Cuts `text` to at most `max_bytes` at a char boundary and marks it
+/// "(truncated)". Returns the size it had when it was cut; a `max_bytes` of
+/// 0 leaves every text whole.
+pub fn cap(text: &mut String, max_bytes: usize) -> Option<usize> {
+    if max_bytes == 0 || text.len() <= max_bytes {
+        return None;
+    }
+    let size = text.len();
+    let end = (0..=max_bytes).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+    text.truncate(end);
+    text.push_str(" (truncated)");
+    Some(size)
+}
+
+/// The first sentence of a plain text summary, shown as the snippet.
+pub fn first_sentence(text: &str) -> String {
+    let text = text.lines().next().unwrap_or_default();
+    let end = text
+        .match_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&i| text[i..].starts_with(' '))
+        .unwrap_or(text.len());
+    text[..end].to_string()
+}
+
+/// Cuts `text` to `width` characters, ending it with "…" when shortened.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
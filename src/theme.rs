@@ -0,0 +1,303 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::{collections::BTreeMap, env};
+
+/// How many colors the terminal can show, from `color_mode` in config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum ColorMode {
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "16")]
+    Ansi16,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+impl ColorMode {
+    /// Resolves `Auto` from COLORTERM and TERM. Anything unrecognized is
+    /// assumed to handle 256 colors, which most terminal emulators do.
+    pub fn resolve(self) -> ColorMode {
+        if self != ColorMode::Auto {
+            return self;
+        }
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorMode::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term.ends_with("-direct") {
+            ColorMode::TrueColor
+        } else if term.contains("256color") {
+            ColorMode::Ansi256
+        } else if term.is_empty()
+            || term == "dumb"
+            || term == "linux"
+            || term == "ansi"
+            || term.starts_with("vt")
+            || term.starts_with("cons")
+        {
+            ColorMode::Ansi16
+        } else {
+            ColorMode::Ansi256
+        }
+    }
+}
+
+/// `theme` in config.toml: a preset name, or a `[theme]` table of colors
+/// with an optional `preset` to start from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ThemeConfig {
+    Preset(String),
+    Custom(BTreeMap<String, String>),
+}
+
+/// The colors the list and popups are drawn with.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub feed: Color,
+    pub manual: Color,
+    pub error: Color,
+    pub status: Color, // "Checking for updates..."
+    pub text: Color, // other rows that are not items
+    pub read: Color,
+    pub bookmarked: Color,
+    pub highlighted: Color, // by a rule
+    pub highlight_bg: Color, // the selected row
+    pub day: Color, // day headers
+    pub secondary: Color, // dates, authors and other details in popups
+    pub prompt: Color, // what is being typed, questions, notes and key names
+    pub info: Color, // the info panel
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        feed: Color::Cyan,
+        manual: Color::Yellow,
+        error: Color::Red,
+        status: Color::Magenta,
+        text: Color::White,
+        read: Color::Gray,
+        bookmarked: Color::LightYellow,
+        highlighted: Color::LightMagenta,
+        highlight_bg: Color::DarkGray,
+        day: Color::Blue,
+        secondary: Color::Gray,
+        prompt: Color::Yellow,
+        info: Color::Green,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        feed: Color::Blue,
+        manual: Color::Rgb(135, 95, 0),
+        error: Color::Red,
+        status: Color::Magenta,
+        text: Color::Reset,
+        read: Color::DarkGray,
+        bookmarked: Color::Rgb(175, 95, 0),
+        highlighted: Color::Magenta,
+        highlight_bg: Color::Rgb(208, 208, 208),
+        day: Color::Blue,
+        secondary: Color::DarkGray,
+        prompt: Color::Rgb(135, 95, 0),
+        info: Color::Rgb(0, 135, 0),
+    };
+
+    const COLORS: [&str; 13] = [
+        "feed", "manual", "error", "status", "text", "read", "bookmarked", "highlighted", "highlight_bg", "day", "secondary", "prompt", "info",
+    ];
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "feed" => &mut self.feed,
+            "manual" => &mut self.manual,
+            "error" => &mut self.error,
+            "status" => &mut self.status,
+            "text" => &mut self.text,
+            "read" => &mut self.read,
+            "bookmarked" => &mut self.bookmarked,
+            "highlighted" => &mut self.highlighted,
+            "highlight_bg" => &mut self.highlight_bg,
+            "day" => &mut self.day,
+            "secondary" => &mut self.secondary,
+            "prompt" => &mut self.prompt,
+            "info" => &mut self.info,
+            _ => return None,
+        })
+    }
+
+    /// The theme `config` describes. Colors are names ratatui knows, such as
+    /// "red" or "darkgray", "#rrggbb", or a 256-color index. Returns why each
+    /// entry that was not used was not.
+    pub fn resolve(config: &ThemeConfig) -> (Theme, Vec<String>) {
+        let mut errors = Vec::new();
+        let empty = BTreeMap::new();
+        let (preset, colors) = match config {
+            ThemeConfig::Preset(name) => (Some(name), &empty),
+            ThemeConfig::Custom(colors) => (colors.get("preset"), colors),
+        };
+        let mut theme = match preset.map(String::as_str) {
+            None | Some("dark") => Theme::DARK,
+            Some("light") => Theme::LIGHT,
+            Some(other) => {
+                errors.push(format!("theme \"{}\": not a preset (dark, light)", other));
+                Theme::DARK
+            }
+        };
+        for (name, value) in colors.iter().filter(|(name, _)| *name != "preset") {
+            let Some(color) = theme.color_mut(name) else {
+                errors.push(format!("[theme] {}: not a color br uses ({})", name, Theme::COLORS.join(", ")));
+                continue;
+            };
+            match parse_color(value) {
+                Some(parsed) => *color = parsed,
+                None => errors.push(format!("[theme] {} = \"{}\": not a color", name, value)),
+            }
+        }
+        (theme, errors)
+    }
+}
+
+// CSS names ratatui does not know, for `parse_color`.
+const CSS: [(&str, (u8, u8, u8)); 12] = [
+    ("orange", (255, 165, 0)),
+    ("purple", (128, 0, 128)),
+    ("pink", (255, 192, 203)),
+    ("brown", (165, 42, 42)),
+    ("teal", (0, 128, 128)),
+    ("navy", (0, 0, 128)),
+    ("olive", (128, 128, 0)),
+    ("maroon", (128, 0, 0)),
+    ("lime", (0, 255, 0)),
+    ("aqua", (0, 255, 255)),
+    ("fuchsia", (255, 0, 255)),
+    ("silver", (192, 192, 192)),
+];
+
+/// A color as config.toml writes it: a name ratatui knows, such as "red" or
+/// "darkgray", one of a few more CSS names such as "orange" or "teal",
+/// "#rrggbb" or "#rgb", or a 256-color index.
+pub fn parse_color(text: &str) -> Option<Color> {
+    if let Ok(color) = text.parse() {
+        return Some(color);
+    }
+    if let Some(&(_, (r, g, b))) = CSS.iter().find(|(name, _)| name.eq_ignore_ascii_case(text)) {
+        return Some(Color::Rgb(r, g, b));
+    }
+    // #f60 is #ff6600.
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 3)?;
+    let digits: Vec<u8> = hex.chars().map(|c| c.to_digit(16).map(|d| d as u8 * 17)).collect::<Option<_>>()?;
+    Some(Color::Rgb(digits[0], digits[1], digits[2]))
+}
+
+// xterm's default values for the 16 ANSI colors, used to find the closest
+// match for colors the terminal cannot show.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI.iter().min_by_key(|(_, ansi)| distance(rgb, *ansi)).unwrap().0
+}
+
+fn nearest_indexed((r, g, b): (u8, u8, u8)) -> Color {
+    let level = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap() as u8
+    };
+    Color::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+/// Turns configured colors and styles into ones the terminal can show.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    mode: ColorMode,
+    pub theme: Theme,
+}
+
+impl Palette {
+    pub fn new(mode: ColorMode, theme: Theme) -> Palette {
+        Palette { mode: mode.resolve(), theme }
+    }
+
+    fn is_limited(&self) -> bool {
+        self.mode == ColorMode::Ansi16
+    }
+
+    pub fn color(&self, color: Color) -> Color {
+        match (self.mode, color) {
+            (ColorMode::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi((r, g, b)),
+            (ColorMode::Ansi16, Color::Indexed(i)) => nearest_ansi(indexed_to_rgb(i)),
+            (ColorMode::Ansi256, Color::Rgb(r, g, b)) => nearest_indexed((r, g, b)),
+            _ => color,
+        }
+    }
+
+    pub fn fg(&self, color: Color) -> Style {
+        Style::default().fg(self.color(color))
+    }
+
+    /// The selected row. A background color may disappear on 16-color
+    /// consoles, so those get reverse video instead.
+    pub fn highlight(&self) -> Style {
+        if self.is_limited() {
+            Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+        } else {
+            Style::default().bg(self.color(self.theme.highlight_bg)).add_modifier(Modifier::BOLD)
+        }
+    }
+
+    /// Unread items. With few colors the feed color may match the read
+    /// color, so they are also made bold.
+    pub fn new_item(&self, color: Color) -> Style {
+        if self.is_limited() {
+            self.fg(color).add_modifier(Modifier::BOLD)
+        } else {
+            self.fg(color)
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// The `[translate]` section of config.toml: a LibreTranslate server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Backend {
+    pub url: String, // e.g. "http://localhost:5000"
+    pub api_key: Option<String>,
+    pub summaries: Option<bool>, // translate summaries for the peek popup as well, default false
+}
+
+/// An item's title, and summary if asked for, in the `target` language.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translated {
+    pub target: String,
+    pub language: Option<String>, // detected language of the original
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl Translated {
+    /// False when the original was already in the target language.
+    pub fn is_translation(&self) -> bool {
+        self.language.as_deref() != Some(self.target.as_str())
+    }
+}
+
+/// Keyed by item link, persisted as translations.json so each item is only
+/// sent to the server once.
+pub type Cache = Arc<Mutex<HashMap<String, Translated>>>;
+
+/// A missing or unreadable file is an empty cache.
+pub fn load(path: &Path) -> HashMap<String, Translated> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct Detected {
+    language: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+    #[serde(rename = "detectedLanguage")]
+    detected_language: Option<Detected>,
+}
+
+// One POST /translate. Returns the text and the detected source language.
+async fn request(http: &reqwest::Client, backend: &Backend, text: &str, target: &str) -> Result<(String, Option<String>), String> {
+    let mut body = json!({ "q": text, "source": "auto", "target": target, "format": "text" });
+    if let Some(key) = &backend.api_key {
+        body["api_key"] = json!(key);
+    }
+    let url = format!("{}/translate", backend.url.trim_end_matches('/'));
+    let response = http.post(&url).json(&body).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let response: Response = response.json().await.map_err(|e| e.to_string())?;
+    Ok((response.translated_text, response.detected_language.map(|d| d.language)))
+}
+
+/// The translation of an item into `target`, from the cache or the server.
+/// Summaries are only translated with `summaries = true`.
+pub async fn translate(http: &reqwest::Client, backend: &Backend, cache: &Cache, link: &str, title: &str, summary: Option<&str>, target: &str) -> Result<Translated, String> {
+    if let Some(cached) = cache.lock().unwrap().get(link).filter(|t| t.target == target) {
+        return Ok(cached.clone());
+    }
+    let (translated_title, language) = request(http, backend, title, target).await?;
+    let mut translated = Translated { target: target.to_string(), language, title: translated_title, summary: None };
+    if backend.summaries.unwrap_or(false)
+        && translated.is_translation()
+        && let Some(summary) = summary
+    {
+        translated.summary = Some(request(http, backend, summary, target).await?.0);
+    }
+    cache.lock().unwrap().insert(link.to_string(), translated.clone());
+    Ok(translated)
+}